@@ -0,0 +1,407 @@
+/// Anthropic Messages API backend for mode transformations (`llm.provider = "anthropic"`).
+///
+/// Unlike chat completions (see `llm_provider::ChatCompletions`), the system prompt is a
+/// top-level field and the key goes in `x-api-key`.
+use crate::http_client;
+use crate::llm::{parse_one_line, LlmOutput, TokenUsage};
+use crate::llm_provider::{Endpoint, LlmProvider, LlmRequest, OnDelta};
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::Emitter;
+use tokio_util::sync::CancellationToken;
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+/// Used when `llm.model` is not a Claude model (e.g. left over from another provider).
+const DEFAULT_MODEL: &str = "claude-haiku-4-5";
+const API_VERSION: &str = "2023-06-01";
+
+pub struct Anthropic {
+    /// Without the `/v1/...` suffix.
+    base_url: String,
+    /// None for a local proxy without a key.
+    api_key: Option<String>,
+    model: String,
+    timeout: Duration,
+}
+
+#[derive(Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    system: &'a str,
+    messages: Vec<Message<'a>>,
+    max_tokens: u32,
+    temperature: f32,
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+struct Usage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+/// `data:` payload of one SSE event (the `event:` line repeats `type` and is ignored).
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart { message: StartedMessage },
+    ContentBlockDelta { delta: Delta },
+    /// Carries the cumulative output token count.
+    MessageDelta {
+        #[serde(default)]
+        usage: Usage,
+    },
+    MessageStop,
+    Error { error: ApiError },
+    /// ping, content_block_start/stop…
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct StartedMessage {
+    #[serde(default)]
+    usage: Usage,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Delta {
+    TextDelta { text: String },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct ApiError {
+    #[serde(rename = "type")]
+    kind: String,
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: Usage,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text { text: String },
+    #[serde(other)]
+    Other,
+}
+
+/// Text and usage accumulated over a stream.
+#[derive(Debug, Default)]
+struct StreamState {
+    text: String,
+    usage: TokenUsage,
+    done: bool,
+}
+
+impl StreamState {
+    /// Apply one `data:` payload; returns true when it added text. Unknown or malformed
+    /// payloads are skipped, like in the chat completions stream.
+    fn apply(&mut self, data: &str) -> Result<bool, String> {
+        let Ok(event) = serde_json::from_str::<StreamEvent>(data) else {
+            return Ok(false);
+        };
+        match event {
+            StreamEvent::MessageStart { message } => {
+                self.usage.input = message.usage.input_tokens;
+                self.usage.output = message.usage.output_tokens;
+            }
+            StreamEvent::ContentBlockDelta {
+                delta: Delta::TextDelta { text },
+            } => {
+                self.text.push_str(&text);
+                return Ok(true);
+            }
+            StreamEvent::MessageDelta { usage } => self.usage.output = usage.output_tokens,
+            StreamEvent::MessageStop => self.done = true,
+            StreamEvent::Error { error } => {
+                return Err(format!("Anthropic API error ({}): {}", error.kind, error.message))
+            }
+            StreamEvent::ContentBlockDelta { .. } | StreamEvent::Other => {}
+        }
+        Ok(false)
+    }
+
+    fn output(self, model: &str) -> LlmOutput {
+        LlmOutput {
+            text: self.text.trim().to_string(),
+            provider: "anthropic".to_string(),
+            model: model.to_string(),
+            usage: Some(self.usage),
+        }
+    }
+}
+
+impl Anthropic {
+    fn request_body<'a>(&'a self, request: &LlmRequest<'a>, stream: bool) -> MessagesRequest<'a> {
+        MessagesRequest {
+            model: &self.model,
+            system: request.system,
+            messages: vec![Message {
+                role: "user",
+                content: request.text,
+            }],
+            max_tokens: request.max_tokens,
+            // Anthropic accepts 0.0–1.0 only
+            temperature: request.temperature.clamp(0.0, 1.0),
+            stream,
+        }
+    }
+
+    async fn send(&self, request: &LlmRequest<'_>, stream: bool) -> Result<reqwest::Response, String> {
+        let mut builder = http_client::post(&self.base_url, "/v1/messages", None)?
+            .header("Content-Type", "application/json")
+            .header("anthropic-version", API_VERSION);
+        if let Some(key) = &self.api_key {
+            builder = builder.header("x-api-key", key);
+        }
+        let resp = builder
+            .json(&self.request_body(request, stream))
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(|e| format!("Erreur requête API Anthropic: {}", e))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(format!("Anthropic API error {}: {}", status, body));
+        }
+        Ok(resp)
+    }
+}
+
+impl LlmProvider for Anthropic {
+    fn id(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn name(&self) -> &'static str {
+        "Anthropic"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a LlmRequest<'a>,
+        app: &'a tauri::AppHandle,
+        cancel: CancellationToken,
+        on_delta: OnDelta<'a>,
+    ) -> BoxFuture<'a, Result<LlmOutput, String>> {
+        Box::pin(async move {
+            let resp = self.send(request, true).await?;
+            let mut stream = resp.bytes_stream();
+            let mut buf = Vec::<u8>::new();
+            let mut state = StreamState::default();
+
+            loop {
+                let chunk_result = tokio::select! {
+                    _ = cancel.cancelled() => return Err("Annulé".to_string()),
+                    next = stream.next() => next,
+                };
+                let Some(chunk_result) = chunk_result else {
+                    break;
+                };
+                let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
+                buf.extend_from_slice(&chunk);
+
+                while let Some((line, rest)) = parse_one_line(&buf) {
+                    buf = rest;
+                    let Some(data) = line.strip_prefix("data: ") else {
+                        continue;
+                    };
+                    let before = state.text.len();
+                    if state.apply(data.trim())? {
+                        on_delta(&state.text[before..]);
+                        let _ = app.emit("llm_chunk", state.text.trim().to_string());
+                    }
+                    if state.done {
+                        return Ok(state.output(&self.model));
+                    }
+                }
+            }
+
+            Ok(state.output(&self.model))
+        })
+    }
+
+    fn complete<'a>(&'a self, request: &'a LlmRequest<'a>) -> BoxFuture<'a, Result<LlmOutput, String>> {
+        Box::pin(async move {
+            let parsed: MessagesResponse = self
+                .send(request, false)
+                .await?
+                .json()
+                .await
+                .map_err(|e| format!("Réponse API invalide: {}", e))?;
+            let text: String = parsed
+                .content
+                .into_iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Text { text } => Some(text),
+                    ContentBlock::Other => None,
+                })
+                .collect();
+            Ok(LlmOutput {
+                text: text.trim().to_string(),
+                provider: "anthropic".to_string(),
+                model: self.model.clone(),
+                usage: Some(TokenUsage {
+                    input: parsed.usage.input_tokens,
+                    output: parsed.usage.output_tokens,
+                    estimated: false,
+                }),
+            })
+        })
+    }
+}
+
+/// Registry builder (see `llm_provider`). Never falls back to another vendor's key: it
+/// would be sent to Anthropic.
+/// The configured model when it is a Claude one, else `DEFAULT_MODEL` (a model left over
+/// from another provider).
+fn claude_model(model: Option<&str>) -> String {
+    model
+        .filter(|m| m.starts_with("claude"))
+        .unwrap_or(DEFAULT_MODEL)
+        .to_string()
+}
+
+pub fn build(endpoint: &Endpoint) -> Result<Box<dyn LlmProvider>, String> {
+    let base_url = endpoint.resolve_base_url(DEFAULT_BASE_URL)?;
+    let key = crate::secrets::get_own_key_for_provider("anthropic")
+        .map_err(|_| "No Anthropic API key found. Add an Anthropic key (sk-ant-...) in Settings → API Keys.".to_string());
+    let model = claude_model(endpoint.model.as_deref());
+    Ok(Box::new(Anthropic {
+        api_key: http_client::key_unless_local(key, &base_url)?,
+        base_url,
+        model,
+        timeout: endpoint.timeout,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider() -> Anthropic {
+        Anthropic {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            api_key: Some("sk-ant-test".to_string()),
+            model: DEFAULT_MODEL.to_string(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    fn request(system: &str, temperature: f32) -> LlmRequest<'_> {
+        LlmRequest {
+            system,
+            text: "bonjour",
+            temperature,
+            max_tokens: 1024,
+        }
+    }
+
+    // ── request_body ────────────────────────────────────────────────
+
+    #[test]
+    fn test_request_body_system_is_top_level() {
+        let json = serde_json::to_value(provider().request_body(&request("Réécris.", 0.3), true)).unwrap();
+        assert_eq!(json["system"], "Réécris.");
+        assert_eq!(json["stream"], true);
+        let messages = json["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0]["role"], "user");
+        assert_eq!(messages[0]["content"], "bonjour");
+    }
+
+    #[test]
+    fn test_request_body_omits_empty_system_and_clamps_temperature() {
+        let json = serde_json::to_value(provider().request_body(&request("", 1.7), false)).unwrap();
+        assert!(json.get("system").is_none());
+        assert_eq!(json["temperature"], 1.0);
+    }
+
+    // ── claude_model ────────────────────────────────────────────────
+
+    #[test]
+    fn test_claude_model_keeps_claude_models_only() {
+        assert_eq!(claude_model(Some("claude-sonnet-4-5")), "claude-sonnet-4-5");
+        // Left over from another provider
+        assert_eq!(claude_model(Some("gpt-4o-mini")), DEFAULT_MODEL);
+        assert_eq!(claude_model(None), DEFAULT_MODEL);
+    }
+
+    // ── StreamState ─────────────────────────────────────────────────
+
+    #[test]
+    fn test_stream_state_accumulates_text_and_usage() {
+        let events = [
+            r#"{"type":"message_start","message":{"id":"msg_1","model":"claude-haiku-4-5","usage":{"input_tokens":25,"output_tokens":1}}}"#,
+            r#"{"type":"content_block_start","index":0,"content_block":{"type":"text","text":""}}"#,
+            r#"{"type":"ping"}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"Bon"}}"#,
+            r#"{"type":"content_block_delta","index":0,"delta":{"type":"text_delta","text":"jour."}}"#,
+            r#"{"type":"content_block_stop","index":0}"#,
+            r#"{"type":"message_delta","delta":{"stop_reason":"end_turn"},"usage":{"output_tokens":12}}"#,
+            r#"{"type":"message_stop"}"#,
+        ];
+        let mut state = StreamState::default();
+        let added: Vec<bool> = events.iter().map(|e| state.apply(e).unwrap()).collect();
+        assert_eq!(added, vec![false, false, false, true, true, false, false, false]);
+        assert!(state.done);
+        let output = state.output(DEFAULT_MODEL);
+        assert_eq!(output.text, "Bonjour.");
+        assert_eq!(output.usage, Some(TokenUsage { input: 25, output: 12, estimated: false }));
+    }
+
+    #[test]
+    fn test_stream_state_error_event() {
+        let mut state = StreamState::default();
+        let err = state
+            .apply(r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#)
+            .unwrap_err();
+        assert!(err.contains("overloaded_error"));
+    }
+
+    #[test]
+    fn test_stream_state_skips_malformed_and_non_text_deltas() {
+        let mut state = StreamState::default();
+        assert!(!state.apply("not json").unwrap());
+        assert!(!state
+            .apply(r#"{"type":"content_block_delta","index":0,"delta":{"type":"input_json_delta","partial_json":"{"}}"#)
+            .unwrap());
+        assert!(state.text.is_empty());
+    }
+
+    // ── MessagesResponse ────────────────────────────────────────────
+
+    #[test]
+    fn test_messages_response_deserialization() {
+        let json = r#"{"id":"msg_1","type":"message","role":"assistant","content":[{"type":"text","text":"Prompt amélioré"}],"usage":{"input_tokens":40,"output_tokens":9}}"#;
+        let parsed: MessagesResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.usage, Usage { input_tokens: 40, output_tokens: 9 });
+        assert!(matches!(&parsed.content[0], ContentBlock::Text { text } if text == "Prompt amélioré"));
+    }
+}
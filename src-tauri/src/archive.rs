@@ -0,0 +1,322 @@
+/// Archive locale des enregistrements (opt-in) : un FLAC 16 kHz mono par prise + index JSON,
+/// rétention par âge, nombre et taille. Sert à `retranscribe_recording`.
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::Manager;
+
+const ARCHIVE_DIRNAME: &str = "recordings";
+const INDEX_FILENAME: &str = "index.json";
+
+/// Serializes index read-modify-write cycles (recordings are stored from short-lived threads).
+static ARCHIVE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingMeta {
+    pub id: String,
+    /// Unix timestamp (seconds) of the end of the recording.
+    pub created_at: i64,
+    pub duration_secs: f64,
+    /// Input device actually used (may be the default after a fallback).
+    pub device: Option<String>,
+    /// Mode active when the take was recorded.
+    pub mode: Option<String>,
+    pub provider: String,
+    pub language: Option<String>,
+    pub size_bytes: u64,
+}
+
+impl RecordingMeta {
+    pub fn new(
+        duration_secs: f64,
+        device: Option<String>,
+        mode: Option<String>,
+        provider: String,
+        language: Option<String>,
+    ) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            created_at: chrono::Utc::now().timestamp(),
+            duration_secs,
+            device,
+            mode,
+            provider,
+            language,
+            size_bytes: 0,
+        }
+    }
+
+    fn file_name(&self) -> String {
+        format!("{}.flac", self.id)
+    }
+}
+
+/// Limits applied after each new recording; `None` disables that axis.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RetentionPolicy {
+    pub max_age_days: Option<u32>,
+    pub max_count: Option<u32>,
+    pub max_size_bytes: Option<u64>,
+}
+
+impl From<&crate::preferences::RecordingPrefs> for RetentionPolicy {
+    fn from(prefs: &crate::preferences::RecordingPrefs) -> Self {
+        Self {
+            max_age_days: prefs.archive_max_age_days,
+            max_count: prefs.archive_max_count,
+            max_size_bytes: prefs.archive_max_size_mb.map(|mb| mb as u64 * 1024 * 1024),
+        }
+    }
+}
+
+/// Split entries into (kept, expired), oldest first in both. Age is checked first, then
+/// the oldest recordings go until both the count and the total size fit.
+fn apply_retention(
+    mut entries: Vec<RecordingMeta>,
+    policy: &RetentionPolicy,
+    now: i64,
+) -> (Vec<RecordingMeta>, Vec<RecordingMeta>) {
+    entries.sort_by_key(|e| e.created_at);
+    let mut expired = Vec::new();
+    if let Some(days) = policy.max_age_days {
+        let cutoff = now - days as i64 * 86_400;
+        let (old, recent): (Vec<_>, Vec<_>) = entries.into_iter().partition(|e| e.created_at < cutoff);
+        expired.extend(old);
+        entries = recent;
+    }
+    let mut total: u64 = entries.iter().map(|e| e.size_bytes).sum();
+    let mut first_kept = 0;
+    for e in &entries {
+        let over_count = policy
+            .max_count
+            .is_some_and(|max| entries.len() - first_kept > max as usize);
+        let over_size = policy.max_size_bytes.is_some_and(|max| total > max);
+        if !over_count && !over_size {
+            break;
+        }
+        total -= e.size_bytes;
+        first_kept += 1;
+    }
+    expired.extend(entries.drain(..first_kept));
+    (entries, expired)
+}
+
+fn archive_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())
+        .map(|p| p.join(ARCHIVE_DIRNAME))
+}
+
+fn load_index(dir: &Path) -> Vec<RecordingMeta> {
+    std::fs::read_to_string(dir.join(INDEX_FILENAME))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_index(dir: &Path, entries: &[RecordingMeta]) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    std::fs::write(
+        dir.join(INDEX_FILENAME),
+        serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?,
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn store_in(
+    dir: &Path,
+    samples: &[f32],
+    sample_rate: u32,
+    mut meta: RecordingMeta,
+    policy: &RetentionPolicy,
+) -> Result<RecordingMeta, String> {
+    let bytes = crate::audio::write_flac_to_bytes(samples, sample_rate)?;
+    meta.size_bytes = bytes.len() as u64;
+    let _guard = ARCHIVE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(meta.file_name()), &bytes).map_err(|e| e.to_string())?;
+    let mut entries = load_index(dir);
+    entries.push(meta.clone());
+    let (kept, expired) = apply_retention(entries, policy, meta.created_at);
+    for e in &expired {
+        let _ = std::fs::remove_file(dir.join(e.file_name()));
+    }
+    save_index(dir, &kept)?;
+    Ok(meta)
+}
+
+fn delete_in(dir: &Path, id: &str) -> Result<(), String> {
+    let _guard = ARCHIVE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries = load_index(dir);
+    let pos = entries
+        .iter()
+        .position(|e| e.id == id)
+        .ok_or_else(|| format!("Enregistrement introuvable : {}", id))?;
+    let removed = entries.remove(pos);
+    let _ = std::fs::remove_file(dir.join(removed.file_name()));
+    save_index(dir, &entries)
+}
+
+/// Only ids present in the index resolve to a path (no traversal through the id).
+fn path_in(dir: &Path, id: &str) -> Result<PathBuf, String> {
+    let entry = load_index(dir)
+        .into_iter()
+        .find(|e| e.id == id)
+        .ok_or_else(|| format!("Enregistrement introuvable : {}", id))?;
+    let path = dir.join(entry.file_name());
+    if path.exists() {
+        Ok(path)
+    } else {
+        Err(format!("Fichier audio manquant pour l'enregistrement {}", id))
+    }
+}
+
+/// Store a finished take (16 kHz mono) and apply the retention settings.
+pub fn store(
+    app: &tauri::AppHandle,
+    samples: &[f32],
+    sample_rate: u32,
+    meta: RecordingMeta,
+) -> Result<RecordingMeta, String> {
+    let prefs = crate::preferences::get_preferences(app).unwrap_or_default();
+    let policy = RetentionPolicy::from(&prefs.recording);
+    store_in(&archive_dir(app)?, samples, sample_rate, meta, &policy)
+}
+
+/// Archived recordings, newest first.
+pub fn list_recordings(app: &tauri::AppHandle) -> Result<Vec<RecordingMeta>, String> {
+    let mut entries = load_index(&archive_dir(app)?);
+    entries.sort_by_key(|e| std::cmp::Reverse(e.created_at));
+    Ok(entries)
+}
+
+pub fn delete_recording(app: &tauri::AppHandle, id: &str) -> Result<(), String> {
+    delete_in(&archive_dir(app)?, id)
+}
+
+pub fn recording_path(app: &tauri::AppHandle, id: &str) -> Result<PathBuf, String> {
+    path_in(&archive_dir(app)?, id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(id: &str, created_at: i64, size_bytes: u64) -> RecordingMeta {
+        RecordingMeta {
+            id: id.to_string(),
+            created_at,
+            duration_secs: 1.0,
+            device: None,
+            mode: None,
+            provider: "openai".to_string(),
+            language: Some("fr".to_string()),
+            size_bytes,
+        }
+    }
+
+    fn ids(entries: &[RecordingMeta]) -> Vec<&str> {
+        entries.iter().map(|e| e.id.as_str()).collect()
+    }
+
+    // ── apply_retention ─────────────────────────────────────────────
+
+    #[test]
+    fn test_retention_no_limits_keeps_all() {
+        let entries = vec![meta("b", 20, 10), meta("a", 10, 10)];
+        let (kept, expired) = apply_retention(entries, &RetentionPolicy::default(), 100);
+        assert_eq!(ids(&kept), vec!["a", "b"]);
+        assert!(expired.is_empty());
+    }
+
+    #[test]
+    fn test_retention_by_age() {
+        let now = 10 * 86_400;
+        let entries = vec![meta("old", 0, 10), meta("new", now - 86_400, 10)];
+        let policy = RetentionPolicy { max_age_days: Some(2), ..Default::default() };
+        let (kept, expired) = apply_retention(entries, &policy, now);
+        assert_eq!(ids(&kept), vec!["new"]);
+        assert_eq!(ids(&expired), vec!["old"]);
+    }
+
+    #[test]
+    fn test_retention_by_count_drops_oldest() {
+        let entries = vec![meta("c", 30, 1), meta("a", 10, 1), meta("b", 20, 1)];
+        let policy = RetentionPolicy { max_count: Some(2), ..Default::default() };
+        let (kept, expired) = apply_retention(entries, &policy, 100);
+        assert_eq!(ids(&kept), vec!["b", "c"]);
+        assert_eq!(ids(&expired), vec!["a"]);
+    }
+
+    #[test]
+    fn test_retention_by_size_drops_oldest() {
+        let entries = vec![meta("a", 10, 400), meta("b", 20, 400), meta("c", 30, 400)];
+        let policy = RetentionPolicy { max_size_bytes: Some(900), ..Default::default() };
+        let (kept, expired) = apply_retention(entries, &policy, 100);
+        assert_eq!(ids(&kept), vec!["b", "c"]);
+        assert_eq!(ids(&expired), vec!["a"]);
+    }
+
+    #[test]
+    fn test_retention_size_can_drop_newest_too_large() {
+        let entries = vec![meta("a", 10, 10), meta("huge", 20, 1000)];
+        let policy = RetentionPolicy { max_size_bytes: Some(100), ..Default::default() };
+        let (kept, _) = apply_retention(entries, &policy, 100);
+        assert!(kept.is_empty());
+    }
+
+    #[test]
+    fn test_retention_policy_from_prefs() {
+        let prefs = crate::preferences::RecordingPrefs {
+            archive_max_size_mb: Some(2),
+            ..Default::default()
+        };
+        let policy = RetentionPolicy::from(&prefs);
+        assert_eq!(policy.max_size_bytes, Some(2 * 1024 * 1024));
+        assert_eq!(policy.max_age_days, Some(30));
+    }
+
+    // ── store / delete / path (with tempdir) ────────────────────────
+
+    #[test]
+    fn test_store_writes_flac_and_index() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join(ARCHIVE_DIRNAME);
+        let stored = store_in(&dir, &[0.1; 1600], 16000, meta("x", 100, 0), &RetentionPolicy::default()).unwrap();
+        assert!(stored.size_bytes > 0);
+        let path = path_in(&dir, "x").unwrap();
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), stored.size_bytes);
+        assert_eq!(load_index(&dir), vec![stored]);
+    }
+
+    #[test]
+    fn test_store_applies_retention_and_removes_files() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        let policy = RetentionPolicy { max_count: Some(1), ..Default::default() };
+        store_in(&dir, &[0.0; 160], 16000, meta("first", 100, 0), &policy).unwrap();
+        store_in(&dir, &[0.0; 160], 16000, meta("second", 200, 0), &policy).unwrap();
+        assert_eq!(ids(&load_index(&dir)), vec!["second"]);
+        assert!(!dir.join("first.flac").exists());
+        assert!(path_in(&dir, "first").is_err());
+    }
+
+    #[test]
+    fn test_delete_removes_entry_and_file() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().to_path_buf();
+        store_in(&dir, &[0.0; 160], 16000, meta("x", 100, 0), &RetentionPolicy::default()).unwrap();
+        delete_in(&dir, "x").unwrap();
+        assert!(load_index(&dir).is_empty());
+        assert!(!dir.join("x.flac").exists());
+        assert!(delete_in(&dir, "x").is_err());
+    }
+
+    #[test]
+    fn test_path_rejects_unknown_id() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(path_in(tmp.path(), "../preferences").is_err());
+    }
+}
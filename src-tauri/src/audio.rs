@@ -1,7 +1,7 @@
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::Sample;
 use std::io::Write;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex, Once};
 use tauri::Emitter;
@@ -58,36 +58,103 @@ pub fn list_input_devices() -> Result<Vec<AudioInputDevice>, String> {
     Ok(out)
 }
 
+/// How often the device watcher re-enumerates input devices.
+const DEVICE_POLL_MS: u64 = 2000;
+
+/// True when a device appeared or disappeared between two enumerations (order is ignored).
+fn device_list_changed(previous: &[String], current: &[String]) -> bool {
+    let mut previous = previous.to_vec();
+    let mut current = current.to_vec();
+    previous.sort();
+    current.sort();
+    previous != current
+}
+
+/// Hot-plug watcher: polls the input device list, emits `input_devices_changed` (the tray
+/// rebuilds its microphone submenu) and tells the audio worker, which fails over when the
+/// device it records from is gone.
+fn watch_input_devices(app: tauri::AppHandle) {
+    let mut known: Vec<String> = list_input_devices()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|d| d.id)
+        .collect();
+    loop {
+        std::thread::sleep(std::time::Duration::from_millis(DEVICE_POLL_MS));
+        let Ok(devices) = list_input_devices() else {
+            continue;
+        };
+        let ids: Vec<String> = devices.iter().map(|d| d.id.clone()).collect();
+        if !device_list_changed(&known, &ids) {
+            continue;
+        }
+        #[cfg(debug_assertions)]
+        eprintln!("[audio] input devices changed: {:?}", ids);
+        let _ = app.emit("input_devices_changed", &devices);
+        if let Some(state) = app.try_state::<RecorderState>() {
+            state.send(AudioCommand::DevicesChanged(ids.clone()));
+        }
+        known = ids;
+    }
+}
+
 /// Max WAV file size for Whisper API (25 MB). We target 24 MB to leave margin.
-/// PCM 16-bit mono = 2 bytes/sample, so max_samples = 24_000_000 / 2 = 12_000_000.
-/// At 48 kHz this gives ~4 min 10s, at 16 kHz ~12 min 30s.
+/// PCM 16-bit mono at 16 kHz = 2 bytes/sample, so one upload holds at most
+/// 12_000_000 samples, i.e. ~12 min 30s. Longer recordings are split into segments.
 const MAX_WAV_BYTES: usize = 24_000_000;
 
-/// Compute max samples based on actual device sample rate (respects Whisper's 25 MB limit).
-fn max_samples_for_rate(_sample_rate: u32) -> usize {
-    // PCM 16-bit = 2 bytes per sample → fixed limit regardless of rate
-    MAX_WAV_BYTES / 2
+/// Whisper works on 16 kHz mono internally; anything more is wasted upload.
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+
+/// Hard ceiling on a single recording. Not an API limit (segments take care of that),
+/// just a bound on capture memory: 30 min at 48 kHz is ~350 MB of f32 samples.
+const MAX_RECORDING_SECS: u64 = 30 * 60;
+
+/// Compute max native-rate samples to capture before auto-stopping.
+fn max_samples_for_rate(sample_rate: u32) -> usize {
+    (MAX_RECORDING_SECS * sample_rate.max(1) as u64) as usize
 }
 
 #[derive(Clone, serde::Serialize)]
-struct TranscriptionReadyPayload {
+pub struct TranscriptionReadyPayload {
     output: String,
     thoughts: Option<String>,
     mode: Option<String>,
     pasted: bool,
+    /// ISO 639-1 code of the dictation (detected, or the configured one).
+    language: Option<String>,
+    /// Wall time of each stage since the key was released, in order, then "total".
+    timings: Vec<crate::usage::StageTiming>,
 }
 
 pub enum AudioCommand {
     Start,
     Stop,
+    /// Stop appending to the buffer without ending the take.
+    Pause,
+    /// Continue a paused take into the same buffer.
+    Resume,
+    /// Recording preferences changed: open or close the always-warm stream accordingly.
+    SyncWarm,
+    /// The input stream failed mid-recording: reopen the preferred (or default) device and
+    /// keep appending to the same buffer.
+    Reopen,
+    /// The set of input devices changed (hot-plug); carries the current device ids.
+    DevicesChanged(Vec<String>),
 }
 
 pub struct RecorderState {
     cmd_tx: Mutex<Option<mpsc::Sender<AudioCommand>>>,
     is_capturing: AtomicBool,
+    /// True while the current take is paused (still capturing, nothing appended).
+    is_paused: AtomicBool,
     /// Bundle ID of the app that was frontmost when recording started.
     /// Used to reactivate it before auto-paste (Cmd+V).
     previous_app: Mutex<Option<String>>,
+    /// Text before the cursor in the focused field at recording start, when
+    /// `transcription.cursor_context` is on; `Some("")` for an empty field. Read on a
+    /// background thread, so it may still be `None` in the first moments of the take.
+    cursor_context: Arc<Mutex<Option<String>>>,
 }
 
 impl Default for RecorderState {
@@ -95,7 +162,9 @@ impl Default for RecorderState {
         Self {
             cmd_tx: Mutex::new(None),
             is_capturing: AtomicBool::new(false),
+            is_paused: AtomicBool::new(false),
             previous_app: Mutex::new(None),
+            cursor_context: Arc::new(Mutex::new(None)),
         }
     }
 }
@@ -105,6 +174,10 @@ impl RecorderState {
         self.is_capturing.load(Ordering::SeqCst)
     }
 
+    pub fn is_paused(&self) -> bool {
+        self.is_paused.load(Ordering::SeqCst)
+    }
+
     /// Utilisé par le worker quand start_stream échoue pour réaligner l’état.
     pub fn set_capturing(&self, value: bool) {
         self.is_capturing.store(value, Ordering::SeqCst);
@@ -115,13 +188,29 @@ impl RecorderState {
         self.previous_app.lock().ok().and_then(|mut g| g.take())
     }
 
+    /// App that was frontmost when the current take started, without clearing it.
+    pub fn previous_app(&self) -> Option<String> {
+        self.previous_app.lock().ok().and_then(|g| g.clone())
+    }
+
+    /// Text before the cursor when the current take started (see `whisper_prompt`).
+    pub fn cursor_context(&self) -> Option<String> {
+        self.cursor_context.lock().ok().and_then(|g| g.clone())
+    }
+
     /// Pre-spawn the audio worker thread so the first recording starts instantly.
     /// Call this once from app setup.
     pub fn warmup(&self, app: tauri::AppHandle) {
         let mut guard = self.cmd_tx.lock().unwrap_or_else(|e| e.into_inner());
         if guard.is_none() {
             let (tx, rx) = mpsc::channel();
-            std::thread::spawn(move || run_audio_worker(rx, app));
+            std::thread::spawn({
+                let app = app.clone();
+                move || run_audio_worker(rx, app)
+            });
+            std::thread::spawn(move || watch_input_devices(app));
+            // Always-warm mode: open the stream now so the first recording has pre-roll too.
+            let _ = tx.send(AudioCommand::SyncWarm);
             *guard = Some(tx);
         }
     }
@@ -130,6 +219,7 @@ impl RecorderState {
         if self.is_capturing.swap(true, Ordering::SeqCst) {
             return Ok(());
         }
+        self.is_paused.store(false, Ordering::SeqCst);
         // Send Start command FIRST for minimum latency — the stream opens immediately.
         let mut guard = self.cmd_tx.lock().map_err(|e| e.to_string())?;
         if guard.is_none() {
@@ -156,13 +246,73 @@ impl RecorderState {
                 }
             }
         }
+        // AX calls into the target app can be slow: never hold up the stream for them.
+        if let Ok(mut context) = self.cursor_context.lock() {
+            *context = None;
+        }
+        let cursor_context = self.cursor_context.clone();
+        std::thread::spawn(move || {
+            let prefs = crate::preferences::get_preferences(&app).unwrap_or_default();
+            if !prefs.transcription.cursor_context {
+                return;
+            }
+            let text = crate::clipboard::text_before_cursor(crate::whisper_prompt::PROMPT_TAIL_CHARS);
+            if let Ok(mut context) = cursor_context.lock() {
+                *context = text;
+            }
+        });
         Ok(())
     }
 
+    /// Ask the worker to re-apply recording preferences (always-warm mode, input device).
+    /// No-op while recording; the next Start picks the new settings up anyway.
+    pub fn sync_warm(&self) {
+        self.send(AudioCommand::SyncWarm);
+    }
+
+    /// Ask the worker to reopen the input stream after a device failure (recording only).
+    fn reopen_stream(&self) {
+        self.send(AudioCommand::Reopen);
+    }
+
+    fn send(&self, cmd: AudioCommand) {
+        if let Ok(guard) = self.cmd_tx.lock() {
+            if let Some(ref tx) = *guard {
+                let _ = tx.send(cmd);
+            }
+        }
+    }
+
+    /// Pause the current take; the buffer is kept and paused time does not count toward
+    /// the recording cap. Returns false when there was nothing to pause.
+    pub fn pause_capture(&self) -> Result<bool, String> {
+        if !self.is_capturing() || self.is_paused.swap(true, Ordering::SeqCst) {
+            return Ok(false);
+        }
+        let guard = self.cmd_tx.lock().map_err(|e| e.to_string())?;
+        if let Some(ref tx) = *guard {
+            tx.send(AudioCommand::Pause).map_err(|e| e.to_string())?;
+        }
+        Ok(true)
+    }
+
+    /// Resume a paused take. Returns false when the take was not paused.
+    pub fn resume_capture(&self) -> Result<bool, String> {
+        if !self.is_capturing() || !self.is_paused.swap(false, Ordering::SeqCst) {
+            return Ok(false);
+        }
+        let guard = self.cmd_tx.lock().map_err(|e| e.to_string())?;
+        if let Some(ref tx) = *guard {
+            tx.send(AudioCommand::Resume).map_err(|e| e.to_string())?;
+        }
+        Ok(true)
+    }
+
     pub fn stop_capture(&self) -> Result<(), String> {
         if !self.is_capturing.swap(false, Ordering::SeqCst) {
             return Ok(());
         }
+        self.is_paused.store(false, Ordering::SeqCst);
         let guard = self.cmd_tx.lock().map_err(|e| e.to_string())?;
         if let Some(ref tx) = *guard {
             tx.send(AudioCommand::Stop).map_err(|e| e.to_string())?;
@@ -174,7 +324,7 @@ impl RecorderState {
 const CANCELLED_MSG: &str = "Annulé";
 
 /// Filler words to strip from transcriptions (case-insensitive, whole-word).
-/// French and English fillers that add no semantic value.
+/// French and English fillers that add no semantic value; see `fillers_for`.
 const FILLERS_FR: &[&str] = &[
     "euh", "euhm", "heu", "heum", "hmm", "hm",
     "bah", "beh", "ben", "bon", "bon ben",
@@ -202,7 +352,17 @@ const FILLERS_EN: &[&str] = &[
     "at the end of the day", "to be honest",
 ];
 
-pub fn strip_fillers(s: &str) -> String {
+/// Fillers of the detected language (ISO 639-1); both lists when it is unknown or neither
+/// French nor English, so "like" survives in French and "donc" in English.
+fn fillers_for(language: Option<&str>) -> Vec<&'static str> {
+    match language {
+        Some("fr") => FILLERS_FR.to_vec(),
+        Some("en") => FILLERS_EN.to_vec(),
+        _ => FILLERS_FR.iter().chain(FILLERS_EN.iter()).copied().collect(),
+    }
+}
+
+pub fn strip_fillers(s: &str, language: Option<&str>) -> String {
     let trimmed = s.trim();
     if trimmed.is_empty() {
         return String::new();
@@ -212,7 +372,7 @@ pub fn strip_fillers(s: &str) -> String {
     let mut cleaned: Vec<String> = Vec::new();
     let mut i = 0;
 
-    let mut all_fillers: Vec<&str> = FILLERS_FR.iter().chain(FILLERS_EN.iter()).copied().collect();
+    let mut all_fillers = fillers_for(language);
     all_fillers.sort_by(|a, b| b.split_whitespace().count().cmp(&a.split_whitespace().count()));
 
     while i < words.len() {
@@ -251,8 +411,8 @@ pub fn strip_fillers(s: &str) -> String {
     result.trim().to_string()
 }
 
-fn light_fast_path(s: &str) -> String {
-    let stripped = strip_fillers(s);
+fn light_fast_path(s: &str, language: Option<&str>) -> String {
+    let stripped = strip_fillers(s, language);
     if stripped.is_empty() {
         return String::new();
     }
@@ -283,7 +443,7 @@ fn light_fast_path(s: &str) -> String {
 
 /// Find the "---REFLECTION---" separator in any variant (case, spacing, French).
 /// Returns (start, end) byte offsets if found.
-fn find_reflection_separator(text: &str) -> Option<(usize, usize)> {
+pub(crate) fn find_reflection_separator(text: &str) -> Option<(usize, usize)> {
     let lower = text.to_lowercase();
     for keyword in &["reflection", "réflexion"] {
         if let Some(kw_pos) = lower.find(keyword) {
@@ -307,19 +467,57 @@ fn find_reflection_separator(text: &str) -> Option<(usize, usize)> {
     None
 }
 
-async fn run_pipeline(
+/// Result of the transcription → dictionary → snippets → mode transform chain, before delivery.
+struct PipelineOutput {
+    transcribed_text: String,
+    payload: TranscriptionReadyPayload,
+    voice_commands: Vec<crate::voice_commands::VoiceCommand>,
+}
+
+/// Per-run replacements for the saved preferences and active mode (re-transcription).
+#[derive(Debug, Clone, Default)]
+pub struct PipelineOverrides {
+    pub provider: Option<String>,
+    /// Whisper language code; "auto" (or an empty string) lets the provider detect it.
+    pub language: Option<String>,
+    pub mode_id: Option<String>,
+}
+
+/// Everything between the upload and the paste, shared by live recordings and `transcribe_file`.
+/// With a `typer`, the LLM output is typed as it streams.
+#[allow(clippy::too_many_arguments)]
+async fn transcribe_and_transform(
     cancel: tokio_util::sync::CancellationToken,
-    wav_bytes: Vec<u8>,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let transcribed_text = tokio::select! {
+    segments: Vec<EncodedAudio>,
+    committed: Option<crate::transcribe::Transcription>,
+    context: Option<String>,
+    app: &tauri::AppHandle,
+    overrides: &PipelineOverrides,
+    typer: Option<&crate::stream_insert::StreamTyper>,
+    timer: &mut crate::usage::StageTimer,
+) -> Result<PipelineOutput, String> {
+    let mut prefs = crate::preferences::get_preferences(app).unwrap_or_default();
+    if let Some(ref provider) = overrides.provider {
+        prefs.transcription.provider = provider.clone();
+    }
+    if let Some(ref language) = overrides.language {
+        prefs.transcription.language = Some(language.clone());
+    }
+    let transcription = tokio::select! {
         _ = cancel.cancelled() => return Err(CANCELLED_MSG.to_string()),
-        r = crate::transcribe::transcribe_bytes(wav_bytes, &app) => r?,
+        r = crate::transcribe::transcribe_segments(segments, app, &prefs, committed, context.as_deref()) => r?,
     };
-    crate::usage::increment_transcription(&app);
-    let transcribed_text = strip_fillers(&transcribed_text);
-    let transcribed_text = crate::dictionary::apply_corrections(&app, &transcribed_text);
-    let transcribed_text = crate::snippets::process_snippets(&transcribed_text, &app);
+    timer.mark_with("transcription", transcription.provider.clone(), transcription.model.clone());
+    crate::usage::increment_transcription(app);
+    // Fillers, voice command phrases and the mode follow the language actually spoken
+    let language = transcription.language;
+    let transcribed_text = strip_fillers(&transcription.text, language.as_deref());
+    timer.mark("fillers");
+    let transcribed_text = crate::dictionary::apply_corrections(app, &transcribed_text);
+    crate::dictionary::mark_used(app, &transcribed_text);
+    timer.mark("dictionary");
+    let transcribed_text = crate::snippets::process_snippets(&transcribed_text, app);
+    timer.mark("snippets");
     let edit_result = crate::edit_commands::process_edit_commands(&transcribed_text);
     if edit_result.edits_applied > 0 {
         crate::clipboard::log_debug(&format!(
@@ -328,12 +526,22 @@ async fn run_pipeline(
         ));
     }
     let transcribed_text = edit_result.text;
-    let prompt_state = app.try_state::<crate::prompt_state::ActivePromptState>();
-    let mode_prompt = prompt_state.as_ref().and_then(|s| s.get().ok()).flatten();
-    let active_mode = prompt_state
-        .as_ref()
-        .and_then(|s| s.get_mode().ok())
-        .flatten();
+    timer.mark("edit_commands");
+    let language_mode = language
+        .as_deref()
+        .and_then(|lang| prefs.transcription.language_modes.get(lang));
+    let (mode_prompt, active_mode) = match (&overrides.mode_id, language_mode) {
+        (Some(mode_id), _) => (
+            Some(crate::modes::get_mode_prompt(app, mode_id)?),
+            Some(mode_id.clone()),
+        ),
+        // A deleted mode left in the mapping falls back to the active one
+        (None, Some(mode_id)) => match crate::modes::get_mode_prompt(app, mode_id) {
+            Ok(prompt) => (Some(prompt), Some(mode_id.clone())),
+            Err(_) => active_mode_and_prompt(app),
+        },
+        (None, None) => active_mode_and_prompt(app),
+    };
 
     // Fix 3: Validate transcription before sending to LLM
     // If too short (< 3 words), skip LLM — avoids transforming hallucinations
@@ -347,25 +555,29 @@ async fn run_pipeline(
 
     let is_light_mode = active_mode.as_deref() == Some("light");
 
+    let mut llm_source = None;
     let final_text = if is_light_mode {
-        light_fast_path(&transcribed_text)
+        light_fast_path(&transcribed_text, language.as_deref())
     } else if word_count < 3 {
         // Too short for meaningful LLM transformation — just clean up
-        light_fast_path(&transcribed_text)
+        light_fast_path(&transcribed_text, language.as_deref())
     } else if let Some(ref prompt) = mode_prompt {
         if !prompt.is_empty() {
+            let push = typer.map(|typer| move |delta: &str| typer.push(delta));
             match crate::llm::transform_text_streaming(
                 &transcribed_text,
                 prompt,
-                &app,
+                app,
                 cancel.clone(),
                 temp_override,
+                push.as_ref().map(|push| push as crate::llm_provider::OnDelta<'_>),
             )
             .await
             {
-                Ok(t) => {
-                    crate::usage::increment_llm(&app, 0, 0);
-                    t
+                Ok(out) => {
+                    // Tokens are recorded by `transform_text_streaming`
+                    llm_source = Some((out.provider, out.model));
+                    out.text
                 }
                 Err(e) => {
                     if e == CANCELLED_MSG {
@@ -373,8 +585,14 @@ async fn run_pipeline(
                     }
                     #[cfg(debug_assertions)]
                     eprintln!("Erreur transformation LLM: {}", e);
+                    // Time spent failing still counts against the provider
+                    llm_source = Some((prefs.llm.provider.clone(), prefs.llm.model.clone()));
                     let _ = app.emit("llm_skipped", e.clone());
-                    transcribed_text.clone()
+                    match typer {
+                        // Partly typed already: the output is what was typed
+                        Some(typer) if typer.started() => typer.received(),
+                        _ => transcribed_text.clone(),
+                    }
                 }
             }
         } else {
@@ -384,15 +602,21 @@ async fn run_pipeline(
         transcribed_text.clone()
     };
 
+    match llm_source {
+        Some((provider, model)) => timer.mark_with("llm", Some(provider), Some(model)),
+        None => timer.mark("cleanup"),
+    }
+
     // Voice commands: extract from final text in light/Direct mode only
     let (final_text, voice_commands) = if is_light_mode {
-        let result = crate::voice_commands::extract_commands(&final_text);
+        let result = crate::voice_commands::extract_commands(&final_text, language.as_deref());
         if !result.commands.is_empty() {
             crate::clipboard::log_debug(&format!(
                 "[run_pipeline] voice commands detected: {:?}",
                 result.commands
             ));
         }
+        timer.mark("voice_commands");
         (result.cleaned_text, result.commands)
     } else {
         (final_text, Vec::new())
@@ -408,6 +632,8 @@ async fn run_pipeline(
             thoughts,
             mode: active_mode.clone(),
             pasted: false,
+            language: language.clone(),
+            timings: Vec::new(),
         }
     } else {
         TranscriptionReadyPayload {
@@ -415,16 +641,66 @@ async fn run_pipeline(
             thoughts: None,
             mode: active_mode.clone(),
             pasted: false,
+            language: language.clone(),
+            timings: Vec::new(),
         }
     };
 
     // Count words in the final output for usage stats
     let output_word_count = payload.output.split_whitespace().count() as u64;
-    crate::usage::increment_words(&app, output_word_count);
+    crate::usage::increment_words(app, output_word_count);
+    let payload = TranscriptionReadyPayload {
+        timings: timer.timings(),
+        ..payload
+    };
+
+    Ok(PipelineOutput {
+        transcribed_text,
+        payload,
+        voice_commands,
+    })
+}
+
+/// Prompt and id of the mode currently selected in the UI.
+fn active_mode_and_prompt(app: &tauri::AppHandle) -> (Option<String>, Option<String>) {
+    let prompt_state = app.try_state::<crate::prompt_state::ActivePromptState>();
+    let mode_prompt = prompt_state.as_ref().and_then(|s| s.get().ok()).flatten();
+    let active_mode = prompt_state
+        .as_ref()
+        .and_then(|s| s.get_mode().ok())
+        .flatten();
+    (mode_prompt, active_mode)
+}
 
+/// `timer` started when the key was released; the stages up to the upload are marked.
+async fn run_pipeline(
+    cancel: tokio_util::sync::CancellationToken,
+    segments: Vec<EncodedAudio>,
+    committed: Option<crate::transcribe::Transcription>,
+    app: tauri::AppHandle,
+    mut timer: crate::usage::StageTimer,
+) -> Result<(), String> {
     let prefs = crate::preferences::get_preferences(&app).unwrap_or_default();
+    let typer = (prefs.behavior.insertion_mode == "stream" && !prefs.behavior.paste_input_and_output)
+        .then(|| crate::stream_insert::StreamTyper::start(cancel.clone(), app.clone()));
+    let PipelineOutput {
+        transcribed_text,
+        payload,
+        voice_commands,
+    } = transcribe_and_transform(
+        cancel,
+        segments,
+        committed,
+        crate::whisper_prompt::dictation_context(&app),
+        &app,
+        &PipelineOverrides::default(),
+        typer.as_ref(),
+        &mut timer,
+    )
+    .await?;
+
     let text_to_copy =
-        if prefs.behavior.paste_input_and_output && active_mode.as_deref() != Some("light") {
+        if prefs.behavior.paste_input_and_output && payload.mode.as_deref() != Some("light") {
             format!(
                 "Original\n\n{}\n\nResult\n\n{}",
                 transcribed_text, payload.output
@@ -433,39 +709,56 @@ async fn run_pipeline(
             payload.output.clone()
         };
 
-    // 1. Try direct AX insertion first (no clipboard pollution)
+    // 0. Typed as it streamed: wait for the last chunks
     let mut did_paste = false;
-    let ax_inserted = match crate::clipboard::insert_text_via_ax(&text_to_copy) {
-        Ok(true) => {
-            crate::clipboard::log_debug("[run_pipeline] AX insertion succeeded, skipping clipboard");
-            did_paste = true;
-            true
-        }
-        Ok(false) => {
-            crate::clipboard::log_debug("[run_pipeline] AX insertion not available, falling back to clipboard");
-            false
-        }
-        Err(e) => {
-            crate::clipboard::log_debug(&format!("[run_pipeline] AX insertion error: {}, falling back to clipboard", e));
-            false
-        }
+    let streamed = match typer {
+        Some(typer) if typer.started() => Some(typer.finish().await?),
+        _ => None,
     };
+    if let Some(inserted) = streamed {
+        crate::clipboard::log_debug(&format!("[run_pipeline] stream insertion done, inserted={}", inserted));
+        did_paste = inserted;
+        // Not everything reached a text field: keep the whole output at hand
+        if !inserted {
+            let _ = crate::clipboard::copy_to_clipboard(&text_to_copy, &app);
+        }
+        timer.mark("stream_insertion");
+    } else {
+        // 1. Try direct AX insertion first (no clipboard pollution)
+        let ax_inserted = match crate::clipboard::insert_text_via_ax(&text_to_copy) {
+            Ok(true) => {
+                crate::clipboard::log_debug("[run_pipeline] AX insertion succeeded, skipping clipboard");
+                did_paste = true;
+                true
+            }
+            Ok(false) => {
+                crate::clipboard::log_debug("[run_pipeline] AX insertion not available, falling back to clipboard");
+                false
+            }
+            Err(e) => {
+                crate::clipboard::log_debug(&format!("[run_pipeline] AX insertion error: {}, falling back to clipboard", e));
+                false
+            }
+        };
+        timer.mark("ax_insertion");
 
-    // 2. Fall back to clipboard + Cmd+V if AX didn't work
-    if !ax_inserted {
-        if let Err(e) = crate::clipboard::copy_to_clipboard(&text_to_copy, &app) {
-            crate::clipboard::log_debug(&format!("[run_pipeline] clipboard FAILED: {}", e));
-        } else {
-            let has_text_focus = crate::clipboard::has_focused_text_field();
-            crate::clipboard::log_debug(&format!("[run_pipeline] clipboard OK, has_text_focus={}", has_text_focus));
+        // 2. Fall back to clipboard + Cmd+V if AX didn't work
+        if !ax_inserted {
+            if let Err(e) = crate::clipboard::copy_to_clipboard(&text_to_copy, &app) {
+                crate::clipboard::log_debug(&format!("[run_pipeline] clipboard FAILED: {}", e));
+            } else {
+                let has_text_focus = crate::clipboard::has_focused_text_field();
+                crate::clipboard::log_debug(&format!("[run_pipeline] clipboard OK, has_text_focus={}", has_text_focus));
 
-            match crate::clipboard::send_paste_keystroke() {
-                Ok(()) => {
-                    crate::clipboard::log_debug("[run_pipeline] Cmd+V sent OK");
-                    did_paste = has_text_focus;
+                match crate::clipboard::send_paste_keystroke() {
+                    Ok(()) => {
+                        crate::clipboard::log_debug("[run_pipeline] Cmd+V sent OK");
+                        did_paste = has_text_focus;
+                    }
+                    Err(e) => crate::clipboard::log_debug(&format!("[run_pipeline] Cmd+V FAILED: {}", e)),
                 }
-                Err(e) => crate::clipboard::log_debug(&format!("[run_pipeline] Cmd+V FAILED: {}", e)),
             }
+            timer.mark("paste");
         }
     }
 
@@ -476,15 +769,18 @@ async fn run_pipeline(
                 "[run_pipeline] voice commands FAILED: {}", e
             ));
         }
+        timer.mark("command_execution");
     }
     let mut payload_with_paste = payload.clone();
     payload_with_paste.pasted = did_paste;
+    payload_with_paste.timings = timer.timings();
+    crate::usage::record_timings(&app, &payload_with_paste.timings);
     let _ = app.emit("transcription_ready", payload_with_paste);
-    if let Some(state) = app.try_state::<crate::LastOutputState>() {
-        if let Ok(mut guard) = state.0.lock() {
-            *guard = Some(payload.output);
-        }
-    }
+    let inserted_into = app
+        .try_state::<RecorderState>()
+        .and_then(|state| state.previous_app())
+        .filter(|_| did_paste);
+    crate::set_last_output(&app, payload.output, inserted_into);
     if prefs.behavior.sound_on_complete {
         let _ = app.emit_to("main", "play_completion_sound", ());
     }
@@ -494,64 +790,421 @@ async fn run_pipeline(
     Ok(())
 }
 
+/// Transcribe an audio file (voice memo…) through the same chain as a recording — dictionary,
+/// snippets, active mode — and return the result instead of pasting it.
+pub async fn transcribe_file(
+    path: std::path::PathBuf,
+    app: tauri::AppHandle,
+) -> Result<TranscriptionReadyPayload, String> {
+    transcribe_path(path, PipelineOverrides::default(), app).await
+}
+
+/// Rerun an archived take, optionally with another provider, language or mode.
+pub async fn retranscribe_recording(
+    id: &str,
+    overrides: PipelineOverrides,
+    app: tauri::AppHandle,
+) -> Result<TranscriptionReadyPayload, String> {
+    let path = crate::archive::recording_path(&app, id)?;
+    transcribe_path(path, overrides, app).await
+}
+
+async fn transcribe_path(
+    path: std::path::PathBuf,
+    overrides: PipelineOverrides,
+    app: tauri::AppHandle,
+) -> Result<TranscriptionReadyPayload, String> {
+    let mut timer = crate::usage::StageTimer::start();
+    let encoder = crate::preferences::get_preferences(&app)
+        .map(|p| AudioEncoder::from_pref(&p.transcription.upload_format))
+        .unwrap_or(AudioEncoder::Wav);
+    // Decoding, resampling and encoding are CPU-bound: keep them off the async runtime.
+    let segments = tauri::async_runtime::spawn_blocking(move || {
+        let decoded = crate::audio_file::decode_file(&path, MAX_RECORDING_SECS)?;
+        let samples = resample(&decoded.samples, decoded.sample_rate, WHISPER_SAMPLE_RATE);
+        drop(decoded);
+        let vad = detect_voice_activity(&samples, WHISPER_SAMPLE_RATE);
+        if !vad.has_speech() {
+            return Err("Aucune parole détectée dans le fichier.".to_string());
+        }
+        encode_segments(&vad.samples, WHISPER_SAMPLE_RATE, encoder)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    // Own token, so a dictation running meanwhile keeps (and clears) its own.
+    let cancel = tokio_util::sync::CancellationToken::new();
+    let file_cancel = app.try_state::<crate::FileTranscriptionCancel>();
+    if let Some(state) = &file_cancel {
+        if !state.0.set_if_idle(cancel.clone()) {
+            return Err("Une transcription de fichier est déjà en cours.".to_string());
+        }
+    }
+    timer.mark("decoding");
+    let result = transcribe_and_transform(cancel, segments, None, None, &app, &overrides, None, &mut timer).await;
+    if let Some(state) = &file_cancel {
+        state.0.clear();
+    }
+    let output = result?;
+    crate::set_last_output(&app, output.payload.output.clone(), None);
+    Ok(output.payload)
+}
+
+/// Cut 16 kHz speech into upload-sized segments at pauses and encode each one.
+fn encode_segments(
+    samples: &[f32],
+    sample_rate: u32,
+    encoder: AudioEncoder,
+) -> Result<Vec<EncodedAudio>, String> {
+    split_at_silences(samples, sample_rate, max_segment_samples(sample_rate))
+        .into_iter()
+        .map(|range| EncodedAudio::encode(&samples[range], sample_rate, encoder))
+        .collect()
+}
+
+/// Decode an audio file into upload segments for subtitles, each with its start time in
+/// seconds. Unlike `encode_segments` pauses are not trimmed, so timings follow the file;
+/// segments without speech are skipped. CPU-bound: call from a blocking task.
+pub fn load_timed_segments(
+    path: &std::path::Path,
+    encoder: AudioEncoder,
+) -> Result<Vec<(f64, EncodedAudio)>, String> {
+    let decoded = crate::audio_file::decode_file(path, MAX_RECORDING_SECS)?;
+    let samples = resample(&decoded.samples, decoded.sample_rate, WHISPER_SAMPLE_RATE);
+    drop(decoded);
+    split_at_silences(&samples, WHISPER_SAMPLE_RATE, max_segment_samples(WHISPER_SAMPLE_RATE))
+        .into_iter()
+        .filter(|range| detect_voice_activity(&samples[range.clone()], WHISPER_SAMPLE_RATE).has_speech())
+        .map(|range| {
+            let offset = range.start as f64 / WHISPER_SAMPLE_RATE as f64;
+            EncodedAudio::encode(&samples[range], WHISPER_SAMPLE_RATE, encoder).map(|audio| (offset, audio))
+        })
+        .collect()
+}
+
 fn clear_pipeline_cancel(app: &tauri::AppHandle) {
     if let Some(s) = app.try_state::<crate::PipelineCancel>() {
         s.clear()
     }
 }
 
+/// State shared between the audio worker and the cpal input callback.
+#[derive(Clone, Default)]
+struct CaptureShared {
+    buffer: Arc<Mutex<Vec<f32>>>,
+    /// Rate of `buffer` for readers outside the worker (live partials). A failover that
+    /// resamples the buffer updates it under the buffer lock.
+    sample_rate: Arc<AtomicU32>,
+    /// True while a recording is in progress. When false, a warm stream only feeds its pre-roll.
+    recording: Arc<AtomicBool>,
+    /// True while the recording is paused: input is dropped until it resumes.
+    paused: Arc<AtomicBool>,
+    /// Set by the callback when the sample cap is reached.
+    buffer_full: Arc<AtomicBool>,
+    /// Set by the callback when the end-of-speech detector fires.
+    silence_reached: Arc<AtomicBool>,
+    /// Latest input level window, published by the callback for the watcher thread.
+    level: Arc<Mutex<LevelState>>,
+    /// Set when the open stream lost its device (error callback or device unplugged).
+    stream_failed: Arc<AtomicBool>,
+}
+
+/// Recording settings an input stream was opened with. A change means reopening it.
+#[derive(Clone, Debug, PartialEq)]
+struct StreamSettings {
+    device_id: Option<String>,
+    silence_timeout_secs: Option<u32>,
+    always_warm: bool,
+}
+
+impl From<&crate::preferences::RecordingPrefs> for StreamSettings {
+    fn from(prefs: &crate::preferences::RecordingPrefs) -> Self {
+        Self {
+            device_id: prefs.input_device_id.clone(),
+            silence_timeout_secs: prefs.silence_timeout_secs,
+            always_warm: prefs.always_warm,
+        }
+    }
+}
+
+fn current_stream_settings(app: &tauri::AppHandle) -> StreamSettings {
+    let prefs = crate::preferences::get_preferences(app)
+        .map(|p| p.recording)
+        .unwrap_or_default();
+    StreamSettings::from(&prefs)
+}
+
+/// After a recording, the open stream keeps listening only if always-warm is still on and
+/// nothing it was opened with changed meanwhile (`SyncWarm` is ignored while recording).
+fn keep_warm_after_stop(open: Option<&StreamSettings>, current: &StreamSettings) -> bool {
+    current.always_warm && open == Some(current)
+}
+
+/// Tell the UI whether the microphone is held open between recordings (always-warm mode).
+fn emit_warm_state(app: &tauri::AppHandle, warm: &mut bool, value: bool) {
+    if *warm != value {
+        *warm = value;
+        let _ = app.emit("mic_warm", value);
+    }
+}
+
+impl CaptureShared {
+    fn reset_flags(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.buffer_full.store(false, Ordering::SeqCst);
+        self.silence_reached.store(false, Ordering::SeqCst);
+        if let Ok(mut level) = self.level.lock() {
+            *level = LevelState::default();
+        }
+    }
+}
+
 fn run_audio_worker(rx: mpsc::Receiver<AudioCommand>, app: tauri::AppHandle) {
     let mut stream_holder: Option<cpal::Stream> = None;
-    let buffer: Arc<Mutex<Vec<f32>>> = Arc::new(Mutex::new(Vec::new()));
-    let buffer_full: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+    // Settings the open stream was built with (None when no stream is open).
+    let mut open_settings: Option<StreamSettings> = None;
+    // Name of the device the open stream actually uses (may be the default after a fallback).
+    let mut open_device: Option<String> = None;
+    let mut warm_indicated = false;
+    let shared = CaptureShared::default();
+    let buffer = shared.buffer.clone();
     let mut sample_rate: u32 = 16000;
+    // Live partial transcription of the current take, when enabled.
+    let mut live_take: Option<Arc<crate::live_transcript::LiveTake>> = None;
 
     while let Ok(cmd) = rx.recv() {
         match cmd {
+            AudioCommand::SyncWarm => {
+                if shared.recording.load(Ordering::SeqCst) {
+                    continue;
+                }
+                let settings = current_stream_settings(&app);
+                if open_settings.as_ref() != Some(&settings) || shared.stream_failed.load(Ordering::SeqCst) {
+                    // Closed, failed, or opened with stale settings: drop it and the pre-roll it holds.
+                    stream_holder = None;
+                    open_settings = None;
+                    if settings.always_warm {
+                        match start_stream(&mut stream_holder, &shared, &mut sample_rate, &settings) {
+                            Ok(name) => {
+                                open_device = Some(name);
+                                open_settings = Some(settings);
+                            }
+                            Err(_e) => {
+                                #[cfg(debug_assertions)]
+                                eprintln!("[audio] could not open warm stream: {}", _e);
+                            }
+                        }
+                    }
+                }
+                emit_warm_state(&app, &mut warm_indicated, open_settings.is_some());
+            }
             AudioCommand::Start => {
-                if stream_holder.is_some() {
+                if shared.recording.load(Ordering::SeqCst) {
                     continue;
                 }
-                buffer_full.store(false, Ordering::SeqCst);
-                let max_samples = max_samples_for_rate(sample_rate);
-                let device_id = crate::preferences::get_preferences(&app)
-                    .ok()
-                    .and_then(|p| p.recording.input_device_id.clone());
-                if let Err(e) = start_stream(
-                    &mut stream_holder,
-                    &buffer,
-                    &buffer_full,
-                    &mut sample_rate,
-                    max_samples,
-                    device_id.as_deref(),
-                ) {
+                shared.reset_flags();
+                let prefs = crate::preferences::get_preferences(&app).unwrap_or_default();
+                let recording_prefs = prefs.recording;
+                let settings = StreamSettings::from(&recording_prefs);
+                if open_settings.as_ref() != Some(&settings) || shared.stream_failed.load(Ordering::SeqCst) {
+                    stream_holder = None;
+                    open_settings = None;
+                }
+                if let Ok(mut b) = buffer.lock() {
+                    b.clear();
+                }
+                // Set before the stream opens (or, when warm, flips the running one into
+                // recording): the callback prepends its pre-roll on this transition.
+                shared.recording.store(true, Ordering::SeqCst);
+                let opened = if stream_holder.is_some() {
+                    Ok(())
+                } else {
+                    start_stream(&mut stream_holder, &shared, &mut sample_rate, &settings).map(|name| {
+                        open_device = Some(name);
+                        open_settings = Some(settings);
+                    })
+                };
+                if let Err(e) = opened {
+                    shared.recording.store(false, Ordering::SeqCst);
+                    emit_warm_state(&app, &mut warm_indicated, false);
                     if let Some(state) = app.try_state::<RecorderState>() {
                         state.set_capturing(false);
                     }
                     let _ = app.emit("transcription_error", e);
                 } else {
-                    // Start a watcher thread that auto-stops when buffer is full
-                    let full_flag = buffer_full.clone();
+                    shared.sample_rate.store(sample_rate, Ordering::SeqCst);
+                    if prefs.transcription.live_partials {
+                        let take = Arc::new(crate::live_transcript::LiveTake::default());
+                        live_take = Some(take.clone());
+                        tauri::async_runtime::spawn(crate::live_transcript::run(
+                            app.clone(),
+                            shared.buffer.clone(),
+                            shared.sample_rate.clone(),
+                            take,
+                        ));
+                    }
+                    // Watcher thread: publishes live input levels, warns about a silent mic and
+                    // auto-stops when the buffer is full or the speaker went silent.
+                    // Exits on its own once the recording has been stopped by other means.
+                    let watched = shared.clone();
                     let app_for_stop = app.clone();
+                    let rate = sample_rate;
+                    let spectrum = recording_prefs.level_spectrum;
                     std::thread::spawn(move || {
+                        // Time spent actually recording; pauses do not count toward the warning.
+                        let mut active_ms: u32 = 0;
+                        let mut last_seq = 0;
+                        let mut warned_silent = false;
                         loop {
-                            std::thread::sleep(std::time::Duration::from_millis(500));
-                            if full_flag.load(Ordering::SeqCst) {
-                                #[cfg(debug_assertions)]
-                                eprintln!("[audio] buffer full — auto-stopping recording");
-                                if let Some(state) = app_for_stop.try_state::<RecorderState>() {
-                                    let _ = state.stop_capture();
-                                    let _ = app_for_stop.emit("recording_stopped", ());
+                            std::thread::sleep(std::time::Duration::from_millis(LEVEL_WINDOW_MS as u64));
+                            let Some(state) = app_for_stop.try_state::<RecorderState>() else {
+                                break;
+                            };
+                            if !state.is_capturing() {
+                                break;
+                            }
+                            if watched.stream_failed.swap(false, Ordering::SeqCst) {
+                                state.reopen_stream();
+                            }
+                            if state.is_paused() {
+                                continue;
+                            }
+                            active_ms += LEVEL_WINDOW_MS;
+                            let (payload, max_rms) = match watched.level.lock() {
+                                Ok(level) => {
+                                    let payload = (level.seq != last_seq).then(|| AudioLevelPayload {
+                                        rms: level.rms,
+                                        peak: level.peak,
+                                        clipping: level.clipping,
+                                        bands: spectrum.then(|| band_levels(&level.window, rate)),
+                                    });
+                                    last_seq = level.seq;
+                                    (payload, level.max_rms)
                                 }
+                                Err(_) => (None, 0.0),
+                            };
+                            if let Some(payload) = payload {
+                                let _ = app_for_stop.emit("audio_level", payload);
+                            }
+                            // Muted headset / wrong device: nothing ever reaches speech level.
+                            if !warned_silent
+                                && active_ms >= MIC_SILENT_WARNING_MS
+                                && max_rms < VAD_MIN_SPEECH_RMS
+                            {
+                                warned_silent = true;
+                                let _ = app_for_stop.emit("mic_silent_warning", max_rms);
+                            }
+                            let full = watched.buffer_full.load(Ordering::SeqCst);
+                            let silent = watched.silence_reached.load(Ordering::SeqCst);
+                            if full || silent {
+                                #[cfg(debug_assertions)]
+                                eprintln!(
+                                    "[audio] auto-stopping recording ({})",
+                                    if full { "buffer full" } else { "silence timeout" }
+                                );
+                                let _ = state.stop_capture();
+                                let _ = app_for_stop.emit("recording_stopped", ());
                                 break;
                             }
                         }
                     });
                 }
             }
-            AudioCommand::Stop => {
+            AudioCommand::Pause => {
+                if shared.recording.load(Ordering::SeqCst) {
+                    shared.paused.store(true, Ordering::SeqCst);
+                }
+            }
+            AudioCommand::Resume => {
+                shared.paused.store(false, Ordering::SeqCst);
+            }
+            AudioCommand::Reopen => {
+                if !shared.recording.load(Ordering::SeqCst) {
+                    continue;
+                }
+                let settings = open_settings.clone().unwrap_or_else(|| current_stream_settings(&app));
+                let previous_rate = sample_rate;
+                // Hold the buffer while streams are swapped, so what was captured so far can be
+                // brought to the new device's rate before appending resumes.
+                shared.recording.store(false, Ordering::SeqCst);
                 stream_holder = None;
-                let samples = buffer.lock().ok().map(|mut b| std::mem::take(&mut *b)).unwrap_or_default();
+                open_settings = None;
+                match start_stream(&mut stream_holder, &shared, &mut sample_rate, &settings) {
+                    Ok(name) => {
+                        if let Ok(mut b) = buffer.lock() {
+                            if sample_rate != previous_rate {
+                                let converted = resample(&b, previous_rate, sample_rate);
+                                *b = converted;
+                            }
+                            shared.sample_rate.store(sample_rate, Ordering::SeqCst);
+                        }
+                        #[cfg(debug_assertions)]
+                        eprintln!("[audio] input stream reopened on '{}' ({} Hz)", name, sample_rate);
+                        shared.recording.store(true, Ordering::SeqCst);
+                        let _ = app.emit("input_device_failover", &name);
+                        open_device = Some(name);
+                        open_settings = Some(settings);
+                    }
+                    Err(_e) => {
+                        // No usable device left: stop and transcribe what was captured so far.
+                        #[cfg(debug_assertions)]
+                        eprintln!("[audio] could not reopen input stream: {}", _e);
+                        if let Some(state) = app.try_state::<RecorderState>() {
+                            let _ = state.stop_capture();
+                        }
+                        let _ = app.emit("recording_stopped", ());
+                    }
+                }
+            }
+            AudioCommand::DevicesChanged(ids) => {
+                if stream_holder.is_none() {
+                    continue;
+                }
+                let recording = shared.recording.load(Ordering::SeqCst);
+                let lost = open_device.as_ref().is_some_and(|d| !ids.contains(d));
+                // An idle warm stream that fell back to the default moves back to the preferred
+                // device once it is plugged in again. Never switch mid-recording for that.
+                let preferred_back = !recording
+                    && open_settings
+                        .as_ref()
+                        .and_then(|s| s.device_id.as_ref())
+                        .is_some_and(|id| ids.contains(id) && open_device.as_ref() != Some(id));
+                if lost || preferred_back {
+                    shared.stream_failed.store(true, Ordering::SeqCst);
+                    if !recording {
+                        if let Some(state) = app.try_state::<RecorderState>() {
+                            state.sync_warm();
+                        }
+                    }
+                }
+            }
+            AudioCommand::Stop => {
+                let mut timer = crate::usage::StageTimer::start();
+                shared.recording.store(false, Ordering::SeqCst);
+                shared.paused.store(false, Ordering::SeqCst);
+                // No partial after this point; keep what live partials already committed.
+                let committed = live_take.take().and_then(|take| take.finish());
+                let current = current_stream_settings(&app);
+                if keep_warm_after_stop(open_settings.as_ref(), &current) {
+                    // Keep listening into the in-memory pre-roll ring only.
+                    emit_warm_state(&app, &mut warm_indicated, true);
+                } else {
+                    stream_holder = None;
+                    open_settings = None;
+                    emit_warm_state(&app, &mut warm_indicated, false);
+                    if current.always_warm {
+                        // Settings changed mid-recording: reopen warm with the new ones.
+                        if let Some(state) = app.try_state::<RecorderState>() {
+                            state.sync_warm();
+                        }
+                    }
+                }
+                let native = buffer.lock().ok().map(|mut b| std::mem::take(&mut *b)).unwrap_or_default();
+                let device_rate = sample_rate;
+                // Everything downstream (VAD, encoding, upload) works on 16 kHz mono.
+                let samples = resample(&native, device_rate, WHISPER_SAMPLE_RATE);
+                drop(native);
+                let sample_rate = WHISPER_SAMPLE_RATE;
                 let sample_count = samples.len();
                 let duration_secs = sample_count as f64 / sample_rate as f64;
                 let rms = if samples.is_empty() {
@@ -560,9 +1213,12 @@ fn run_audio_worker(rx: mpsc::Receiver<AudioCommand>, app: tauri::AppHandle) {
                     (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
                 };
                 let peak = samples.iter().map(|s| s.abs()).fold(0.0f32, f32::max);
+                let vad = detect_voice_activity(&samples, sample_rate);
+                let speech_ratio = vad.speech_ratio();
+                let trimmed_secs = vad.samples.len() as f64 / sample_rate as f64;
                 let diag = format!(
-                    "[audio] samples={}, duration={:.2}s, rate={}, RMS={:.6}, peak={:.6}\n",
-                    sample_count, duration_secs, sample_rate, rms, peak
+                    "[audio] samples={}, duration={:.2}s, device_rate={}, rate={}, RMS={:.6}, peak={:.6}, speech_ratio={:.2}, trimmed={:.2}s\n",
+                    sample_count, duration_secs, device_rate, sample_rate, rms, peak, speech_ratio, trimmed_secs
                 );
                 #[cfg(debug_assertions)]
                 eprintln!("{}", diag.trim());
@@ -579,8 +1235,8 @@ fn run_audio_worker(rx: mpsc::Receiver<AudioCommand>, app: tauri::AppHandle) {
                 }
                 // Emit audio diagnostics so frontend can show/log them
                 let _ = app.emit("audio_diagnostics", format!(
-                    "duration={:.2}s samples={} rate={} rms={:.6} peak={:.6}",
-                    duration_secs, sample_count, sample_rate, rms, peak
+                    "duration={:.2}s samples={} rate={} rms={:.6} peak={:.6} speech_ratio={:.2} trimmed={:.2}s",
+                    duration_secs, sample_count, sample_rate, rms, peak, speech_ratio, trimmed_secs
                 ));
 
                 if samples.is_empty() {
@@ -598,19 +1254,55 @@ fn run_audio_worker(rx: mpsc::Receiver<AudioCommand>, app: tauri::AppHandle) {
                     let _ = app.emit("transcription_error", "Audio trop faible ou silence détecté. Parlez plus fort ou plus près du micro.");
                     continue;
                 }
+                // Frame-level VAD found no speech at all (noise, breathing, keyboard) — don't upload.
+                if !vad.has_speech() {
+                    let _ = app.emit("transcription_error", "Aucune parole détectée. Parlez plus fort ou plus près du micro.");
+                    continue;
+                }
                 if let Ok(mut b) = buffer.lock() {
                     b.clear();
                 }
-                let wav_bytes = match write_wav_to_bytes(&samples, sample_rate) {
-                    Ok(b) => b,
+                let prefs = crate::preferences::get_preferences(&app).unwrap_or_default();
+                let encoder = AudioEncoder::from_pref(&prefs.transcription.upload_format);
+                // Only the audio after the committed part is uploaded again
+                let speech = match committed {
+                    Some(ref c) => {
+                        let rest = samples.get(c.samples..).unwrap_or_default();
+                        detect_voice_activity(rest, sample_rate).samples
+                    }
+                    None => vad.samples,
+                };
+                // Opt-in local archive, so a mis-heard take can be re-transcribed later.
+                // FLAC encoding of a long take is not instant: keep it off the worker thread.
+                if prefs.recording.archive_enabled {
+                    let meta = crate::archive::RecordingMeta::new(
+                        duration_secs,
+                        open_device.clone(),
+                        active_mode_and_prompt(&app).1,
+                        prefs.transcription.provider.clone(),
+                        prefs.transcription.language.clone(),
+                    );
+                    let app_for_archive = app.clone();
+                    std::thread::spawn(move || {
+                        if let Err(_e) = crate::archive::store(&app_for_archive, &samples, sample_rate, meta) {
+                            #[cfg(debug_assertions)]
+                            eprintln!("[archive] could not store recording: {}", _e);
+                        }
+                    });
+                }
+                // Long recordings go up as several segments cut in pauses.
+                let segments = match encode_segments(&speech, sample_rate, encoder) {
+                    Ok(s) => s,
                     Err(e) => {
                         let _ = app.emit("transcription_error", e);
                         continue;
                     }
                 };
+                timer.mark("encoding");
                 let app_for_spawn = app.clone();
+                let committed = committed.map(|c| c.transcription);
                 if let Err(e) = app.run_on_main_thread(move || {
-                    let bytes = wav_bytes;
+                    let segments = segments;
                     let handle = app_for_spawn.clone();
                     let cancel = tokio_util::sync::CancellationToken::new();
                     let cancel_child = cancel.clone();
@@ -618,7 +1310,7 @@ fn run_audio_worker(rx: mpsc::Receiver<AudioCommand>, app: tauri::AppHandle) {
                         state.set(cancel);
                     }
                     tauri::async_runtime::spawn(async move {
-                        let result = run_pipeline(cancel_child, bytes, handle.clone()).await;
+                        let result = run_pipeline(cancel_child, segments, committed, handle.clone(), timer).await;
                         clear_pipeline_cancel(&handle);
                         if let Err(ref err) = result {
                             #[cfg(debug_assertions)]
@@ -694,146 +1386,380 @@ fn check_microphone_permission() -> Result<(), String> {
     Ok(())
 }
 
-fn start_stream(
-    stream_holder: &mut Option<cpal::Stream>,
-    buffer: &Arc<Mutex<Vec<f32>>>,
-    buffer_full: &Arc<AtomicBool>,
-    sample_rate: &mut u32,
-    max_samples: usize,
-    device_id: Option<&str>,
-) -> Result<(), String> {
-    // Check cached permission (warmed up at startup). Fall back to live check if not cached yet.
-    MIC_PERMISSION_ONCE.call_once(|| {
-        MIC_PERMISSION_OK.store(check_microphone_permission().is_ok(), Ordering::SeqCst);
-    });
-    if !MIC_PERMISSION_OK.load(Ordering::SeqCst) {
-        // Permission was denied at startup — try once more in case user granted it since
-        if check_microphone_permission().is_err() {
-            return Err("Microphone access denied. Please grant microphone permission in System Settings > Privacy & Security > Microphone.".to_string());
-        }
-        MIC_PERMISSION_OK.store(true, Ordering::SeqCst);
-    }
+/// Live level metering window (~20 updates per second).
+const LEVEL_WINDOW_MS: u32 = 50;
+/// A sample at or above this magnitude counts as clipped.
+const CLIP_LEVEL: f32 = 0.999;
+/// Warn if nothing reaches speech level this long after the recording started.
+const MIC_SILENT_WARNING_MS: u32 = 2000;
+/// Centre frequencies of the coarse spectrum in `audio_level` events (octave bands).
+const SPECTRUM_BANDS_HZ: &[f32] = &[125.0, 250.0, 500.0, 1000.0, 2000.0, 4000.0, 8000.0];
 
-    let host = cpal::default_host();
-    let device = if let Some(id) = device_id {
-        host.devices()
-            .map_err(|e| e.to_string())?
-            .find(|d| d.name().ok().as_deref() == Some(id))
-            .or_else(|| {
-                // Selected device not found (unplugged?) — fall back to system default
-                #[cfg(debug_assertions)]
-                eprintln!("[audio] device '{}' not found, falling back to default", id);
-                host.default_input_device()
-            })
-            .ok_or_else(|| "no input device".to_string())?
-    } else {
-        host.default_input_device()
-            .ok_or_else(|| "no input device".to_string())?
-    };
+#[derive(Clone, serde::Serialize)]
+struct AudioLevelPayload {
+    rms: f32,
+    peak: f32,
+    clipping: bool,
+    /// Magnitude per `SPECTRUM_BANDS_HZ` band (0.0 – 1.0), when `level_spectrum` is on.
+    bands: Option<Vec<f32>>,
+}
 
-    let device_name = device.name().unwrap_or_else(|_| "unknown".to_string());
-    let config = device.default_input_config().map_err(|e| e.to_string())?;
-    let channels = config.channels();
+/// Last completed level window, shared between the callback and the watcher thread.
+#[derive(Default)]
+struct LevelState {
+    /// Bumped on every window so the watcher only emits fresh readings.
+    seq: u64,
+    rms: f32,
+    peak: f32,
+    clipping: bool,
+    /// Highest window RMS since the recording started.
+    max_rms: f32,
+    /// Raw samples of the last window, for the optional spectrum.
+    window: Vec<f32>,
+}
 
-    #[cfg(debug_assertions)]
-    eprintln!(
-        "[audio] device='{}', format={:?}, rate={}, channels={}",
-        device_name,
-        config.sample_format(),
-        config.sample_rate().0,
-        channels
-    );
-    #[cfg(debug_assertions)]
-    {
-        let _ = std::fs::write(
-            "/tmp/ghosty_device.log",
-            format!(
-                "device={}\nformat={:?}\nrate={}\nchannels={}\n",
-                device_name,
-                config.sample_format(),
-                config.sample_rate().0,
-                channels
-            ),
-        );
+/// Accumulates one level window in the callback and publishes it without ever blocking.
+struct LevelMeter {
+    window_len: usize,
+    samples: Vec<f32>,
+    max_rms: f32,
+    shared: Arc<Mutex<LevelState>>,
+}
+
+impl LevelMeter {
+    fn new(sample_rate: u32, shared: Arc<Mutex<LevelState>>) -> Self {
+        let window_len = ((sample_rate * LEVEL_WINDOW_MS / 1000) as usize).max(1);
+        Self {
+            window_len,
+            samples: Vec::with_capacity(window_len),
+            max_rms: 0.0,
+            shared,
+        }
     }
 
-    *sample_rate = config.sample_rate().0;
-    buffer.lock().map_err(|e| e.to_string())?.clear();
+    fn reset(&mut self) {
+        self.samples.clear();
+        self.max_rms = 0.0;
+    }
 
-    let buffer_clone = buffer.clone();
-    let full_flag = buffer_full.clone();
+    fn push(&mut self, s: f32) {
+        self.samples.push(s);
+        if self.samples.len() < self.window_len {
+            return;
+        }
+        let rms = frame_rms(&self.samples);
+        let peak = self.samples.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        self.max_rms = self.max_rms.max(rms);
+        // try_lock: if the watcher is reading, skip this window rather than stall the audio thread.
+        if let Ok(mut level) = self.shared.try_lock() {
+            level.seq = level.seq.wrapping_add(1);
+            level.rms = rms;
+            level.peak = peak;
+            level.clipping = peak >= CLIP_LEVEL;
+            level.max_rms = self.max_rms;
+            std::mem::swap(&mut level.window, &mut self.samples);
+        }
+        self.samples.clear();
+    }
+}
+
+/// Coarse spectrum: Goertzel magnitude at each `SPECTRUM_BANDS_HZ` centre frequency,
+/// normalised so a full-scale sine reads ~1.0. Bands above Nyquist read 0.
+fn band_levels(window: &[f32], sample_rate: u32) -> Vec<f32> {
+    SPECTRUM_BANDS_HZ
+        .iter()
+        .map(|&freq| {
+            if window.is_empty() || freq >= sample_rate as f32 / 2.0 {
+                return 0.0;
+            }
+            let coeff = 2.0 * (2.0 * std::f32::consts::PI * freq / sample_rate as f32).cos();
+            let (mut s1, mut s2) = (0.0f32, 0.0f32);
+            for &x in window {
+                let s0 = x + coeff * s1 - s2;
+                s2 = s1;
+                s1 = s0;
+            }
+            let power = (s1 * s1 + s2 * s2 - coeff * s1 * s2).max(0.0);
+            (2.0 * power.sqrt() / window.len() as f32).min(1.0)
+        })
+        .collect()
+}
+
+/// End-of-speech detector run inside the input callback (hands-free auto-stop).
+/// Same frame classifier as `detect_voice_activity`, but streaming: the noise floor
+/// follows quiet frames down immediately and drifts up slowly, and the detector only
+/// fires once speech has been heard, so a slow start never ends the recording.
+struct SilenceDetector {
+    frame_len: usize,
+    timeout_samples: usize,
+    noise_floor: f32,
+    sum_sq: f32,
+    crossings: usize,
+    count: usize,
+    prev: f32,
+    heard_speech: bool,
+    silent_samples: usize,
+    fired: Arc<AtomicBool>,
+}
+
+impl SilenceDetector {
+    fn new(sample_rate: u32, timeout_secs: u32, fired: Arc<AtomicBool>) -> Self {
+        Self {
+            frame_len: vad_frame_len(sample_rate),
+            timeout_samples: sample_rate as usize * timeout_secs as usize,
+            noise_floor: VAD_NOISE_FLOOR_MAX,
+            sum_sq: 0.0,
+            crossings: 0,
+            count: 0,
+            prev: 0.0,
+            heard_speech: false,
+            silent_samples: 0,
+            fired,
+        }
+    }
+
+    /// Start over for a new recording, keeping the learned noise floor.
+    fn reset(&mut self) {
+        self.sum_sq = 0.0;
+        self.crossings = 0;
+        self.count = 0;
+        self.heard_speech = false;
+        self.silent_samples = 0;
+    }
+
+    fn push(&mut self, s: f32) {
+        if self.count > 0 && (s >= 0.0) != (self.prev >= 0.0) {
+            self.crossings += 1;
+        }
+        self.prev = s;
+        self.sum_sq += s * s;
+        self.count += 1;
+        if self.count >= self.frame_len {
+            self.end_frame();
+        }
+    }
+
+    fn end_frame(&mut self) {
+        let rms = (self.sum_sq / self.count as f32).sqrt();
+        let zcr = self.crossings as f32 / (self.count - 1).max(1) as f32;
+        let threshold = (self.noise_floor * VAD_ENERGY_RATIO).max(VAD_MIN_SPEECH_RMS);
+
+        if is_speech_frame(rms, zcr, threshold) {
+            self.heard_speech = true;
+            self.silent_samples = 0;
+        } else {
+            self.noise_floor = if rms < self.noise_floor {
+                rms
+            } else {
+                self.noise_floor + (rms - self.noise_floor) * 0.05
+            }
+            .clamp(VAD_NOISE_FLOOR_MIN, VAD_NOISE_FLOOR_MAX);
+            if self.heard_speech {
+                self.silent_samples += self.count;
+                if self.silent_samples >= self.timeout_samples {
+                    self.fired.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+
+        self.sum_sq = 0.0;
+        self.crossings = 0;
+        self.count = 0;
+    }
+}
+
+/// Audio kept from before the recording starts in always-warm mode. Covers the time
+/// between the key press and the first callback, where the first syllable used to go.
+const PRE_ROLL_MS: u32 = 500;
+
+/// Everything the cpal input callback needs, independent of the device sample format.
+struct CaptureSink {
+    buffer: Arc<Mutex<Vec<f32>>>,
+    buffer_full: Arc<AtomicBool>,
+    recording: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    was_recording: bool,
+    /// Always-warm mode only: the last `PRE_ROLL_MS` of audio while idle.
+    /// Lives in this callback's memory only — never written anywhere, gone with the stream.
+    pre_roll: std::collections::VecDeque<f32>,
+    pre_roll_len: usize,
+    max_samples: usize,
+    channels: usize,
+    silence: Option<SilenceDetector>,
+    level: LevelMeter,
+}
+
+impl CaptureSink {
+    fn push<T>(&mut self, data: &[T])
+    where
+        T: cpal::Sample,
+        f32: cpal::FromSample<T>,
+    {
+        // Downmix to mono by averaging channels: some mics only carry the voice on channel 1.
+        let mono = data
+            .chunks(self.channels.max(1))
+            .map(|frame| frame.iter().map(|&x| f32::from_sample(x)).sum::<f32>() / frame.len() as f32);
+
+        if self.paused.load(Ordering::SeqCst) {
+            // Paused: drop the input and re-arm the start transition with an empty pre-roll,
+            // so resuming resets the silence detector and meter without replaying the pause.
+            self.was_recording = false;
+            self.pre_roll.clear();
+            return;
+        }
+        if !self.recording.load(Ordering::SeqCst) {
+            self.was_recording = false;
+            if self.pre_roll_len > 0 {
+                for s in mono {
+                    if self.pre_roll.len() == self.pre_roll_len {
+                        self.pre_roll.pop_front();
+                    }
+                    self.pre_roll.push_back(s);
+                }
+            }
+            return;
+        }
+
+        let Ok(mut guard) = self.buffer.lock() else {
+            return;
+        };
+        if !self.was_recording {
+            // Recording just started: prepend what was heard right before the key press.
+            self.was_recording = true;
+            guard.extend(self.pre_roll.drain(..));
+            if let Some(ref mut silence) = self.silence {
+                silence.reset();
+            }
+            self.level.reset();
+        }
+        for s in mono {
+            if guard.len() >= self.max_samples {
+                self.buffer_full.store(true, Ordering::SeqCst);
+                break;
+            }
+            if let Some(ref mut silence) = self.silence {
+                silence.push(s);
+            }
+            self.level.push(s);
+            guard.push(s);
+        }
+    }
+}
+
+fn start_stream(
+    stream_holder: &mut Option<cpal::Stream>,
+    shared: &CaptureShared,
+    sample_rate: &mut u32,
+    settings: &StreamSettings,
+) -> Result<String, String> {
+    // Check cached permission (warmed up at startup). Fall back to live check if not cached yet.
+    MIC_PERMISSION_ONCE.call_once(|| {
+        MIC_PERMISSION_OK.store(check_microphone_permission().is_ok(), Ordering::SeqCst);
+    });
+    if !MIC_PERMISSION_OK.load(Ordering::SeqCst) {
+        // Permission was denied at startup — try once more in case user granted it since
+        if check_microphone_permission().is_err() {
+            return Err("Microphone access denied. Please grant microphone permission in System Settings > Privacy & Security > Microphone.".to_string());
+        }
+        MIC_PERMISSION_OK.store(true, Ordering::SeqCst);
+    }
+
+    let host = cpal::default_host();
+    let device = if let Some(id) = settings.device_id.as_deref() {
+        host.devices()
+            .map_err(|e| e.to_string())?
+            .find(|d| d.name().ok().as_deref() == Some(id))
+            .or_else(|| {
+                // Selected device not found (unplugged?) — fall back to system default
+                #[cfg(debug_assertions)]
+                eprintln!("[audio] device '{}' not found, falling back to default", id);
+                host.default_input_device()
+            })
+            .ok_or_else(|| "no input device".to_string())?
+    } else {
+        host.default_input_device()
+            .ok_or_else(|| "no input device".to_string())?
+    };
+
+    let device_name = device.name().unwrap_or_else(|_| "unknown".to_string());
+    let config = device.default_input_config().map_err(|e| e.to_string())?;
+    let channels = config.channels();
+
+    #[cfg(debug_assertions)]
+    eprintln!(
+        "[audio] device='{}', format={:?}, rate={}, channels={}",
+        device_name,
+        config.sample_format(),
+        config.sample_rate().0,
+        channels
+    );
+    #[cfg(debug_assertions)]
+    {
+        let _ = std::fs::write(
+            "/tmp/ghosty_device.log",
+            format!(
+                "device={}\nformat={:?}\nrate={}\nchannels={}\n",
+                device_name,
+                config.sample_format(),
+                config.sample_rate().0,
+                channels
+            ),
+        );
+    }
+
+    *sample_rate = config.sample_rate().0;
+    shared.stream_failed.store(false, Ordering::SeqCst);
+
+    let stream_failed = shared.stream_failed.clone();
     let err_fn = move |err: cpal::StreamError| {
-        let _ = err; // consumed; logged only in debug builds
         #[cfg(debug_assertions)]
         eprintln!("audio stream error: {}", err);
+        // Unplugged / disconnected: the worker reopens the preferred or default device.
+        if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+            stream_failed.store(true, Ordering::SeqCst);
+        }
     };
 
-    // Force mono: take only channel 0 from interleaved multi-channel input
-    let ch = channels as usize;
+    let pre_roll_len = if settings.always_warm {
+        (*sample_rate as usize * PRE_ROLL_MS as usize) / 1000
+    } else {
+        0
+    };
+    let sink = CaptureSink {
+        buffer: shared.buffer.clone(),
+        buffer_full: shared.buffer_full.clone(),
+        recording: shared.recording.clone(),
+        paused: shared.paused.clone(),
+        was_recording: false,
+        pre_roll: std::collections::VecDeque::with_capacity(pre_roll_len),
+        pre_roll_len,
+        max_samples: max_samples_for_rate(*sample_rate),
+        channels: channels as usize,
+        silence: settings
+            .silence_timeout_secs
+            .filter(|&secs| secs > 0)
+            .map(|secs| SilenceDetector::new(*sample_rate, secs, shared.silence_reached.clone())),
+        level: LevelMeter::new(*sample_rate, shared.level.clone()),
+    };
 
     let stream = match config.sample_format() {
         cpal::SampleFormat::F32 => {
-            let b = buffer_clone.clone();
-            let ff = full_flag.clone();
+            let mut sink = sink;
             device
                 .build_input_stream(
                     &config.into(),
-                    move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                        if let Ok(mut guard) = b.lock() {
-                            if ch <= 1 {
-                                if guard.len() + data.len() <= max_samples {
-                                    guard.extend_from_slice(data);
-                                } else if guard.len() < max_samples {
-                                    let remaining = max_samples - guard.len();
-                                    guard.extend_from_slice(&data[..remaining]);
-                                    ff.store(true, Ordering::SeqCst);
-                                } else {
-                                    ff.store(true, Ordering::SeqCst);
-                                }
-                            } else {
-                                // Extract channel 0 from interleaved data
-                                for chunk in data.chunks(ch) {
-                                    if guard.len() >= max_samples {
-                                        ff.store(true, Ordering::SeqCst);
-                                        break;
-                                    }
-                                    guard.push(chunk[0]);
-                                }
-                            }
-                        }
-                    },
+                    move |data: &[f32], _: &cpal::InputCallbackInfo| sink.push(data),
                     err_fn,
                     None,
                 )
                 .map_err(|e| e.to_string())?
         }
         cpal::SampleFormat::I16 => {
-            let b = buffer_clone.clone();
-            let ff = full_flag.clone();
+            let mut sink = sink;
             device
                 .build_input_stream(
                     &config.into(),
-                    move |data: &[i16], _: &cpal::InputCallbackInfo| {
-                        if let Ok(mut guard) = b.lock() {
-                            if ch <= 1 {
-                                for &s in data {
-                                    if guard.len() >= max_samples {
-                                        ff.store(true, Ordering::SeqCst);
-                                        break;
-                                    }
-                                    guard.push(f32::from_sample(s));
-                                }
-                            } else {
-                                for chunk in data.chunks(ch) {
-                                    if guard.len() >= max_samples {
-                                        ff.store(true, Ordering::SeqCst);
-                                        break;
-                                    }
-                                    guard.push(f32::from_sample(chunk[0]));
-                                }
-                            }
-                        }
-                    },
+                    move |data: &[i16], _: &cpal::InputCallbackInfo| sink.push(data),
                     err_fn,
                     None,
                 )
@@ -844,7 +1770,7 @@ fn start_stream(
 
     stream.play().map_err(|e| e.to_string())?;
     *stream_holder = Some(stream);
-    Ok(())
+    Ok(device_name)
 }
 
 #[allow(dead_code)]
@@ -906,6 +1832,476 @@ pub fn write_wav_to_bytes(samples: &[f32], sample_rate: u32) -> Result<Vec<u8>,
     Ok(buf)
 }
 
+// ── Upload encoding ─────────────────────────────────────────────────
+
+/// Audio format sent to the transcription API. OpenAI and Groq both accept
+/// WAV, FLAC and OGG/Opus; the choice comes from `TranscriptionPrefs::upload_format`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioEncoder {
+    /// Raw 16-bit PCM. Largest upload, zero CPU.
+    Wav,
+    /// Lossless, typically 40–60% of the WAV size for speech.
+    Flac,
+    /// Lossy speech codec, ~20× smaller than WAV at 24 kbit/s.
+    Opus,
+}
+
+impl AudioEncoder {
+    /// Parse the preference value; unknown values fall back to WAV (always accepted).
+    pub fn from_pref(value: &str) -> Self {
+        match value.trim().to_lowercase().as_str() {
+            "flac" => AudioEncoder::Flac,
+            "opus" | "ogg" => AudioEncoder::Opus,
+            _ => AudioEncoder::Wav,
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            AudioEncoder::Wav => "audio/wav",
+            AudioEncoder::Flac => "audio/flac",
+            AudioEncoder::Opus => "audio/ogg",
+        }
+    }
+
+    /// File name for the multipart part — the APIs sniff the format from the extension.
+    pub fn file_name(self) -> &'static str {
+        match self {
+            AudioEncoder::Wav => "audio.wav",
+            AudioEncoder::Flac => "audio.flac",
+            AudioEncoder::Opus => "audio.ogg",
+        }
+    }
+
+    pub fn encode(self, samples: &[f32], sample_rate: u32) -> Result<Vec<u8>, String> {
+        match self {
+            AudioEncoder::Wav => write_wav_to_bytes(samples, sample_rate),
+            AudioEncoder::Flac => write_flac_to_bytes(samples, sample_rate),
+            AudioEncoder::Opus => write_opus_to_bytes(samples, sample_rate),
+        }
+    }
+}
+
+/// Encoded upload payload: the bytes plus the format they are in.
+#[derive(Clone, Debug)]
+pub struct EncodedAudio {
+    pub bytes: Vec<u8>,
+    pub encoder: AudioEncoder,
+    /// What transcription is billed on.
+    pub duration_secs: f64,
+}
+
+impl EncodedAudio {
+    /// Encode with the preferred format, falling back to WAV if that encoder fails.
+    pub fn encode(samples: &[f32], sample_rate: u32, encoder: AudioEncoder) -> Result<Self, String> {
+        match encoder.encode(samples, sample_rate) {
+            Ok(bytes) => Ok(Self {
+                bytes,
+                encoder,
+                duration_secs: samples.len() as f64 / sample_rate as f64,
+            }),
+            Err(_e) if encoder != AudioEncoder::Wav => {
+                #[cfg(debug_assertions)]
+                eprintln!("[audio] {:?} encoding failed ({}), falling back to WAV", encoder, _e);
+                Self::encode(samples, sample_rate, AudioEncoder::Wav)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn multipart_part(&self) -> Result<reqwest::multipart::Part, String> {
+        reqwest::multipart::Part::bytes(self.bytes.clone())
+            .file_name(self.encoder.file_name())
+            .mime_str(self.encoder.mime_type())
+            .map_err(|e| e.to_string())
+    }
+}
+
+fn to_pcm16(s: f32) -> i32 {
+    // Same conversion as the WAV writer so every format carries identical samples
+    (s.clamp(-1.0, 1.0) * 32767.0) as i16 as i32
+}
+
+/// Lossless FLAC through `flacenc`, from the same 16-bit samples as the WAV writer.
+pub fn write_flac_to_bytes(samples: &[f32], sample_rate: u32) -> Result<Vec<u8>, String> {
+    use flacenc::component::BitRepr;
+    use flacenc::error::Verify;
+
+    let pcm: Vec<i32> = samples.iter().map(|&s| to_pcm16(s)).collect();
+    let config = flacenc::config::Encoder::default()
+        .into_verified()
+        .map_err(|(_, e)| format!("FLAC config: {}", e))?;
+    let source = flacenc::source::MemSource::from_samples(&pcm, 1, 16, sample_rate as usize);
+    let stream = flacenc::encode_with_fixed_block_size(&config, source, config.block_size)
+        .map_err(|e| format!("FLAC encoding failed: {}", e))?;
+    let mut sink = flacenc::bitsink::ByteSink::new();
+    stream
+        .write(&mut sink)
+        .map_err(|e| format!("FLAC encoding failed: {}", e))?;
+    Ok(sink.as_slice().to_vec())
+}
+
+/// Opus frame duration (20 ms is the codec's sweet spot for speech).
+const OPUS_FRAME_MS: u32 = 20;
+/// Speech bitrate: transparent enough that Whisper accuracy is unaffected.
+const OPUS_BITRATE: i32 = 24_000;
+/// Arbitrary Ogg logical stream serial — there is only one stream per file.
+const OPUS_OGG_SERIAL: u32 = 0x4768_6f73;
+
+/// Encode mono speech as Opus in an Ogg container (RFC 7845).
+pub fn write_opus_to_bytes(samples: &[f32], sample_rate: u32) -> Result<Vec<u8>, String> {
+    use audiopus::coder::Encoder;
+    use audiopus::{Application, Bitrate, Channels, SampleRate};
+    use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+    let opus_rate = SampleRate::try_from(sample_rate as i32)
+        .map_err(|_| format!("unsupported Opus sample rate: {}", sample_rate))?;
+    let mut encoder = Encoder::new(opus_rate, Channels::Mono, Application::Voip)
+        .map_err(|e| format!("Opus encoder init failed: {}", e))?;
+    encoder
+        .set_bitrate(Bitrate::BitsPerSecond(OPUS_BITRATE))
+        .map_err(|e| e.to_string())?;
+
+    // Granule positions are always counted at 48 kHz in Ogg Opus.
+    let to_48k = 48_000 / sample_rate as u64;
+    let pre_skip = encoder.lookahead().map_err(|e| e.to_string())? as u64 * to_48k;
+    let frame_len = (sample_rate * OPUS_FRAME_MS / 1000) as usize;
+    let end_granule = pre_skip + samples.len() as u64 * to_48k;
+
+    let mut writer = PacketWriter::new(Vec::new());
+    let write_err = |e: std::io::Error| e.to_string();
+
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1); // version
+    head.push(1); // channels
+    head.extend_from_slice(&(pre_skip as u16).to_le_bytes());
+    head.extend_from_slice(&sample_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // channel mapping family
+    writer
+        .write_packet(head.into_boxed_slice(), OPUS_OGG_SERIAL, PacketWriteEndInfo::EndPage, 0)
+        .map_err(write_err)?;
+
+    let vendor = b"ghosty";
+    let mut tags = Vec::new();
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes()); // no user comments
+    writer
+        .write_packet(tags.into_boxed_slice(), OPUS_OGG_SERIAL, PacketWriteEndInfo::EndPage, 0)
+        .map_err(write_err)?;
+
+    // Pad with silence so the decoder also gets the samples delayed by the encoder lookahead.
+    let needed = (((end_granule + to_48k - 1) / to_48k) as usize).max(1);
+    let frames = (needed + frame_len - 1) / frame_len;
+    let mut padded = samples.to_vec();
+    padded.resize(frames * frame_len, 0.0);
+
+    let mut packet = vec![0u8; 4000];
+    for (i, frame) in padded.chunks(frame_len).enumerate() {
+        let len = encoder
+            .encode_float(frame, &mut packet)
+            .map_err(|e| format!("Opus encoding failed: {}", e))?;
+        let last = i + 1 == frames;
+        let granule = if last {
+            end_granule
+        } else {
+            ((i + 1) * frame_len) as u64 * to_48k
+        };
+        let end = if last {
+            PacketWriteEndInfo::EndStream
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        writer
+            .write_packet(packet[..len].to_vec().into_boxed_slice(), OPUS_OGG_SERIAL, end, granule)
+            .map_err(write_err)?;
+    }
+    Ok(writer.into_inner())
+}
+
+// ── Resampling ──────────────────────────────────────────────────────
+
+/// Zero crossings of the sinc kept on each side of the kernel centre (at the output rate).
+/// 16 gives a steep enough transition band for speech without a huge kernel.
+const RESAMPLE_ZERO_CROSSINGS: usize = 16;
+/// Cutoff as a fraction of the output Nyquist frequency, so the transition band
+/// ends before the fold-over point instead of straddling it.
+const RESAMPLE_ROLLOFF: f64 = 0.95;
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Blackman window over `x` in [-1, 1].
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+    let t = std::f64::consts::PI * x;
+    0.42 + 0.5 * t.cos() + 0.08 * (2.0 * t).cos()
+}
+
+/// Resample mono audio with a polyphase windowed-sinc filter.
+/// The rate ratio is reduced to `up / down`; each of the `up` phases gets its own
+/// precomputed, unity-gain kernel, so the inner loop is a plain dot product.
+/// When downsampling, the kernel is widened to low-pass below the new Nyquist.
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    if samples.is_empty() || from_rate == to_rate || from_rate == 0 || to_rate == 0 {
+        return samples.to_vec();
+    }
+    let g = gcd(from_rate, to_rate);
+    let up = (to_rate / g) as usize;
+    let down = (from_rate / g) as usize;
+
+    // Cutoff relative to the input Nyquist frequency.
+    let cutoff = (to_rate as f64 / from_rate as f64).min(1.0) * RESAMPLE_ROLLOFF;
+    let half = (RESAMPLE_ZERO_CROSSINGS as f64 / cutoff).ceil() as usize;
+    let taps = 2 * half;
+
+    // kernels[phase][j] weights input sample `base - half + 1 + j`,
+    // sitting `d = frac + half - 1 - j` samples before the output position.
+    let kernels: Vec<Vec<f32>> = (0..up)
+        .map(|phase| {
+            let frac = phase as f64 / up as f64;
+            let raw: Vec<f64> = (0..taps)
+                .map(|j| {
+                    let d = frac + half as f64 - 1.0 - j as f64;
+                    let x = std::f64::consts::PI * cutoff * d;
+                    let sinc = if x.abs() < 1e-12 { 1.0 } else { x.sin() / x };
+                    sinc * blackman(d / half as f64)
+                })
+                .collect();
+            let sum: f64 = raw.iter().sum();
+            raw.iter().map(|w| (w / sum) as f32).collect()
+        })
+        .collect();
+
+    let out_len = (samples.len() * up + down - 1) / down;
+    let last = samples.len() as isize - 1;
+    (0..out_len)
+        .map(|n| {
+            let pos = n * down;
+            let base = (pos / up) as isize;
+            let kernel = &kernels[pos % up];
+            let start = base - half as isize + 1;
+            kernel
+                .iter()
+                .enumerate()
+                .map(|(j, &w)| {
+                    // Hold the edge samples instead of padding with zeros (no clicks at the ends).
+                    let idx = (start + j as isize).clamp(0, last) as usize;
+                    samples[idx] * w
+                })
+                .sum()
+        })
+        .collect()
+}
+
+// ── Segmentation ────────────────────────────────────────────────────
+
+/// Longest segment uploaded in one request. Far below the 25 MB limit in any format,
+/// and short enough that the segments of a long dictation transcribe in parallel.
+const SEGMENT_MAX_SECS: u32 = 120;
+/// Cut points are searched in the last part of each segment.
+const SEGMENT_SEARCH_SECS: u32 = 30;
+/// Width of the quietest stretch picked as a cut point (a short pause between words).
+const SEGMENT_PAUSE_MS: u32 = 300;
+
+fn max_segment_samples(sample_rate: u32) -> usize {
+    ((SEGMENT_MAX_SECS * sample_rate) as usize).min(MAX_WAV_BYTES / 2)
+}
+
+/// Split a recording into ranges of at most `max_len` samples, cutting each one
+/// in the quietest `SEGMENT_PAUSE_MS` stretch of its last `SEGMENT_SEARCH_SECS`
+/// so no word is chopped in half. Short recordings come back as a single range.
+pub fn split_at_silences(
+    samples: &[f32],
+    sample_rate: u32,
+    max_len: usize,
+) -> Vec<std::ops::Range<usize>> {
+    if samples.is_empty() {
+        return Vec::new();
+    }
+    let max_len = max_len.max(1);
+    let frame_len = vad_frame_len(sample_rate);
+    let search = ((SEGMENT_SEARCH_SECS * sample_rate) as usize).min(max_len / 2);
+    let pause_frames = ((SEGMENT_PAUSE_MS / VAD_FRAME_MS) as usize).max(1);
+
+    let mut ranges = Vec::new();
+    let mut start = 0;
+    while samples.len() - start > max_len {
+        let window_end = start + max_len;
+        let window_start = window_end - search;
+        let energies: Vec<f32> = samples[window_start..window_end]
+            .chunks(frame_len)
+            .map(frame_rms)
+            .collect();
+        // Sliding energy over `pause_frames` frames; the minimum is the best pause.
+        let span = pause_frames.min(energies.len()).max(1);
+        let best = energies
+            .windows(span)
+            .map(|w| w.iter().sum::<f32>())
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        // window_start > start, so every segment is non-empty and the loop always advances
+        let cut = (window_start + (best + span / 2) * frame_len).min(window_end);
+        ranges.push(start..cut);
+        start = cut;
+    }
+    ranges.push(start..samples.len());
+    ranges
+}
+
+// ── Voice activity detection ────────────────────────────────────────
+
+/// VAD analysis window. 30 ms is the usual speech frame size (WebRTC VAD uses 10/20/30).
+const VAD_FRAME_MS: u32 = 30;
+/// Frames kept after the last speech frame so word endings (plosives, fricatives) survive.
+const VAD_HANGOVER_FRAMES: usize = 8;
+/// Frames kept before a speech onset so the attack of the first syllable isn't clipped.
+const VAD_ONSET_FRAMES: usize = 3;
+/// Internal pauses longer than this are collapsed down to `VAD_KEPT_PAUSE_MS`.
+const VAD_MAX_PAUSE_MS: u32 = 700;
+const VAD_KEPT_PAUSE_MS: u32 = 300;
+/// Bounds for the adaptive noise floor (frame RMS). The upper bound keeps continuous
+/// speech without pauses from being mistaken for background noise.
+const VAD_NOISE_FLOOR_MIN: f32 = 0.0005;
+const VAD_NOISE_FLOOR_MAX: f32 = 0.008;
+/// A frame must be this many times louder than the noise floor to count as speech.
+const VAD_ENERGY_RATIO: f32 = 3.0;
+/// Absolute minimum frame RMS for speech, whatever the noise floor.
+const VAD_MIN_SPEECH_RMS: f32 = 0.002;
+/// Zero-crossing rate above which a quiet frame is treated as hiss rather than voice.
+const VAD_NOISE_ZCR: f32 = 0.25;
+
+/// Result of `detect_voice_activity`: the trimmed samples plus frame statistics.
+pub struct VadResult {
+    pub samples: Vec<f32>,
+    pub speech_frames: usize,
+    pub total_frames: usize,
+}
+
+impl VadResult {
+    /// Fraction of analysed frames classified as speech (0.0 – 1.0).
+    pub fn speech_ratio(&self) -> f32 {
+        if self.total_frames == 0 {
+            0.0
+        } else {
+            self.speech_frames as f32 / self.total_frames as f32
+        }
+    }
+
+    pub fn has_speech(&self) -> bool {
+        self.speech_frames > 0
+    }
+}
+
+fn vad_frame_len(sample_rate: u32) -> usize {
+    ((sample_rate * VAD_FRAME_MS / 1000) as usize).max(1)
+}
+
+fn frame_rms(frame: &[f32]) -> f32 {
+    if frame.is_empty() {
+        return 0.0;
+    }
+    (frame.iter().map(|s| s * s).sum::<f32>() / frame.len() as f32).sqrt()
+}
+
+fn frame_zcr(frame: &[f32]) -> f32 {
+    if frame.len() < 2 {
+        return 0.0;
+    }
+    let crossings = frame
+        .windows(2)
+        .filter(|w| (w[0] >= 0.0) != (w[1] >= 0.0))
+        .count();
+    crossings as f32 / (frame.len() - 1) as f32
+}
+
+/// Classify a single frame against a speech energy threshold.
+/// Quiet frames with a noise-like zero-crossing rate are rejected; loud ones pass
+/// regardless so unvoiced consonants (s, f, ch) aren't cut.
+fn is_speech_frame(rms: f32, zcr: f32, threshold: f32) -> bool {
+    rms >= threshold && (zcr < VAD_NOISE_ZCR || rms >= threshold * 2.0)
+}
+
+/// Frame-level VAD (energy + zero-crossing rate, with onset padding and hangover).
+/// Trims leading/trailing silence and collapses long internal pauses.
+/// Whisper hallucinates on silence, so only the speech region is worth uploading.
+pub fn detect_voice_activity(samples: &[f32], sample_rate: u32) -> VadResult {
+    let frame_len = vad_frame_len(sample_rate);
+    let frames: Vec<&[f32]> = samples.chunks(frame_len).collect();
+    let total_frames = frames.len();
+    if total_frames == 0 {
+        return VadResult {
+            samples: Vec::new(),
+            speech_frames: 0,
+            total_frames: 0,
+        };
+    }
+
+    let energies: Vec<f32> = frames.iter().map(|f| frame_rms(f)).collect();
+
+    // Noise floor = 10th percentile of frame energies, bounded.
+    let mut sorted = energies.clone();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let noise_floor = sorted[sorted.len() / 10].clamp(VAD_NOISE_FLOOR_MIN, VAD_NOISE_FLOOR_MAX);
+    let threshold = (noise_floor * VAD_ENERGY_RATIO).max(VAD_MIN_SPEECH_RMS);
+
+    let raw: Vec<bool> = frames
+        .iter()
+        .zip(&energies)
+        .map(|(f, &rms)| is_speech_frame(rms, frame_zcr(f), threshold))
+        .collect();
+    let speech_frames = raw.iter().filter(|&&s| s).count();
+
+    // Dilate speech regions: onset padding before, hangover after.
+    let mut keep = vec![false; total_frames];
+    for (i, _) in raw.iter().enumerate().filter(|(_, &s)| s) {
+        let start = i.saturating_sub(VAD_ONSET_FRAMES);
+        let end = (i + VAD_HANGOVER_FRAMES).min(total_frames - 1);
+        keep[start..=end].iter_mut().for_each(|k| *k = true);
+    }
+
+    let max_pause = (VAD_MAX_PAUSE_MS / VAD_FRAME_MS) as usize;
+    let kept_pause = (VAD_KEPT_PAUSE_MS / VAD_FRAME_MS) as usize;
+    let first = keep.iter().position(|&k| k);
+    let last = keep.iter().rposition(|&k| k);
+    let mut out = Vec::new();
+    if let (Some(first), Some(last)) = (first, last) {
+        let mut i = first;
+        while i <= last {
+            if keep[i] {
+                out.extend_from_slice(frames[i]);
+                i += 1;
+                continue;
+            }
+            let gap_end = (i..=last).find(|&j| keep[j]).unwrap_or(last + 1);
+            let gap = gap_end - i;
+            let kept = if gap > max_pause { kept_pause } else { gap };
+            for frame in &frames[i..i + kept] {
+                out.extend_from_slice(frame);
+            }
+            i = gap_end;
+        }
+    }
+
+    VadResult {
+        samples: out,
+        speech_frames,
+        total_frames,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1034,12 +2430,537 @@ mod tests {
 
     #[test]
     fn test_max_samples_for_rate() {
-        // 24 MB / 2 bytes per sample = 12,000,000 samples
-        assert_eq!(max_samples_for_rate(48000), 12_000_000);
-        assert_eq!(max_samples_for_rate(16000), 12_000_000);
-        // At 48 kHz: 12M / 48000 = 250s = 4 min 10s
-        let duration_48k = 12_000_000.0 / 48000.0;
-        assert!(duration_48k > 240.0 && duration_48k < 260.0);
+        // Same 30 min ceiling at every rate
+        assert_eq!(max_samples_for_rate(16000), 28_800_000);
+        assert_eq!(max_samples_for_rate(48000), 86_400_000);
+        let duration_44k = max_samples_for_rate(44100) as f64 / 44100.0;
+        assert!((duration_44k - 1800.0).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_max_segment_samples_fits_upload_limit() {
+        // 120 s at 16 kHz = 3.84 MB of WAV, well under MAX_WAV_BYTES
+        assert_eq!(max_segment_samples(16000), 1_920_000);
+        assert!(max_segment_samples(16000) * 2 <= MAX_WAV_BYTES);
+    }
+
+    // ── AudioEncoder / FLAC ─────────────────────────────────────────
+
+    #[test]
+    fn test_audio_encoder_from_pref() {
+        assert_eq!(AudioEncoder::from_pref("flac"), AudioEncoder::Flac);
+        assert_eq!(AudioEncoder::from_pref(" Opus "), AudioEncoder::Opus);
+        assert_eq!(AudioEncoder::from_pref("wav"), AudioEncoder::Wav);
+        assert_eq!(AudioEncoder::from_pref("mp3"), AudioEncoder::Wav);
+    }
+
+    #[test]
+    fn test_audio_encoder_mime_matches_extension() {
+        assert_eq!(AudioEncoder::Wav.mime_type(), "audio/wav");
+        assert_eq!(AudioEncoder::Flac.mime_type(), "audio/flac");
+        assert_eq!(AudioEncoder::Opus.mime_type(), "audio/ogg");
+        assert!(AudioEncoder::Flac.file_name().ends_with(".flac"));
+        assert!(AudioEncoder::Opus.file_name().ends_with(".ogg"));
+    }
+
+    #[test]
+    fn test_flac_streaminfo() {
+        let samples = vec![0.1f32; 5000];
+        let flac = write_flac_to_bytes(&samples, 16000).unwrap();
+        assert_eq!(&flac[0..4], b"fLaC");
+        assert_eq!(flac[4] & 0x7F, 0); // STREAMINFO comes first
+        assert_eq!(&flac[5..8], &[0, 0, 34]);
+        let info = &flac[8..42];
+        let rate = ((info[10] as u32) << 12) | ((info[11] as u32) << 4) | (info[12] as u32 >> 4);
+        assert_eq!(rate, 16000);
+        assert_eq!((info[12] >> 1) & 0x07, 0); // mono
+        let total = u32::from_be_bytes([info[14], info[15], info[16], info[17]]);
+        assert_eq!(total, 5000);
+    }
+
+    #[test]
+    fn test_flac_compresses_speech_and_silence() {
+        let rate = 16000;
+        let samples = voice(rate as usize * 2, rate);
+        let wav = write_wav_to_bytes(&samples, rate).unwrap();
+        let flac = write_flac_to_bytes(&samples, rate).unwrap();
+        assert!(flac.len() < wav.len() * 2 / 3, "flac {} vs wav {}", flac.len(), wav.len());
+        // Digital silence collapses to constant subframes
+        let silence = write_flac_to_bytes(&vec![0.0; rate as usize * 2], rate).unwrap();
+        assert!(silence.len() < 200);
+    }
+
+    #[test]
+    fn test_flac_noise_never_larger_than_verbatim() {
+        let samples = noise(20000, 0.9);
+        let flac = write_flac_to_bytes(&samples, 16000).unwrap();
+        // Verbatim fallback: 2 bytes/sample plus small per-frame headers
+        assert!(flac.len() < samples.len() * 2 + 200);
+    }
+
+    #[test]
+    fn test_encoded_audio_wav_passthrough() {
+        let samples = vec![0.0f32; 1600];
+        let audio = EncodedAudio::encode(&samples, 16000, AudioEncoder::Wav).unwrap();
+        assert_eq!(audio.encoder, AudioEncoder::Wav);
+        assert_eq!(audio.bytes, write_wav_to_bytes(&samples, 16000).unwrap());
+    }
+
+    // ── resample ────────────────────────────────────────────────────
+
+    fn tone(freq: f32, len: usize, rate: u32) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / rate as f32).sin() * 0.5)
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn test_resample_same_rate_is_identity() {
+        let input = tone(440.0, 1000, 16000);
+        assert_eq!(resample(&input, 16000, 16000), input);
+    }
+
+    #[test]
+    fn test_resample_empty() {
+        assert!(resample(&[], 48000, 16000).is_empty());
+    }
+
+    #[test]
+    fn test_resample_output_length() {
+        assert_eq!(resample(&vec![0.0; 48000], 48000, 16000).len(), 16000);
+        assert_eq!(resample(&vec![0.0; 44100], 44100, 16000).len(), 16000);
+        assert_eq!(resample(&vec![0.0; 8000], 8000, 16000).len(), 16000);
+    }
+
+    #[test]
+    fn test_resample_keeps_dc_level() {
+        let out = resample(&vec![0.25; 4800], 48000, 16000);
+        assert!(out.iter().all(|&s| (s - 0.25).abs() < 1e-3));
+    }
+
+    #[test]
+    fn test_resample_preserves_speech_band_tone() {
+        // 1 kHz at 48 kHz → 16 kHz: same amplitude, same frequency
+        let out = resample(&tone(1000.0, 48000, 48000), 48000, 16000);
+        let expected = tone(1000.0, out.len(), 16000);
+        let mid = &out[1000..out.len() - 1000];
+        assert!((rms(mid) - rms(&expected[1000..out.len() - 1000])).abs() < 0.01);
+        let err: f32 = mid
+            .iter()
+            .zip(&expected[1000..])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(err < 0.01, "max error {}", err);
+    }
+
+    #[test]
+    fn test_resample_rejects_above_new_nyquist() {
+        // 12 kHz cannot exist at 16 kHz; it must be filtered, not aliased to 4 kHz
+        let out = resample(&tone(12000.0, 48000, 48000), 48000, 16000);
+        assert!(rms(&out[1000..out.len() - 1000]) < 0.01);
+    }
+
+    #[test]
+    fn test_resample_44k1() {
+        let out = resample(&tone(440.0, 44100, 44100), 44100, 16000);
+        let expected = tone(440.0, out.len(), 16000);
+        let err: f32 = out[500..out.len() - 500]
+            .iter()
+            .zip(&expected[500..])
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(err < 0.01, "max error {}", err);
+    }
+
+    // ── CaptureSink ─────────────────────────────────────────────────
+
+    fn sink(channels: usize, max_samples: usize) -> CaptureSink {
+        CaptureSink {
+            buffer: Arc::new(Mutex::new(Vec::new())),
+            buffer_full: Arc::new(AtomicBool::new(false)),
+            recording: Arc::new(AtomicBool::new(true)),
+            paused: Arc::new(AtomicBool::new(false)),
+            was_recording: false,
+            pre_roll: std::collections::VecDeque::new(),
+            pre_roll_len: 0,
+            max_samples,
+            channels,
+            silence: None,
+            level: LevelMeter::new(16000, Arc::new(Mutex::new(LevelState::default()))),
+        }
+    }
+
+    #[test]
+    fn test_capture_sink_averages_channels() {
+        let mut s = sink(2, 100);
+        // Voice on channel 1 only: must not come out silent
+        s.push(&[0.0f32, 0.5, 0.0, -0.5]);
+        assert_eq!(*s.buffer.lock().unwrap(), vec![0.25, -0.25]);
+    }
+
+    #[test]
+    fn test_capture_sink_converts_i16() {
+        let mut s = sink(1, 100);
+        s.push(&[i16::MAX, 0]);
+        let buf = s.buffer.lock().unwrap();
+        assert!((buf[0] - 1.0).abs() < 1e-3);
+        assert_eq!(buf[1], 0.0);
+    }
+
+    #[test]
+    fn test_capture_sink_prepends_pre_roll() {
+        let mut s = sink(1, 100);
+        s.pre_roll_len = 3;
+        s.recording.store(false, Ordering::SeqCst);
+        s.push(&[0.1f32, 0.2, 0.3, 0.4, 0.5]);
+        assert!(s.buffer.lock().unwrap().is_empty());
+        s.recording.store(true, Ordering::SeqCst);
+        s.push(&[0.6f32]);
+        // Only the last `pre_roll_len` idle samples survive, then the live audio
+        assert_eq!(*s.buffer.lock().unwrap(), vec![0.3, 0.4, 0.5, 0.6]);
+        assert!(s.pre_roll.is_empty());
+    }
+
+    #[test]
+    fn test_capture_sink_no_pre_roll_when_not_warm() {
+        let mut s = sink(1, 100);
+        s.recording.store(false, Ordering::SeqCst);
+        s.push(&[0.1f32, 0.2]);
+        s.recording.store(true, Ordering::SeqCst);
+        s.push(&[0.3f32]);
+        assert_eq!(*s.buffer.lock().unwrap(), vec![0.3]);
+    }
+
+    #[test]
+    fn test_capture_sink_pause_keeps_buffer_and_drops_input() {
+        let mut s = sink(1, 100);
+        s.pre_roll_len = 3;
+        s.push(&[0.1f32, 0.2]);
+        s.paused.store(true, Ordering::SeqCst);
+        s.push(&[0.9f32, 0.9, 0.9]);
+        s.paused.store(false, Ordering::SeqCst);
+        s.push(&[0.3f32]);
+        // Nothing said during the pause is kept, not even as pre-roll
+        assert_eq!(*s.buffer.lock().unwrap(), vec![0.1, 0.2, 0.3]);
+    }
+
+    #[test]
+    fn test_capture_sink_pause_does_not_count_toward_cap() {
+        let mut s = sink(1, 3);
+        s.push(&[0.1f32, 0.2]);
+        s.paused.store(true, Ordering::SeqCst);
+        s.push(&[0.5f32; 10]);
+        assert!(!s.buffer_full.load(Ordering::SeqCst));
+        s.paused.store(false, Ordering::SeqCst);
+        s.push(&[0.3f32]);
+        assert_eq!(s.buffer.lock().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_capture_sink_sets_full_flag() {
+        let mut s = sink(1, 3);
+        s.push(&[0.1f32; 5]);
+        assert_eq!(s.buffer.lock().unwrap().len(), 3);
+        assert!(s.buffer_full.load(Ordering::SeqCst));
+    }
+
+    // ── keep_warm_after_stop ────────────────────────────────────────
+
+    fn warm_settings(always_warm: bool) -> StreamSettings {
+        StreamSettings { device_id: None, silence_timeout_secs: Some(3), always_warm }
+    }
+
+    #[test]
+    fn test_warm_stream_stays_open_after_stop() {
+        let open = warm_settings(true);
+        assert!(keep_warm_after_stop(Some(&open), &warm_settings(true)));
+    }
+
+    #[test]
+    fn test_warm_disabled_while_recording_closes_stream_on_stop() {
+        let open = warm_settings(true);
+        assert!(!keep_warm_after_stop(Some(&open), &warm_settings(false)));
+    }
+
+    #[test]
+    fn test_warm_stream_with_stale_settings_closes_on_stop() {
+        let open = warm_settings(true);
+        let mut current = warm_settings(true);
+        current.device_id = Some("usb-mic".to_string());
+        assert!(!keep_warm_after_stop(Some(&open), &current));
+        assert!(!keep_warm_after_stop(None, &current));
+    }
+
+    // ── LevelMeter / band_levels ────────────────────────────────────
+
+    #[test]
+    fn test_level_meter_publishes_each_window() {
+        let shared = Arc::new(Mutex::new(LevelState::default()));
+        let mut meter = LevelMeter::new(16000, shared.clone());
+        // 50 ms at 16 kHz = 800 samples per window
+        for s in voice(1600, 16000) {
+            meter.push(s);
+        }
+        let level = shared.lock().unwrap();
+        assert_eq!(level.seq, 2);
+        assert!((level.rms - 0.3 / 2f32.sqrt()).abs() < 0.01);
+        assert!(level.peak > 0.29 && level.peak <= 0.3);
+        assert!(!level.clipping);
+        assert_eq!(level.window.len(), 800);
+    }
+
+    #[test]
+    fn test_level_meter_flags_clipping() {
+        let shared = Arc::new(Mutex::new(LevelState::default()));
+        let mut meter = LevelMeter::new(16000, shared.clone());
+        for _ in 0..800 {
+            meter.push(1.0);
+        }
+        assert!(shared.lock().unwrap().clipping);
+    }
+
+    #[test]
+    fn test_level_meter_tracks_max_rms_until_reset() {
+        let shared = Arc::new(Mutex::new(LevelState::default()));
+        let mut meter = LevelMeter::new(16000, shared.clone());
+        for s in voice(800, 16000).into_iter().chain(std::iter::repeat(0.0).take(800)) {
+            meter.push(s);
+        }
+        assert!(shared.lock().unwrap().max_rms > 0.2);
+        meter.reset();
+        for _ in 0..800 {
+            meter.push(0.0);
+        }
+        assert_eq!(shared.lock().unwrap().max_rms, 0.0);
+    }
+
+    #[test]
+    fn test_band_levels_peak_at_tone_frequency() {
+        let rate = 16000;
+        let window: Vec<f32> = (0..800)
+            .map(|i| (2.0 * std::f32::consts::PI * 1000.0 * i as f32 / rate as f32).sin())
+            .collect();
+        let bands = band_levels(&window, rate);
+        assert_eq!(bands.len(), SPECTRUM_BANDS_HZ.len());
+        let loudest = bands
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(i, _)| i)
+            .unwrap();
+        assert_eq!(SPECTRUM_BANDS_HZ[loudest], 1000.0);
+        assert!(bands[loudest] > 0.9);
+        // 8 kHz is Nyquist at 16 kHz: not measurable
+        assert_eq!(*bands.last().unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_band_levels_empty_window() {
+        assert!(band_levels(&[], 16000).iter().all(|&b| b == 0.0));
+    }
+
+    // ── split_at_silences ───────────────────────────────────────────
+
+    #[test]
+    fn test_split_short_recording_single_segment() {
+        let samples = voice(16000 * 5, 16000);
+        assert_eq!(split_at_silences(&samples, 16000, 16000 * 10), vec![0..samples.len()]);
+        assert!(split_at_silences(&[], 16000, 16000).is_empty());
+    }
+
+    #[test]
+    fn test_split_cuts_in_pause() {
+        let rate = 16000;
+        // 8 s voice, 0.5 s pause, 8 s voice; max 10 s per segment
+        let mut samples = voice(rate as usize * 8, rate);
+        let pause_start = samples.len();
+        samples.extend(noise(rate as usize / 2, 0.0003));
+        let pause_end = samples.len();
+        samples.extend(voice(rate as usize * 8, rate));
+        let ranges = split_at_silences(&samples, rate, rate as usize * 10);
+        assert_eq!(ranges.len(), 2);
+        assert!(ranges[0].end > pause_start && ranges[0].end < pause_end, "cut at {}", ranges[0].end);
+    }
+
+    #[test]
+    fn test_split_covers_input_within_limit() {
+        let rate = 16000;
+        let max_len = rate as usize * 3;
+        let samples = voice(rate as usize * 20 + 123, rate);
+        let ranges = split_at_silences(&samples, rate, max_len);
+        assert_eq!(ranges.first().unwrap().start, 0);
+        assert_eq!(ranges.last().unwrap().end, samples.len());
+        for pair in ranges.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert!(ranges.iter().all(|r| !r.is_empty() && r.len() <= max_len));
+    }
+
+    // ── detect_voice_activity ───────────────────────────────────────
+
+    /// Low-level deterministic noise (LCG) — stands in for room tone.
+    fn noise(len: usize, amplitude: f32) -> Vec<f32> {
+        let mut state: u32 = 12345;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                ((state >> 16) as f32 / 32768.0 - 1.0) * amplitude
+            })
+            .collect()
+    }
+
+    /// 200 Hz tone — voiced, low zero-crossing rate, well above the noise floor.
+    fn voice(len: usize, rate: u32) -> Vec<f32> {
+        (0..len)
+            .map(|i| (2.0 * std::f32::consts::PI * 200.0 * i as f32 / rate as f32).sin() * 0.3)
+            .collect()
+    }
+
+    #[test]
+    fn test_vad_empty_input() {
+        let vad = detect_voice_activity(&[], 16000);
+        assert!(vad.samples.is_empty());
+        assert!(!vad.has_speech());
+        assert_eq!(vad.speech_ratio(), 0.0);
+    }
+
+    #[test]
+    fn test_vad_silence_has_no_speech() {
+        let vad = detect_voice_activity(&noise(16000 * 2, 0.0003), 16000);
+        assert!(!vad.has_speech());
+        assert!(vad.samples.is_empty());
+    }
+
+    #[test]
+    fn test_vad_trims_leading_and_trailing_silence() {
+        let rate = 16000;
+        let mut samples = noise(rate as usize, 0.0003);
+        samples.extend(voice(rate as usize, rate));
+        samples.extend(noise(rate as usize, 0.0003));
+        let vad = detect_voice_activity(&samples, rate);
+        assert!(vad.has_speech());
+        // 1 s of voice + onset padding + hangover, far less than the 3 s input
+        let kept_secs = vad.samples.len() as f32 / rate as f32;
+        assert!(kept_secs > 1.0 && kept_secs < 1.5, "kept {}s", kept_secs);
+        assert!(vad.speech_ratio() > 0.25 && vad.speech_ratio() < 0.45);
+    }
+
+    #[test]
+    fn test_vad_collapses_long_internal_pause() {
+        let rate = 16000;
+        let mut samples = voice(rate as usize / 2, rate);
+        samples.extend(noise(rate as usize * 3, 0.0003));
+        samples.extend(voice(rate as usize / 2, rate));
+        let vad = detect_voice_activity(&samples, rate);
+        let kept_secs = vad.samples.len() as f32 / rate as f32;
+        // 2 × 0.5 s of voice + a pause collapsed to ~0.3 s (+ padding)
+        assert!(kept_secs < 1.8, "kept {}s", kept_secs);
+        assert!(kept_secs > 1.0, "kept {}s", kept_secs);
+    }
+
+    #[test]
+    fn test_vad_keeps_short_internal_pause() {
+        let rate = 16000;
+        let pause = rate as usize / 2; // 500 ms < VAD_MAX_PAUSE_MS
+        let mut samples = voice(rate as usize / 2, rate);
+        samples.extend(noise(pause, 0.0003));
+        samples.extend(voice(rate as usize / 2, rate));
+        let vad = detect_voice_activity(&samples, rate);
+        // Nothing to trim at the edges, short pause kept intact
+        let diff = samples.len() as i64 - vad.samples.len() as i64;
+        assert!(diff.abs() < vad_frame_len(rate) as i64);
+    }
+
+    #[test]
+    fn test_vad_rejects_quiet_hiss() {
+        // High zero-crossing rate just above the energy threshold: hiss, not voice
+        let mut samples = noise(16000, 0.0003);
+        samples.extend((0..16000).map(|i| if i % 2 == 0 { 0.003 } else { -0.003 }));
+        let vad = detect_voice_activity(&samples, 16000);
+        assert!(!vad.has_speech());
+    }
+
+    #[test]
+    fn test_frame_zcr() {
+        assert_eq!(frame_zcr(&[1.0, -1.0, 1.0, -1.0, 1.0]), 1.0);
+        assert_eq!(frame_zcr(&[0.5, 0.4, 0.3]), 0.0);
+        assert_eq!(frame_zcr(&[0.5]), 0.0);
+    }
+
+    // ── SilenceDetector ─────────────────────────────────────────────
+
+    fn run_detector(samples: &[f32], rate: u32, timeout_secs: u32) -> bool {
+        let fired = Arc::new(AtomicBool::new(false));
+        let mut det = SilenceDetector::new(rate, timeout_secs, fired.clone());
+        for &s in samples {
+            det.push(s);
+        }
+        fired.load(Ordering::SeqCst)
+    }
+
+    #[test]
+    fn test_silence_detector_fires_after_timeout() {
+        let rate = 16000;
+        let mut samples = noise(rate as usize, 0.0003);
+        samples.extend(voice(rate as usize, rate));
+        samples.extend(noise(rate as usize * 2 + 1000, 0.0003));
+        assert!(run_detector(&samples, rate, 2));
+    }
+
+    #[test]
+    fn test_silence_detector_waits_full_timeout() {
+        let rate = 16000;
+        let mut samples = voice(rate as usize, rate);
+        samples.extend(noise(rate as usize * 3 / 2, 0.0003));
+        assert!(!run_detector(&samples, rate, 2));
+    }
+
+    #[test]
+    fn test_silence_detector_ignores_silence_before_speech() {
+        // Hands-free users may take a while to start talking
+        let rate = 16000;
+        assert!(!run_detector(&noise(rate as usize * 5, 0.0003), rate, 2));
+    }
+
+    #[test]
+    fn test_silence_detector_resets_on_speech() {
+        let rate = 16000;
+        let mut samples = voice(rate as usize / 2, rate);
+        samples.extend(noise(rate as usize * 3 / 2, 0.0003));
+        samples.extend(voice(rate as usize / 2, rate));
+        samples.extend(noise(rate as usize * 3 / 2, 0.0003));
+        assert!(!run_detector(&samples, rate, 2));
+    }
+
+    // ── device_list_changed ─────────────────────────────────────────
+
+    fn ids(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn test_device_list_unchanged_ignores_order() {
+        let before = ids(&["MacBook Pro Microphone", "AirPods"]);
+        let after = ids(&["AirPods", "MacBook Pro Microphone"]);
+        assert!(!device_list_changed(&before, &after));
+    }
+
+    #[test]
+    fn test_device_list_changed_on_unplug() {
+        let before = ids(&["MacBook Pro Microphone", "AirPods"]);
+        let after = ids(&["MacBook Pro Microphone"]);
+        assert!(device_list_changed(&before, &after));
+    }
+
+    #[test]
+    fn test_device_list_changed_on_plug() {
+        let before = ids(&["MacBook Pro Microphone"]);
+        let after = ids(&["MacBook Pro Microphone", "USB Audio"]);
+        assert!(device_list_changed(&before, &after));
     }
 
     // ── RecorderState ───────────────────────────────────────────────
@@ -1066,6 +2987,35 @@ mod tests {
         assert!(!state.is_capturing());
     }
 
+    #[test]
+    fn test_recorder_state_pause_requires_capture() {
+        let state = RecorderState::default();
+        assert_eq!(state.pause_capture(), Ok(false));
+        assert!(!state.is_paused());
+    }
+
+    #[test]
+    fn test_recorder_state_pause_and_resume() {
+        let state = RecorderState::default();
+        state.set_capturing(true);
+        assert_eq!(state.pause_capture(), Ok(true));
+        assert!(state.is_paused());
+        assert_eq!(state.pause_capture(), Ok(false));
+        assert_eq!(state.resume_capture(), Ok(true));
+        assert!(!state.is_paused());
+        assert_eq!(state.resume_capture(), Ok(false));
+    }
+
+    #[test]
+    fn test_recorder_state_stop_clears_pause() {
+        let state = RecorderState::default();
+        state.set_capturing(true);
+        state.pause_capture().unwrap();
+        state.stop_capture().unwrap();
+        assert!(!state.is_paused());
+        assert!(!state.is_capturing());
+    }
+
     #[test]
     fn test_recorder_state_take_previous_app_none_by_default() {
         let state = RecorderState::default();
@@ -1089,44 +3039,44 @@ mod tests {
 
     #[test]
     fn test_light_fast_path_empty() {
-        assert_eq!(light_fast_path(""), "");
-        assert_eq!(light_fast_path("   "), "");
+        assert_eq!(light_fast_path("", None), "");
+        assert_eq!(light_fast_path("   ", None), "");
     }
 
     #[test]
     fn test_light_fast_path_capitalizes_first_letter() {
-        let result = light_fast_path("hello world");
+        let result = light_fast_path("hello world", None);
         assert!(result.starts_with('H'));
     }
 
     #[test]
     fn test_light_fast_path_adds_period() {
-        let result = light_fast_path("hello world");
+        let result = light_fast_path("hello world", None);
         assert!(result.ends_with('.'));
     }
 
     #[test]
     fn test_light_fast_path_keeps_existing_punctuation() {
-        let result = light_fast_path("is this a question?");
+        let result = light_fast_path("is this a question?", None);
         assert!(result.ends_with('?'));
         assert!(!result.ends_with("?."));
     }
 
     #[test]
     fn test_light_fast_path_keeps_exclamation() {
-        let result = light_fast_path("that is amazing!");
+        let result = light_fast_path("that is amazing!", None);
         assert!(result.ends_with('!'));
     }
 
     #[test]
     fn test_light_fast_path_removes_french_fillers() {
-        let result = light_fast_path("euh je pense que euh c'est bon");
+        let result = light_fast_path("euh je pense que euh c'est bon", None);
         assert!(!result.to_lowercase().contains("euh"));
     }
 
     #[test]
     fn test_light_fast_path_removes_english_fillers() {
-        let result = light_fast_path("um I think like basically it works");
+        let result = light_fast_path("um I think like basically it works", None);
         assert!(!result.to_lowercase().contains(" um "));
         assert!(!result.to_lowercase().contains(" like "));
         assert!(!result.to_lowercase().contains("basically"));
@@ -1134,13 +3084,13 @@ mod tests {
 
     #[test]
     fn test_light_fast_path_removes_multi_word_fillers() {
-        let result = light_fast_path("you know the meeting is tomorrow");
+        let result = light_fast_path("you know the meeting is tomorrow", None);
         assert!(!result.to_lowercase().contains("you know"));
     }
 
     #[test]
     fn test_light_fast_path_cleans_orphan_spaces() {
-        let result = light_fast_path("euh hello  world");
+        let result = light_fast_path("euh hello  world", None);
         assert!(!result.contains("  "));
     }
 
@@ -1148,25 +3098,25 @@ mod tests {
 
     #[test]
     fn test_strip_fillers_empty() {
-        assert_eq!(strip_fillers(""), "");
-        assert_eq!(strip_fillers("   "), "");
+        assert_eq!(strip_fillers("", None), "");
+        assert_eq!(strip_fillers("   ", None), "");
     }
 
     #[test]
     fn test_strip_fillers_no_capitalization() {
-        let result = strip_fillers("hello world");
+        let result = strip_fillers("hello world", None);
         assert_eq!(result, "hello world");
     }
 
     #[test]
     fn test_strip_fillers_no_punctuation_added() {
-        let result = strip_fillers("hello world");
+        let result = strip_fillers("hello world", None);
         assert!(!result.ends_with('.'));
     }
 
     #[test]
     fn test_strip_fillers_removes_french() {
-        let result = strip_fillers("euh je pense que en fait c'est bon");
+        let result = strip_fillers("euh je pense que en fait c'est bon", None);
         assert!(!result.to_lowercase().contains("euh"));
         assert!(!result.contains("en fait"));
         assert!(result.contains("je pense que"));
@@ -1174,7 +3124,7 @@ mod tests {
 
     #[test]
     fn test_strip_fillers_removes_english() {
-        let result = strip_fillers("um I think like basically it works");
+        let result = strip_fillers("um I think like basically it works", None);
         assert!(!result.contains(" um "));
         assert!(!result.contains(" like "));
         assert!(!result.contains("basically"));
@@ -1184,7 +3134,7 @@ mod tests {
 
     #[test]
     fn test_strip_fillers_removes_new_french_fillers() {
-        let result = strip_fillers("alors je vais en mode te montrer du coup le truc");
+        let result = strip_fillers("alors je vais en mode te montrer du coup le truc", None);
         assert!(!result.to_lowercase().contains("alors"));
         assert!(!result.contains("en mode"));
         assert!(!result.contains("du coup"));
@@ -1192,25 +3142,32 @@ mod tests {
 
     #[test]
     fn test_strip_fillers_removes_new_english_fillers() {
-        let result = strip_fillers("honestly I think to be honest it works");
+        let result = strip_fillers("honestly I think to be honest it works", None);
         assert!(!result.to_lowercase().contains("honestly"));
         assert!(!result.contains("to be honest"));
     }
 
     #[test]
     fn test_strip_fillers_longest_match_first() {
-        let result = strip_fillers("tu vois ce que je veux dire c'est cool");
+        let result = strip_fillers("tu vois ce que je veux dire c'est cool", None);
         assert!(!result.contains("tu vois ce que je veux dire"));
         assert!(result.contains("c'est cool"));
     }
 
     #[test]
     fn test_strip_fillers_cleans_orphan_commas() {
-        let result = strip_fillers("euh, hello world");
+        let result = strip_fillers("euh, hello world", None);
         assert!(!result.contains(" ,"));
         assert!(!result.contains(",,"));
     }
 
+    #[test]
+    fn test_strip_fillers_follows_detected_language() {
+        assert_eq!(strip_fillers("I like donc pastries", Some("en")), "I donc pastries");
+        assert_eq!(strip_fillers("j'aime like donc les gâteaux", Some("fr")), "j'aime like les gâteaux");
+        assert_eq!(strip_fillers("I like donc pastries", None), "I pastries");
+    }
+
     // ── find_reflection_separator ───────────────────────────────────
 
     #[test]
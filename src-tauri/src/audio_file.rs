@@ -0,0 +1,273 @@
+/// Decoding of audio files for `transcribe_file`: symphonia, plus libopus for Ogg Opus voice notes.
+use std::path::Path;
+
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, Packet};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+/// File extensions accepted by `decode_file` (lowercase).
+pub const SUPPORTED_EXTENSIONS: &[&str] = &["wav", "flac", "mp3", "ogg", "oga", "opus"];
+
+/// Ogg Opus always decodes at 48 kHz.
+const OPUS_RATE: u32 = 48_000;
+/// Longest Opus packet: 120 ms at 48 kHz.
+const OPUS_MAX_FRAME: usize = 5760;
+
+/// Mono samples at the file's native rate.
+pub struct DecodedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+}
+
+/// Downmix interleaved frames to mono by averaging channels (same as live capture).
+fn downmix_into(interleaved: &[f32], channels: usize, out: &mut Vec<f32>) {
+    let channels = channels.max(1);
+    out.extend(
+        interleaved
+            .chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32),
+    );
+}
+
+enum PacketDecoder {
+    Symphonia {
+        decoder: Box<dyn symphonia::core::codecs::Decoder>,
+        buf: Option<SampleBuffer<f32>>,
+    },
+    Opus {
+        decoder: audiopus::coder::Decoder,
+        pcm: Vec<f32>,
+        /// Pre-skip samples (encoder lookahead) still to drop from the start.
+        skip: usize,
+    },
+}
+
+impl PacketDecoder {
+    /// Decode one packet, appending mono samples to `out`. Corrupt frames are skipped.
+    fn decode(&mut self, packet: &Packet, out: &mut Vec<f32>) -> Result<(), String> {
+        match self {
+            PacketDecoder::Symphonia { decoder, buf } => {
+                let decoded = match decoder.decode(packet) {
+                    Ok(d) => d,
+                    Err(SymphoniaError::DecodeError(_)) => return Ok(()),
+                    Err(e) => return Err(e.to_string()),
+                };
+                let spec = *decoded.spec();
+                let needed = decoded.capacity() as u64;
+                if buf.as_ref().map_or(true, |b| (b.capacity() as u64) < needed) {
+                    *buf = Some(SampleBuffer::new(needed, spec));
+                }
+                if let Some(b) = buf.as_mut() {
+                    b.copy_interleaved_ref(decoded);
+                    downmix_into(b.samples(), spec.channels.count(), out);
+                }
+                Ok(())
+            }
+            PacketDecoder::Opus { decoder, pcm, skip } => {
+                let input = audiopus::packet::Packet::try_from(&packet.data[..])
+                    .map_err(|e| e.to_string())?;
+                let output = audiopus::MutSignals::try_from(&mut pcm[..]).map_err(|e| e.to_string())?;
+                let len = match decoder.decode_float(Some(input), output, false) {
+                    Ok(n) => n,
+                    Err(_) => return Ok(()),
+                };
+                let dropped = (*skip).min(len);
+                *skip -= dropped;
+                out.extend_from_slice(&pcm[dropped..len]);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Decode an audio file to mono. Files longer than `max_secs` are rejected.
+pub fn decode_file(path: &Path, max_secs: u64) -> Result<DecodedAudio, String> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .filter(|e| SUPPORTED_EXTENSIONS.contains(&e.as_str()))
+        .ok_or_else(|| format!("Format audio non pris en charge : {}", path.display()))?;
+    let file = std::fs::File::open(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    decode_stream(MediaSourceStream::new(Box::new(file), Default::default()), &ext, max_secs)
+}
+
+/// Decode an in-memory WAV, FLAC or Ogg payload (an `EncodedAudio` segment) to mono.
+#[cfg_attr(not(feature = "local-whisper"), allow(dead_code))]
+pub fn decode_bytes(bytes: Vec<u8>, ext: &str) -> Result<DecodedAudio, String> {
+    let cursor = std::io::Cursor::new(bytes);
+    // Segments are short; the limit only guards the length arithmetic
+    decode_stream(MediaSourceStream::new(Box::new(cursor), Default::default()), ext, 3600)
+}
+
+fn decode_stream(mss: MediaSourceStream, ext: &str, max_secs: u64) -> Result<DecodedAudio, String> {
+    let mut hint = Hint::new();
+    hint.with_extension(ext);
+    let probed = symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| format!("Fichier audio illisible : {}", e))?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .ok_or_else(|| "Aucune piste audio dans le fichier".to_string())?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    let (mut decoder, sample_rate) = if params.codec == CODEC_TYPE_OPUS {
+        // Ask libopus for mono directly: it downmixes stereo streams itself.
+        let decoder = audiopus::coder::Decoder::new(audiopus::SampleRate::Hz48000, audiopus::Channels::Mono)
+            .map_err(|e| format!("Opus decoder init failed: {}", e))?;
+        let skip = params.delay.unwrap_or(0) as usize;
+        (
+            PacketDecoder::Opus { decoder, pcm: vec![0.0; OPUS_MAX_FRAME], skip },
+            OPUS_RATE,
+        )
+    } else {
+        let sample_rate = params
+            .sample_rate
+            .ok_or_else(|| "Fréquence d'échantillonnage inconnue".to_string())?;
+        let decoder = symphonia::default::get_codecs()
+            .make(&params, &DecoderOptions::default())
+            .map_err(|e| format!("Codec non pris en charge : {}", e))?;
+        (PacketDecoder::Symphonia { decoder, buf: None }, sample_rate)
+    };
+
+    let max_samples = max_secs as usize * sample_rate as usize;
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(p) => p,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            // Chained Ogg streams etc.: keep what was decoded so far.
+            Err(SymphoniaError::ResetRequired) => break,
+            Err(e) => return Err(format!("Fichier audio illisible : {}", e)),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        decoder.decode(&packet, &mut samples)?;
+        if samples.len() > max_samples {
+            return Err(format!("Fichier trop long (max {} min).", max_secs / 60));
+        }
+    }
+    Ok(DecodedAudio { samples, sample_rate })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(len: usize, rate: u32) -> Vec<f32> {
+        (0..len)
+            .map(|i| 0.3 * (2.0 * std::f32::consts::PI * 200.0 * i as f32 / rate as f32).sin())
+            .collect()
+    }
+
+    // ── downmix_into ────────────────────────────────────────────────
+
+    #[test]
+    fn test_downmix_averages_channels() {
+        let mut out = Vec::new();
+        downmix_into(&[0.0, 0.5, 0.2, 0.4], 2, &mut out);
+        assert_eq!(out, vec![0.25, 0.3]);
+    }
+
+    #[test]
+    fn test_downmix_mono_is_identity() {
+        let mut out = vec![1.0];
+        downmix_into(&[0.1, 0.2], 1, &mut out);
+        assert_eq!(out, vec![1.0, 0.1, 0.2]);
+    }
+
+    // ── decode_file ─────────────────────────────────────────────────
+
+    #[test]
+    fn test_decode_rejects_unknown_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memo.txt");
+        std::fs::write(&path, b"hello").unwrap();
+        assert!(decode_file(&path, 60).is_err());
+    }
+
+    #[test]
+    fn test_decode_stereo_wav_downmixes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memo.wav");
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for _ in 0..1000 {
+            writer.write_sample(i16::MAX / 2).unwrap();
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let decoded = decode_file(&path, 60).unwrap();
+        assert_eq!(decoded.sample_rate, 44100);
+        assert_eq!(decoded.samples.len(), 1000);
+        assert!((decoded.samples[500] - 0.25).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_decode_flac_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memo.flac");
+        let input = sine(16000, 16000);
+        std::fs::write(&path, crate::audio::write_flac_to_bytes(&input, 16000).unwrap()).unwrap();
+
+        let decoded = decode_file(&path, 60).unwrap();
+        assert_eq!(decoded.sample_rate, 16000);
+        assert_eq!(decoded.samples.len(), input.len());
+        let err = input
+            .iter()
+            .zip(&decoded.samples)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max);
+        assert!(err < 1e-3, "max error {}", err);
+    }
+
+    #[test]
+    fn test_decode_ogg_opus_drops_pre_skip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memo.ogg");
+        let input = sine(16000, 16000);
+        std::fs::write(&path, crate::audio::write_opus_to_bytes(&input, 16000).unwrap()).unwrap();
+
+        let decoded = decode_file(&path, 60).unwrap();
+        assert_eq!(decoded.sample_rate, 48000);
+        // One second of audio, give or take the padding of the last frame
+        let len = decoded.samples.len();
+        assert!((48000..48000 + 960 * 2).contains(&len), "len {}", len);
+        let rms = (decoded.samples.iter().map(|s| s * s).sum::<f32>() / len as f32).sqrt();
+        assert!(rms > 0.1, "rms {}", rms);
+    }
+
+    #[test]
+    fn test_decode_bytes_flac() {
+        let input = sine(8000, 16000);
+        let bytes = crate::audio::write_flac_to_bytes(&input, 16000).unwrap();
+        let decoded = decode_bytes(bytes, "flac").unwrap();
+        assert_eq!(decoded.sample_rate, 16000);
+        assert_eq!(decoded.samples.len(), input.len());
+    }
+
+    #[test]
+    fn test_decode_rejects_too_long() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("memo.flac");
+        let input = sine(16000 * 3, 16000);
+        std::fs::write(&path, crate::audio::write_flac_to_bytes(&input, 16000).unwrap()).unwrap();
+        assert!(decode_file(&path, 2).is_err());
+    }
+}
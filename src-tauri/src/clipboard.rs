@@ -94,12 +94,10 @@ pub fn activate_app(_bundle_id: &str) -> bool {
     false
 }
 
-/// Check if the frontmost application has a focused text input field.
-/// Uses lsappinfo to get the frontmost app PID, then macOS Accessibility API
-/// (AXUIElementCreateApplication + AXFocusedUIElement) to check the focused element's role.
+/// PID of the frontmost app via lsappinfo (instant, no permission needed).
+/// `caller` only tags the debug log.
 #[cfg(target_os = "macos")]
-pub fn has_focused_text_field() -> bool {
-    // Step 1: get PID of frontmost app via lsappinfo
+fn frontmost_pid(caller: &str) -> Option<i32> {
     let front = std::process::Command::new("lsappinfo")
         .arg("front")
         .output()
@@ -109,8 +107,8 @@ pub fn has_focused_text_field() -> bool {
         .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
         .unwrap_or_default();
     if asn.is_empty() {
-        log_debug("[has_focused_text_field] no front ASN");
-        return false;
+        log_debug(&format!("[{}] no front ASN", caller));
+        return None;
     }
     let pid_output = std::process::Command::new("lsappinfo")
         .args(["info", "-only", "pid", &asn])
@@ -127,9 +125,21 @@ pub fn has_focused_text_field() -> bool {
         .and_then(|s| s.trim().trim_matches('"').parse().ok())
         .unwrap_or(0);
     if pid == 0 {
-        log_debug(&format!("[has_focused_text_field] no PID from {}", pid_str));
-        return false;
+        log_debug(&format!("[{}] no PID from {}", caller, pid_str));
+        return None;
     }
+    Some(pid)
+}
+
+/// Check if the frontmost application has a focused text input field.
+/// Uses lsappinfo to get the frontmost app PID, then macOS Accessibility API
+/// (AXUIElementCreateApplication + AXFocusedUIElement) to check the focused element's role.
+#[cfg(target_os = "macos")]
+pub fn has_focused_text_field() -> bool {
+    // Step 1: get PID of frontmost app via lsappinfo
+    let Some(pid) = frontmost_pid("has_focused_text_field") else {
+        return false;
+    };
 
     #[link(name = "ApplicationServices", kind = "framework")]
     extern "C" {
@@ -268,35 +278,9 @@ pub fn insert_text_via_ax(text: &str) -> Result<bool, String> {
     }
 
     // Get PID of frontmost app via lsappinfo
-    let front = std::process::Command::new("lsappinfo")
-        .arg("front")
-        .output()
-        .ok();
-    let asn = front
-        .as_ref()
-        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
-        .unwrap_or_default();
-    if asn.is_empty() {
-        log_debug("[insert_text_via_ax] no front ASN");
+    let Some(pid) = frontmost_pid("insert_text_via_ax") else {
         return Ok(false);
-    }
-    let pid_output = std::process::Command::new("lsappinfo")
-        .args(["info", "-only", "pid", &asn])
-        .output()
-        .ok();
-    let pid_str = pid_output
-        .as_ref()
-        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
-        .unwrap_or_default();
-    let pid: i32 = pid_str
-        .split('=')
-        .nth(1)
-        .and_then(|s| s.trim().trim_matches('"').parse().ok())
-        .unwrap_or(0);
-    if pid == 0 {
-        log_debug(&format!("[insert_text_via_ax] no PID from {}", pid_str));
-        return Ok(false);
-    }
+    };
 
     unsafe {
         // Get the app AX element
@@ -353,6 +337,142 @@ pub fn insert_text_via_ax(_text: &str) -> Result<bool, String> {
     Ok(false)
 }
 
+/// Up to `max_chars` UTF-16 units before the cursor in the focused text field, read via
+/// the Accessibility API (AXValue + AXSelectedTextRange) — used as Whisper prompt context.
+///
+/// Returns `Some("")` for an empty field (or a cursor at its start), `None` when nothing
+/// could be read: no permission, no text element, or a password field.
+#[cfg(target_os = "macos")]
+pub fn text_before_cursor(max_chars: usize) -> Option<String> {
+    #[repr(C)]
+    struct CFRange {
+        location: isize,
+        length: isize,
+    }
+    /// `kAXValueCFRangeType`
+    const AX_VALUE_CF_RANGE_TYPE: u32 = 4;
+    const UTF8_ENCODING: u32 = 0x08000100;
+
+    #[link(name = "ApplicationServices", kind = "framework")]
+    extern "C" {
+        fn AXIsProcessTrusted() -> bool;
+        fn AXUIElementCreateApplication(pid: i32) -> *mut std::ffi::c_void;
+        fn AXUIElementCopyAttributeValue(
+            element: *const std::ffi::c_void,
+            attribute: *const std::ffi::c_void,
+            value: *mut *mut std::ffi::c_void,
+        ) -> i32;
+        fn AXValueGetValue(value: *const std::ffi::c_void, value_type: u32, out: *mut std::ffi::c_void) -> bool;
+    }
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFRelease(cf: *mut std::ffi::c_void);
+        fn CFGetTypeID(cf: *const std::ffi::c_void) -> usize;
+        fn CFStringGetTypeID() -> usize;
+        fn CFStringGetLength(s: *const std::ffi::c_void) -> isize;
+        fn CFStringGetCharacters(s: *const std::ffi::c_void, range: CFRange, buf: *mut u16);
+        fn CFStringGetCString(
+            s: *const std::ffi::c_void,
+            buf: *mut u8,
+            buf_size: isize,
+            encoding: u32,
+        ) -> bool;
+        fn CFStringCreateWithCString(
+            alloc: *const std::ffi::c_void,
+            c_str: *const u8,
+            encoding: u32,
+        ) -> *mut std::ffi::c_void;
+    }
+
+    fn cfstr(s: &str) -> *mut std::ffi::c_void {
+        let c = std::ffi::CString::new(s).unwrap();
+        unsafe {
+            CFStringCreateWithCString(std::ptr::null(), c.as_ptr() as *const u8, UTF8_ENCODING)
+        }
+    }
+
+    /// Copy an attribute of `element`; the caller releases the result.
+    unsafe fn copy_attribute(element: *const std::ffi::c_void, name: &str) -> Option<*mut std::ffi::c_void> {
+        let attr = cfstr(name);
+        let mut value: *mut std::ffi::c_void = std::ptr::null_mut();
+        let err = AXUIElementCopyAttributeValue(element, attr, &mut value);
+        CFRelease(attr);
+        (err == 0 && !value.is_null()).then_some(value)
+    }
+
+    unsafe fn string_attribute(element: *const std::ffi::c_void, name: &str) -> Option<String> {
+        let value = copy_attribute(element, name)?;
+        let mut buf = [0u8; 128];
+        let ok = CFGetTypeID(value) == CFStringGetTypeID()
+            && CFStringGetCString(value, buf.as_mut_ptr(), 128, UTF8_ENCODING);
+        CFRelease(value);
+        ok.then(|| {
+            std::ffi::CStr::from_ptr(buf.as_ptr() as *const i8)
+                .to_string_lossy()
+                .to_string()
+        })
+    }
+
+    if !unsafe { AXIsProcessTrusted() } {
+        log_debug("[text_before_cursor] AXIsProcessTrusted=false, skipping");
+        return None;
+    }
+    let pid = frontmost_pid("text_before_cursor")?;
+
+    unsafe {
+        let ax_app = AXUIElementCreateApplication(pid);
+        if ax_app.is_null() {
+            return None;
+        }
+        let focused = copy_attribute(ax_app, "AXFocusedUIElement");
+        CFRelease(ax_app);
+        let focused = focused?;
+
+        // Never read a password field
+        let secure = [string_attribute(focused, "AXRole"), string_attribute(focused, "AXSubrole")]
+            .iter()
+            .any(|r| r.as_deref() == Some("AXSecureTextField"));
+        let value = if secure { None } else { copy_attribute(focused, "AXValue") };
+        let range = if secure { None } else { copy_attribute(focused, "AXSelectedTextRange") };
+        CFRelease(focused);
+
+        let mut text = None;
+        if let (Some(value), Some(range)) = (value, range) {
+            let mut selection = CFRange { location: 0, length: 0 };
+            let has_range = AXValueGetValue(
+                range,
+                AX_VALUE_CF_RANGE_TYPE,
+                &mut selection as *mut CFRange as *mut std::ffi::c_void,
+            );
+            if has_range && CFGetTypeID(value) == CFStringGetTypeID() {
+                // Offsets are UTF-16 units
+                let end = selection.location.clamp(0, CFStringGetLength(value));
+                let start = (end - max_chars as isize).max(0);
+                let mut buf = vec![0u16; (end - start) as usize];
+                CFStringGetCharacters(value, CFRange { location: start, length: end - start }, buf.as_mut_ptr());
+                // The window may start on the second half of a surrogate pair: skip it
+                let skip = buf.first().is_some_and(|u| (0xDC00..0xE000).contains(u)) as usize;
+                text = Some(String::from_utf16_lossy(&buf[skip..]));
+            }
+        }
+        for v in [value, range].into_iter().flatten() {
+            CFRelease(v);
+        }
+        log_debug(&format!(
+            "[text_before_cursor] pid={} secure={} chars={:?}",
+            pid,
+            secure,
+            text.as_ref().map(|t| t.chars().count())
+        ));
+        text
+    }
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn text_before_cursor(_max_chars: usize) -> Option<String> {
+    None
+}
+
 /// Send Cmd+V (macOS) or Ctrl+V (other) to the currently focused application.
 /// On macOS, uses CGEvent API directly — posts to the session event stream,
 /// delivered to whichever app currently has keyboard focus.
@@ -1,9 +1,21 @@
+use crate::transcription_provider::TimedWord;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fs;
 use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
 use tauri::Manager;
 
+/// Held across every load-modify-save of dictionary.json, so the settings commands and
+/// the usage updates of `mark_used` never overwrite each other's changes.
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+fn store_lock() -> MutexGuard<'static, ()> {
+    STORE_LOCK
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DictionaryEntry {
     pub id: String,
@@ -15,6 +27,9 @@ pub struct DictionaryEntry {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub misspellings: Vec<String>,
     pub created_at: i64,
+    /// Last time the word showed up in a transcript; recent words go first in the prompt.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<i64>,
 }
 
 #[derive(Debug, Default, Serialize, Deserialize)]
@@ -69,6 +84,7 @@ pub fn add_entry(
         return Err("Word cannot be empty".to_string());
     }
 
+    let _guard = store_lock();
     let mut store = load_dictionary(app)?;
 
     // Check for duplicates
@@ -87,6 +103,7 @@ pub fn add_entry(
         pronunciation,
         misspellings: normalize_misspellings(misspellings.unwrap_or_default()),
         created_at: chrono::Utc::now().timestamp(),
+        last_used_at: None,
     };
 
     store.entries.push(entry.clone());
@@ -103,6 +120,7 @@ pub fn update_entry(
     pronunciation: Option<String>,
     misspellings: Option<Vec<String>>,
 ) -> Result<DictionaryEntry, String> {
+    let _guard = store_lock();
     let mut store = load_dictionary(app)?;
 
     let entry = store
@@ -137,6 +155,7 @@ pub fn update_entry(
 }
 
 pub fn delete_entry(app: &tauri::AppHandle, id: String) -> Result<(), String> {
+    let _guard = store_lock();
     let mut store = load_dictionary(app)?;
 
     let initial_len = store.entries.len();
@@ -150,47 +169,72 @@ pub fn delete_entry(app: &tauri::AppHandle, id: String) -> Result<(), String> {
     Ok(())
 }
 
-pub fn build_whisper_prompt(app: &tauri::AppHandle) -> String {
-    match get_all_entries(app) {
-        Ok(entries) => {
-            if entries.is_empty() {
-                return String::new();
-            }
-
-            // Only send correct target spellings — never misspellings.
-            // Misspellings in the prompt teach Whisper to *produce* them.
-            // Format as a French sentence for stronger decoder conditioning.
-            let mut seen = HashSet::new();
-            let mut words: Vec<String> = Vec::new();
+/// Words to give Whisper as prompt (see `whisper_prompt::build`), most recently used
+/// first: the prompt budget may not fit them all.
+pub fn prompt_terms(app: &tauri::AppHandle) -> Vec<String> {
+    get_all_entries(app)
+        .map(|entries| prompt_order(entries).into_iter().map(|e| e.word).collect())
+        .unwrap_or_default()
+}
 
-            for entry in entries {
-                let word = entry.word.trim().to_string();
-                if !word.is_empty() && seen.insert(word.to_lowercase()) {
-                    words.push(word);
-                }
-            }
+/// Most recently used entries first; entries never used come after them, newest first.
+fn prompt_order(mut entries: Vec<DictionaryEntry>) -> Vec<DictionaryEntry> {
+    entries.sort_by_key(|e| std::cmp::Reverse((e.last_used_at, e.created_at)));
+    entries
+}
 
-            if words.is_empty() {
-                return String::new();
-            }
+/// Record which dictionary words appear in a final transcript (after corrections). The
+/// file is rewritten on a blocking task, off the dictation path.
+pub fn mark_used(app: &tauri::AppHandle, text: &str) {
+    let app = app.clone();
+    let text = text.to_string();
+    let now = chrono::Utc::now().timestamp();
+    tauri::async_runtime::spawn_blocking(move || {
+        let _guard = store_lock();
+        let Ok(mut store) = load_dictionary(&app) else {
+            return;
+        };
+        if mark_entries_used(&mut store.entries, &text, now) {
+            let _ = save_dictionary(&app, &store);
+        }
+    });
+}
 
-            // French context sentence: much stronger than a bare comma list
-            format!("Mots-clés utilisés : {}.", words.join(", "))
+/// Returns true when an entry was updated.
+fn mark_entries_used(entries: &mut [DictionaryEntry], text: &str, now: i64) -> bool {
+    let text = text.to_lowercase();
+    let mut changed = false;
+    for entry in entries.iter_mut() {
+        if contains_word(&text, &entry.word.trim().to_lowercase()) {
+            entry.last_used_at = Some(now);
+            changed = true;
         }
-        Err(_) => String::new(),
     }
+    changed
+}
+
+/// Whole-word occurrence of `word` in `text` (both lowercase).
+fn contains_word(text: &str, word: &str) -> bool {
+    !word.is_empty()
+        && text.match_indices(word).any(|(pos, _)| {
+            let before = text[..pos].chars().next_back();
+            let after = text[pos + word.len()..].chars().next();
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
 }
 
 /// Apply dictionary-based corrections to transcribed text.
 /// Replaces known misspellings with the correct word (case-insensitive).
 pub fn apply_corrections(app: &tauri::AppHandle, text: &str) -> String {
-    let entries = match get_all_entries(app) {
-        Ok(e) => e,
-        Err(_) => return text.to_string(),
-    };
+    match get_all_entries(app) {
+        Ok(entries) => correct_text(&entries, text),
+        Err(_) => text.to_string(),
+    }
+}
 
+pub fn correct_text(entries: &[DictionaryEntry], text: &str) -> String {
     let mut result = text.to_string();
-    for entry in &entries {
+    for entry in entries {
         for misspelling in &entry.misspellings {
             let m = misspelling.trim();
             if m.is_empty() {
@@ -228,6 +272,47 @@ pub fn apply_corrections(app: &tauri::AppHandle, text: &str) -> String {
     result
 }
 
+/// Word with its surrounding punctuation split off: ("\"", "OpenAI", ",").
+fn split_punctuation(word: &str) -> (&str, &str, &str) {
+    let core_start = word.find(|c: char| c.is_alphanumeric()).unwrap_or(word.len());
+    let core_end = word
+        .rfind(|c: char| c.is_alphanumeric())
+        .map_or(core_start, |i| i + word[i..].chars().next().map_or(1, char::len_utf8));
+    (&word[..core_start], &word[core_start..core_end], &word[core_end..])
+}
+
+/// Same corrections as `apply_corrections`, on timed words (subtitle export). A misspelling
+/// spanning several words ("open ai") becomes one word covering their time span; the
+/// punctuation around it is kept.
+pub fn correct_words(entries: &[DictionaryEntry], words: &mut Vec<TimedWord>) {
+    for entry in entries {
+        for misspelling in &entry.misspellings {
+            let target: Vec<String> = misspelling.split_whitespace().map(str::to_lowercase).collect();
+            if target.is_empty() {
+                continue;
+            }
+            let mut i = 0;
+            while i + target.len() <= words.len() {
+                let matches = words[i..i + target.len()]
+                    .iter()
+                    .zip(&target)
+                    .all(|(w, t)| split_punctuation(&w.word).1.to_lowercase() == *t);
+                if !matches {
+                    i += 1;
+                    continue;
+                }
+                let last = &words[i + target.len() - 1];
+                let (end, trailing) = (last.end, split_punctuation(&last.word).2.to_string());
+                let leading = split_punctuation(&words[i].word).0.to_string();
+                words[i].word = format!("{}{}{}", leading, entry.word, trailing);
+                words[i].end = end;
+                words.drain(i + 1..i + target.len());
+                i += 1;
+            }
+        }
+    }
+}
+
 fn normalize_misspellings(items: Vec<String>) -> Vec<String> {
     let mut seen = HashSet::new();
     let mut out = Vec::new();
@@ -250,6 +335,7 @@ pub fn import_entries(
     app: &tauri::AppHandle,
     entries: Vec<DictionaryEntry>,
 ) -> Result<Vec<DictionaryEntry>, String> {
+    let _guard = store_lock();
     let mut store = load_dictionary(app)?;
 
     for entry in entries {
@@ -307,6 +393,91 @@ mod tests {
         assert!(result.is_empty());
     }
 
+    // ── correct_text / correct_words ────────────────────────────────
+
+    fn entry(word: &str, misspellings: &[&str]) -> DictionaryEntry {
+        DictionaryEntry {
+            id: word.to_string(),
+            word: word.to_string(),
+            entry_type: "technology".to_string(),
+            pronunciation: None,
+            misspellings: misspellings.iter().map(|m| m.to_string()).collect(),
+            created_at: 0,
+            last_used_at: None,
+        }
+    }
+
+    fn timed(word: &str, start: f64, end: f64) -> TimedWord {
+        TimedWord {
+            word: word.to_string(),
+            start,
+            end,
+        }
+    }
+
+    #[test]
+    fn test_correct_text_whole_words_only() {
+        let entries = vec![entry("Kubernetes", &["kubernetis"])];
+        assert_eq!(correct_text(&entries, "On déploie sur kubernetis."), "On déploie sur Kubernetes.");
+        assert_eq!(correct_text(&entries, "kubernetisation"), "kubernetisation");
+    }
+
+    #[test]
+    fn test_correct_words_single_word_keeps_punctuation() {
+        let entries = vec![entry("Kubernetes", &["kubernetis"])];
+        let mut words = vec![timed("sur", 0.0, 0.2), timed("Kubernetis.", 0.2, 0.9)];
+        correct_words(&entries, &mut words);
+        assert_eq!(words, vec![timed("sur", 0.0, 0.2), timed("Kubernetes.", 0.2, 0.9)]);
+    }
+
+    #[test]
+    fn test_correct_words_merges_multi_word_misspelling() {
+        let entries = vec![entry("OpenAI", &["open ai"])];
+        let mut words = vec![
+            timed("chez", 0.0, 0.3),
+            timed("Open", 0.3, 0.5),
+            timed("AI,", 0.5, 0.8),
+            timed("ensuite", 0.9, 1.2),
+        ];
+        correct_words(&entries, &mut words);
+        assert_eq!(
+            words,
+            vec![timed("chez", 0.0, 0.3), timed("OpenAI,", 0.3, 0.8), timed("ensuite", 0.9, 1.2)]
+        );
+    }
+
+    #[test]
+    fn test_split_punctuation() {
+        assert_eq!(split_punctuation("\"Bonjour,\""), ("\"", "Bonjour", ",\""));
+        assert_eq!(split_punctuation("été"), ("", "été", ""));
+        assert_eq!(split_punctuation("..."), ("...", "", ""));
+    }
+
+    // ── prompt_order / mark_entries_used ────────────────────────────
+
+    #[test]
+    fn test_prompt_order_recently_used_first() {
+        let mut old = entry("Ancien", &[]);
+        old.created_at = 1;
+        let mut used = entry("Utilisé", &[]);
+        used.last_used_at = Some(50);
+        let mut new = entry("Nouveau", &[]);
+        new.created_at = 10;
+        let words: Vec<String> = prompt_order(vec![old, used, new]).into_iter().map(|e| e.word).collect();
+        assert_eq!(words, vec!["Utilisé", "Nouveau", "Ancien"]);
+    }
+
+    #[test]
+    fn test_mark_entries_used_whole_words_only() {
+        let mut entries = vec![entry("Tauri", &[]), entry("Rust", &[]), entry("Visual Studio", &[])];
+        assert!(mark_entries_used(&mut entries, "Une app Tauri dans Visual Studio.", 42));
+        assert_eq!(entries[0].last_used_at, Some(42));
+        // "Rustacé" is not "Rust"
+        assert!(!mark_entries_used(&mut entries[1..2], "Un rustacé.", 43));
+        assert_eq!(entries[1].last_used_at, None);
+        assert_eq!(entries[2].last_used_at, Some(42));
+    }
+
     // ── DictionaryEntry serialization ───────────────────────────────
 
     #[test]
@@ -318,6 +489,7 @@ mod tests {
             pronunciation: Some("koo-ber-NET-eez".to_string()),
             misspellings: vec!["kubernetis".to_string(), "kubernets".to_string()],
             created_at: 1234567890,
+            last_used_at: Some(1234567999),
         };
         let json = serde_json::to_string(&entry).unwrap();
         let deserialized: DictionaryEntry = serde_json::from_str(&json).unwrap();
@@ -326,6 +498,7 @@ mod tests {
         assert_eq!(deserialized.entry_type, entry.entry_type);
         assert_eq!(deserialized.pronunciation, entry.pronunciation);
         assert_eq!(deserialized.misspellings, entry.misspellings);
+        assert_eq!(deserialized.last_used_at, entry.last_used_at);
     }
 
     #[test]
@@ -335,6 +508,7 @@ mod tests {
         let entry: DictionaryEntry = serde_json::from_str(json).unwrap();
         assert!(entry.pronunciation.is_none());
         assert!(entry.misspellings.is_empty());
+        assert!(entry.last_used_at.is_none());
     }
 
     #[test]
@@ -346,6 +520,7 @@ mod tests {
             pronunciation: None,
             misspellings: vec![],
             created_at: 0,
+            last_used_at: None,
         };
         let json = serde_json::to_value(&entry).unwrap();
         // Should serialize as "type" not "entry_type"
@@ -371,6 +546,7 @@ mod tests {
                 pronunciation: None,
                 misspellings: vec!["tori".to_string()],
                 created_at: 100,
+                last_used_at: None,
             }],
         };
         let json = serde_json::to_string(&store).unwrap();
@@ -0,0 +1,107 @@
+//! Provider fallback chains (primary with retries, then one attempt per `FallbackHop`), shared by transcription and LLM.
+
+/// Providers tried so far, to skip duplicate hops, name each hop in `provider_fallback`
+/// events and report every failure at the end.
+#[derive(Debug)]
+pub struct FallbackTrail {
+    /// (provider id, model) pairs already tried.
+    tried: Vec<(String, String)>,
+    primary_error: String,
+    /// "Name: error" per failed attempt, primary first.
+    errors: Vec<String>,
+    /// Name of the provider that failed last.
+    last: String,
+}
+
+impl FallbackTrail {
+    /// `primary` is the (id, model) that failed, None when it could not even be built
+    /// (missing key or base URL).
+    pub fn new(primary_name: &str, primary: Option<(&str, &str)>, error: String) -> Self {
+        Self {
+            tried: primary
+                .map(|(id, model)| (id.to_string(), model.to_string()))
+                .into_iter()
+                .collect(),
+            errors: vec![format!("{}: {}", primary_name, error)],
+            primary_error: error,
+            last: primary_name.to_string(),
+        }
+    }
+
+    /// Record the move to the next hop; returns the `provider_fallback` event payload
+    /// ("Groq → OpenAI (gpt-4o-mini)"), or None when that provider and model already failed.
+    pub fn hop(&mut self, id: &str, name: &str, model: &str) -> Option<String> {
+        let key = (id.to_string(), model.to_string());
+        if self.tried.contains(&key) {
+            return None;
+        }
+        self.tried.push(key);
+        let label = format!("{} → {} ({})", self.last, name, model);
+        self.last = name.to_string();
+        Some(label)
+    }
+
+    /// The hop last returned by `hop` failed too.
+    pub fn failed(&mut self, error: String) {
+        self.errors.push(format!("{} fallback: {}", self.last, error));
+    }
+
+    /// Final error once the chain is exhausted; `what` is "transcription" or "transformation".
+    pub fn into_error(self, what: &str, retries: u32) -> String {
+        if self.errors.len() == 1 {
+            format!("Échec {} après {} tentatives: {}", what, retries, self.primary_error)
+        } else {
+            format!("Échec {} après {} tentatives ({})", what, retries, self.errors.join(", "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ── FallbackTrail ───────────────────────────────────────────────
+
+    #[test]
+    fn test_hop_labels_follow_the_chain() {
+        let mut trail = FallbackTrail::new("Ollama", Some(("ollama", "llama3.2")), "refused".into());
+        assert_eq!(
+            trail.hop("groq", "Groq", "llama-3.1-8b-instant").as_deref(),
+            Some("Ollama → Groq (llama-3.1-8b-instant)")
+        );
+        trail.failed("429".into());
+        assert_eq!(
+            trail.hop("openai", "OpenAI", "gpt-4o-mini").as_deref(),
+            Some("Groq → OpenAI (gpt-4o-mini)")
+        );
+    }
+
+    #[test]
+    fn test_hop_skips_what_was_already_tried() {
+        let mut trail = FallbackTrail::new("OpenAI", Some(("openai", "gpt-4o-mini")), "timeout".into());
+        assert!(trail.hop("openai", "OpenAI", "gpt-4o-mini").is_none());
+        // Same provider, another model: a real hop
+        assert!(trail.hop("openai", "OpenAI", "gpt-4o").is_some());
+        assert!(trail.hop("openai", "OpenAI", "gpt-4o").is_none());
+    }
+
+    #[test]
+    fn test_into_error_without_hop_keeps_primary_error() {
+        let trail = FallbackTrail::new("Groq", None, "No Groq API key found.".into());
+        assert_eq!(
+            trail.into_error("transformation", 3),
+            "Échec transformation après 3 tentatives: No Groq API key found."
+        );
+    }
+
+    #[test]
+    fn test_into_error_lists_every_failure() {
+        let mut trail = FallbackTrail::new("Groq", Some(("groq", "whisper-large-v3-turbo")), "503".into());
+        trail.hop("openai", "OpenAI", "whisper-1");
+        trail.failed("401".into());
+        assert_eq!(
+            trail.into_error("transcription", 3),
+            "Échec transcription après 3 tentatives (Groq: 503, OpenAI fallback: 401)"
+        );
+    }
+}
@@ -15,3 +15,19 @@ pub fn on_recording_stop(app: tauri::AppHandle) -> Result<(), String> {
         .ok_or_else(|| "RecorderState not found".to_string())?;
     state.stop_capture()
 }
+
+/// Returns true when the take was actually paused (false if not recording or already paused).
+pub fn on_recording_pause(app: tauri::AppHandle) -> Result<bool, String> {
+    let state = app
+        .try_state::<audio::RecorderState>()
+        .ok_or_else(|| "RecorderState not found".to_string())?;
+    state.pause_capture()
+}
+
+/// Returns true when a paused take was resumed.
+pub fn on_recording_resume(app: tauri::AppHandle) -> Result<bool, String> {
+    let state = app
+        .try_state::<audio::RecorderState>()
+        .ok_or_else(|| "RecorderState not found".to_string())?;
+    state.resume_capture()
+}
@@ -1,5 +1,6 @@
 /// Client HTTP async partagé (connection pooling, timeouts).
 use once_cell::sync::Lazy;
+use std::net::IpAddr;
 use std::time::Duration;
 
 static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
@@ -13,3 +14,198 @@ static HTTP_CLIENT: Lazy<reqwest::Client> = Lazy::new(|| {
 pub fn client() -> &'static reqwest::Client {
     &HTTP_CLIENT
 }
+
+/// Base URL scheme for self-hosted servers listening on a Unix-domain socket:
+/// `unix:///run/whisper.sock` (everything after the scheme is the socket path).
+const UNIX_SCHEME: &str = "unix://";
+
+/// One client per socket path: reqwest binds the socket at build time.
+#[cfg(unix)]
+static UNIX_CLIENTS: Lazy<std::sync::Mutex<std::collections::HashMap<String, reqwest::Client>>> =
+    Lazy::new(Default::default);
+
+fn unix_socket_path(url: &str) -> Option<&str> {
+    url.strip_prefix(UNIX_SCHEME)
+}
+
+/// `localhost`, 127.0.0.0/8 or ::1. The host is parsed, so `127.0.0.1.example.com` or
+/// `localhost.example.com` don't qualify.
+fn is_loopback_url(url: &str) -> bool {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    match parsed.host_str() {
+        Some(host) if host.eq_ignore_ascii_case("localhost") => true,
+        Some(host) => host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback()),
+        None => false,
+    }
+}
+
+/// True when requests to `url` never leave this machine (loopback host or Unix socket).
+pub fn is_local_url(url: &str) -> bool {
+    unix_socket_path(url).is_some() || is_loopback_url(url)
+}
+
+/// Validate an API base URL. Remote hosts require HTTPS to protect API keys in transit;
+/// plain HTTP is only accepted on loopback, and `unix://` targets a local socket.
+pub fn validate_base_url(url: &str) -> Result<(), String> {
+    if let Some(path) = unix_socket_path(url) {
+        if !cfg!(unix) {
+            return Err("Unix-domain sockets are not supported on this platform.".to_string());
+        }
+        if !path.starts_with('/') {
+            return Err(format!("Invalid base URL '{}': expected unix:///absolute/path.sock", url));
+        }
+        Ok(())
+    } else if url.starts_with("https://") {
+        Ok(())
+    } else if url.starts_with("http://") {
+        if is_loopback_url(url) {
+            Ok(())
+        } else {
+            Err("Insecure base URL rejected: plain HTTP is only allowed for loopback servers (localhost, 127.0.0.1, ::1), not for API endpoints that transmit API keys. Use https:// instead.".to_string())
+        }
+    } else {
+        Err(format!("Invalid base URL '{}': must start with https://", url))
+    }
+}
+
+/// Self-hosted servers on this machine usually run without a key: a missing key is only an
+/// error when `base_url` is remote.
+pub fn key_unless_local(key: Result<String, String>, base_url: &str) -> Result<Option<String>, String> {
+    match key {
+        Ok(key) => Ok(Some(key)),
+        Err(_) if is_local_url(base_url) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Request URL for `path` (e.g. `/v1/audio/transcriptions`) under a validated base URL.
+fn endpoint_url(base_url: &str, path: &str) -> String {
+    match unix_socket_path(base_url) {
+        // The host is ignored by the socket connector, but HTTP/1.1 needs one
+        Some(_) => format!("http://localhost{}", path),
+        None => format!("{}{}", base_url, path),
+    }
+}
+
+#[cfg(unix)]
+fn client_for(base_url: &str) -> Result<reqwest::Client, String> {
+    let Some(socket) = unix_socket_path(base_url) else {
+        return Ok(client().clone());
+    };
+    let mut clients = UNIX_CLIENTS.lock().map_err(|e| e.to_string())?;
+    if let Some(existing) = clients.get(socket) {
+        return Ok(existing.clone());
+    }
+    let built = reqwest::Client::builder()
+        .unix_socket(socket)
+        .build()
+        .map_err(|e| format!("Unix socket client init ({}): {}", socket, e))?;
+    clients.insert(socket.to_string(), built.clone());
+    Ok(built)
+}
+
+#[cfg(not(unix))]
+fn client_for(_base_url: &str) -> Result<reqwest::Client, String> {
+    Ok(client().clone())
+}
+
+/// POST to `path` under `base_url` (HTTPS, loopback HTTP or Unix socket), with a bearer
+/// token when a key is set.
+pub fn post(base_url: &str, path: &str, api_key: Option<&str>) -> Result<reqwest::RequestBuilder, String> {
+    let request = client_for(base_url)?.post(endpoint_url(base_url, path));
+    Ok(match api_key {
+        Some(key) => request.header("Authorization", format!("Bearer {}", key)),
+        None => request,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ── validate_base_url ──────────────────────────────────────────
+
+    #[test]
+    fn test_validate_base_url_https_ok() {
+        assert!(validate_base_url("https://api.openai.com").is_ok());
+        assert!(validate_base_url("https://api.groq.com/openai").is_ok());
+    }
+
+    #[test]
+    fn test_validate_base_url_http_rejected() {
+        let result = validate_base_url("http://api.openai.com");
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("plain HTTP"));
+    }
+
+    #[test]
+    fn test_validate_base_url_invalid_scheme() {
+        assert!(validate_base_url("ftp://example.com").is_err());
+        assert!(validate_base_url("api.openai.com").is_err());
+    }
+
+    #[test]
+    fn test_validate_base_url_http_loopback_ok() {
+        assert!(validate_base_url("http://localhost:8000").is_ok());
+        assert!(validate_base_url("http://LOCALHOST:11434").is_ok());
+        assert!(validate_base_url("http://127.0.0.1:8080").is_ok());
+        assert!(validate_base_url("http://127.1.2.3").is_ok());
+        assert!(validate_base_url("http://[::1]:9000").is_ok());
+    }
+
+    #[test]
+    fn test_validate_base_url_http_lookalike_hosts_rejected() {
+        assert!(validate_base_url("http://localhost.example.com").is_err());
+        assert!(validate_base_url("http://127.0.0.1.example.com").is_err());
+        assert!(validate_base_url("http://192.168.1.10:8000").is_err());
+        assert!(validate_base_url("http://user@evil.com#@localhost").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_validate_base_url_unix_socket() {
+        assert!(validate_base_url("unix:///run/whisper.sock").is_ok());
+        assert!(validate_base_url("unix://whisper.sock").is_err());
+    }
+
+    // ── is_local_url / key_unless_local ─────────────────────────────
+
+    #[test]
+    fn test_is_local_url() {
+        assert!(is_local_url("http://localhost:8000"));
+        assert!(is_local_url("https://127.0.0.1"));
+        assert!(is_local_url("unix:///tmp/llm.sock"));
+        assert!(!is_local_url("https://api.openai.com"));
+    }
+
+    #[test]
+    fn test_key_unless_local() {
+        let missing = || Err("no key".to_string());
+        assert_eq!(key_unless_local(missing(), "http://localhost:8000"), Ok(None));
+        assert_eq!(key_unless_local(missing(), "https://api.openai.com"), Err("no key".to_string()));
+        assert_eq!(
+            key_unless_local(Ok("sk-1".to_string()), "http://localhost:8000"),
+            Ok(Some("sk-1".to_string()))
+        );
+    }
+
+    // ── endpoint_url ────────────────────────────────────────────────
+
+    #[test]
+    fn test_endpoint_url() {
+        assert_eq!(
+            endpoint_url("https://api.openai.com", "/v1/chat/completions"),
+            "https://api.openai.com/v1/chat/completions"
+        );
+        assert_eq!(
+            endpoint_url("unix:///run/whisper.sock", "/v1/audio/transcriptions"),
+            "http://localhost/v1/audio/transcriptions"
+        );
+    }
+}
@@ -2,25 +2,37 @@
 const ENABLE_RIGHT_CLICK_SERVICES: bool = true;
 
 mod accessibility;
+mod anthropic;
+mod archive;
 mod audio;
+mod audio_file;
 mod clipboard;
 mod correction_detector;
 mod dictionary;
 mod edit_commands;
 mod errors;
+mod fallback;
 mod hotkey;
 mod http_client;
+mod live_transcript;
 mod llm;
+mod llm_provider;
+mod local_whisper;
 mod modes;
 mod preferences;
+mod pricing;
 mod prompt_state;
 mod secrets;
 mod services_installer;
 mod shortcuts;
 mod snippets;
+mod stream_insert;
+mod subtitles;
 mod transcribe;
+mod transcription_provider;
 mod usage;
 mod voice_commands;
+mod whisper_prompt;
 
 use std::collections::HashMap;
 use std::sync::Mutex;
@@ -42,6 +54,15 @@ impl PipelineCancel {
     pub fn set(&self, token: CancellationToken) {
         *self.guard() = Some(token);
     }
+    /// Comme `set`, sauf si un token est déjà en place : renvoie false sans le remplacer.
+    pub fn set_if_idle(&self, token: CancellationToken) -> bool {
+        let mut guard = self.guard();
+        if guard.is_some() {
+            return false;
+        }
+        *guard = Some(token);
+        true
+    }
     pub fn take_and_cancel(&self) {
         if let Some(t) = self.guard().take() {
             t.cancel();
@@ -52,12 +73,34 @@ impl PipelineCancel {
     }
 }
 
+/// Token d'annulation de la transcription de fichier en cours, distinct de `PipelineCancel`
+/// pour qu'elle ne remplace ni n'efface celui d'une dictée en cours.
+pub struct FileTranscriptionCancel(pub PipelineCancel);
+
 /// Instant de première entrée du curseur dans les bounds (pour focus après survol soutenu).
 pub struct CursorInsideSince(pub Mutex<Option<Instant>>);
 
 /// Dernière sortie (transcription + LLM) pour "Paste last output" dans le menu tray.
 pub struct LastOutputState(pub Mutex<Option<String>>);
 
+/// App (bundle id) où la dernière sortie a été insérée ; None si elle a seulement été copiée
+/// ou renvoyée. Conditionne sa réutilisation comme contexte Whisper (`whisper_prompt`).
+pub struct LastOutputApp(pub Mutex<Option<String>>);
+
+/// Enregistre la dernière sortie et l'app qui l'a reçue.
+pub fn set_last_output(app: &tauri::AppHandle, output: String, inserted_into: Option<String>) {
+    if let Some(state) = app.try_state::<LastOutputState>() {
+        if let Ok(mut guard) = state.0.lock() {
+            *guard = Some(output);
+        }
+    }
+    if let Some(state) = app.try_state::<LastOutputApp>() {
+        if let Ok(mut guard) = state.0.lock() {
+            *guard = inserted_into;
+        }
+    }
+}
+
 /// Flag to prevent floating window focus stealing during auto-paste.
 pub struct PasteInProgress(pub std::sync::atomic::AtomicBool);
 
@@ -67,17 +110,79 @@ pub struct ShortcutMappingState(pub Mutex<HashMap<u32, shortcuts::ShortcutConfig
 /// Items de menu à coche (micro et langues) du tray, pour mettre à jour les coches après clic.
 #[cfg(target_os = "macos")]
 pub struct TrayCheckItems {
-    pub mic_items: Vec<tauri::menu::CheckMenuItem<tauri::Wry>>,
+    pub mic_submenu: tauri::menu::Submenu<tauri::Wry>,
+    /// Rebuilt when input devices are plugged in or removed (`input_devices_changed`).
+    pub mic_items: Mutex<Vec<tauri::menu::CheckMenuItem<tauri::Wry>>>,
     pub lang_items: Vec<tauri::menu::CheckMenuItem<tauri::Wry>>,
 }
 
+/// "Default" + one check item per input device; ids are `tray_mic_default` / `tray_mic_dev_<name>`.
+#[cfg(target_os = "macos")]
+fn build_tray_mic_items(
+    app: &tauri::AppHandle,
+    devices: &[audio::AudioInputDevice],
+    current_mic: Option<&str>,
+) -> tauri::Result<Vec<tauri::menu::CheckMenuItem<tauri::Wry>>> {
+    use tauri::menu::CheckMenuItem;
+    let mic_default_i = CheckMenuItem::with_id(
+        app,
+        "tray_mic_default",
+        "Default",
+        true,
+        current_mic.is_none(),
+        None::<&str>,
+    )?;
+    let mic_device_items: Vec<CheckMenuItem<tauri::Wry>> = devices
+        .iter()
+        .filter_map(|d| {
+            let checked = current_mic.map(|id| d.id.as_str() == id).unwrap_or(false);
+            // Use device name as menu ID for stable identification across sessions
+            CheckMenuItem::with_id(
+                app,
+                format!("tray_mic_dev_{}", d.id),
+                &d.name,
+                true,
+                checked,
+                None::<&str>,
+            )
+            .ok()
+        })
+        .collect();
+    let mut mic_items: Vec<CheckMenuItem<tauri::Wry>> = vec![mic_default_i];
+    mic_items.extend(mic_device_items);
+    Ok(mic_items)
+}
+
 #[cfg(target_os = "macos")]
 impl TrayCheckItems {
     fn set_mic_checked(&self, selected_id: &str) {
-        for item in &self.mic_items {
+        let Ok(items) = self.mic_items.lock() else {
+            return;
+        };
+        for item in items.iter() {
             let _ = item.set_checked(item.id().0.as_str() == selected_id);
         }
     }
+
+    /// Replace the microphone submenu entries with the devices currently plugged in.
+    /// Must run on the main thread.
+    fn rebuild_mic_items(&self, app: &tauri::AppHandle) {
+        let devices = audio::list_input_devices().unwrap_or_default();
+        let prefs = preferences::get_preferences(app).unwrap_or_default();
+        let Ok(new_items) = build_tray_mic_items(app, &devices, prefs.recording.input_device_id.as_deref()) else {
+            return;
+        };
+        let Ok(mut items) = self.mic_items.lock() else {
+            return;
+        };
+        for item in items.iter() {
+            let _ = self.mic_submenu.remove(item);
+        }
+        for item in &new_items {
+            let _ = self.mic_submenu.append(item);
+        }
+        *items = new_items;
+    }
     fn set_lang_checked(&self, selected_id: &str) {
         for item in &self.lang_items {
             let _ = item.set_checked(item.id().0.as_str() == selected_id);
@@ -239,8 +344,12 @@ fn paste_last_output(app: tauri::AppHandle) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn cancel_transcription(state: tauri::State<PipelineCancel>) -> Result<(), String> {
+fn cancel_transcription(
+    state: tauri::State<PipelineCancel>,
+    file: tauri::State<FileTranscriptionCancel>,
+) -> Result<(), String> {
     state.take_and_cancel();
+    file.0.take_and_cancel();
     Ok(())
 }
 
@@ -256,7 +365,9 @@ pub async fn run_transform_selection(app: tauri::AppHandle, mode_id: String) ->
     let result = if prompt.is_empty() {
         Ok(text.clone())
     } else {
-        crate::llm::transform_text_streaming(&text, &prompt, &app, cancel, None).await
+        crate::llm::transform_text_streaming(&text, &prompt, &app, cancel, None, None)
+            .await
+            .map(|out| out.text)
     };
     let full = result.map_err(|e| e.to_string())?;
     let output = if full.contains("---REFLECTION---") {
@@ -280,11 +391,7 @@ pub async fn run_transform_selection(app: tauri::AppHandle, mode_id: String) ->
             let _ = clipboard::send_paste_keystroke();
         });
     }
-    if let Some(state) = app.try_state::<LastOutputState>() {
-        if let Ok(mut guard) = state.0.lock() {
-            *guard = Some(output.clone());
-        }
-    }
+    set_last_output(&app, output.clone(), None);
     let _ = app.emit("transformation_ready", output);
     if prefs.behavior.sound_on_complete {
         let _ = app.emit_to("main", "play_completion_sound", ());
@@ -332,7 +439,7 @@ async fn improve_system_prompt(app: tauri::AppHandle, prompt: String) -> Result<
 #[tauri::command]
 async fn transform_text_direct(app: tauri::AppHandle, text: String, prompt: String) -> Result<String, String> {
     let cancel = tokio_util::sync::CancellationToken::new();
-    let result = crate::llm::transform_text_streaming(&text, &prompt, &app, cancel, None).await?;
+    let result = crate::llm::transform_text_streaming(&text, &prompt, &app, cancel, None, None).await?.text;
     let output = result.split("---REFLECTION---").next().unwrap_or(&result).trim().to_string();
     Ok(output)
 }
@@ -547,6 +654,77 @@ fn stop_recording(app: tauri::AppHandle) -> Result<(), String> {
     Ok(())
 }
 
+/// Transcribe an audio file (WAV, FLAC, MP3, OGG) with the dictionary and active mode.
+/// Returns the result instead of pasting it.
+#[tauri::command]
+async fn transcribe_file(
+    app: tauri::AppHandle,
+    path: String,
+) -> Result<audio::TranscriptionReadyPayload, String> {
+    audio::transcribe_file(std::path::PathBuf::from(path), app).await
+}
+
+/// Archived recordings (opt-in archive), newest first.
+#[tauri::command]
+fn list_recordings(app: tauri::AppHandle) -> Result<Vec<archive::RecordingMeta>, String> {
+    archive::list_recordings(&app)
+}
+
+#[tauri::command]
+fn delete_recording(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    archive::delete_recording(&app, &id)
+}
+
+/// Rerun an archived take with another provider, language or mode (None = current settings).
+/// Returns the result instead of pasting it.
+#[tauri::command]
+async fn retranscribe_recording(
+    app: tauri::AppHandle,
+    id: String,
+    provider: Option<String>,
+    language: Option<String>,
+    mode_id: Option<String>,
+) -> Result<audio::TranscriptionReadyPayload, String> {
+    let overrides = audio::PipelineOverrides {
+        provider,
+        language,
+        mode_id,
+    };
+    audio::retranscribe_recording(&id, overrides, app).await
+}
+
+/// Subtitles of an audio file with word timings: format "srt", "vtt" or "json".
+/// Returns the file content; the frontend saves it.
+#[tauri::command]
+async fn export_subtitles(app: tauri::AppHandle, path: String, format: String) -> Result<String, String> {
+    let format = subtitles::SubtitleFormat::parse(&format)?;
+    subtitles::export(std::path::PathBuf::from(path), format, &app).await
+}
+
+/// Same as `export_subtitles`, for an archived recording.
+#[tauri::command]
+async fn export_recording_subtitles(app: tauri::AppHandle, id: String, format: String) -> Result<String, String> {
+    let format = subtitles::SubtitleFormat::parse(&format)?;
+    let path = archive::recording_path(&app, &id)?;
+    subtitles::export(path, format, &app).await
+}
+
+#[tauri::command]
+fn pause_recording(app: tauri::AppHandle) -> Result<(), String> {
+    if hotkey::on_recording_pause(app.clone())? {
+        let _ = app.emit("recording_paused", ());
+    }
+    Ok(())
+}
+
+#[tauri::command]
+fn resume_recording(app: tauri::AppHandle) -> Result<(), String> {
+    if hotkey::on_recording_resume(app.clone())? {
+        let _ = app.emit("recording_resumed", ());
+    }
+    Ok(())
+}
+
 #[tauri::command]
 fn set_openai_key(key: String) -> Result<(), String> {
     secrets::set_api_key(key)
@@ -607,6 +785,13 @@ fn get_usage_stats(app: tauri::AppHandle) -> usage::UsageStats {
     usage::get_usage_stats(&app)
 }
 
+/// Estimated spend per provider/model, with the user's price overrides.
+#[tauri::command]
+fn get_usage_costs(app: tauri::AppHandle) -> usage::CostBreakdown {
+    let prefs = preferences::get_preferences(&app).unwrap_or_default();
+    usage::get_usage_stats(&app).cost_breakdown(&prefs.advanced.pricing)
+}
+
 #[tauri::command]
 fn reset_usage_stats(app: tauri::AppHandle) -> usage::UsageStats {
     usage::reset_usage_stats(&app)
@@ -672,6 +857,9 @@ fn set_app_preferences(
     prefs: preferences::Preferences,
 ) -> Result<preferences::Preferences, String> {
     preferences::set_preferences(&app, &prefs)?;
+    if let Some(state) = app.try_state::<audio::RecorderState>() {
+        state.sync_warm();
+    }
     preferences::get_preferences(&app)
 }
 
@@ -681,6 +869,9 @@ fn update_app_preferences(
     partial: serde_json::Value,
 ) -> Result<preferences::Preferences, String> {
     let prefs = preferences::update_preferences(&app, partial)?;
+    if let Some(state) = app.try_state::<audio::RecorderState>() {
+        state.sync_warm();
+    }
     let _ = app.emit("preferences-updated", ());
     Ok(prefs)
 }
@@ -808,6 +999,27 @@ fn list_audio_input_devices() -> Result<Vec<audio::AudioInputDevice>, String> {
     audio::list_input_devices()
 }
 
+#[tauri::command]
+fn list_transcription_providers() -> Vec<&'static str> {
+    transcription_provider::provider_ids()
+}
+
+/// whisper.cpp models for the "local" provider, with their download state.
+#[tauri::command]
+fn list_local_models() -> Result<Vec<local_whisper::LocalModelStatus>, String> {
+    local_whisper::list_models()
+}
+
+#[tauri::command]
+async fn download_local_model(app: tauri::AppHandle, id: String) -> Result<(), String> {
+    local_whisper::download_model(&app, &id).await
+}
+
+#[tauri::command]
+fn delete_local_model(id: String) -> Result<(), String> {
+    local_whisper::delete_model(&id)
+}
+
 // ============================================================================
 // DICTIONARY
 // ============================================================================
@@ -1009,6 +1221,12 @@ fn run_shortcut_action(
                 let _ = app.emit("recording_stopped", ());
             }
         }
+        ShortcutAction::PauseRecording if state == ShortcutState::Pressed => {
+            let _ = pause_recording(app.clone());
+        }
+        ShortcutAction::ResumeRecording if state == ShortcutState::Pressed => {
+            let _ = resume_recording(app.clone());
+        }
         ShortcutAction::PasteLastOutput if state == ShortcutState::Pressed => {
             let _ = paste_last_output(app.clone());
         }
@@ -1099,6 +1317,7 @@ pub fn run() {
             if let Some(state) = app.try_state::<audio::RecorderState>() {
                 state.warmup(app.handle().clone());
             }
+            local_whisper::init(app.handle());
 
             #[cfg(desktop)]
             {
@@ -1154,32 +1373,7 @@ pub fn run() {
                     let shortcuts_i = MenuItem::with_id(app, "tray_shortcuts", "Shortcuts", true, None::<&str>)?;
 
                     let devices = audio::list_input_devices().unwrap_or_default();
-                    let mic_default_i = CheckMenuItem::with_id(
-                        app,
-                        "tray_mic_default",
-                        "Default",
-                        true,
-                        current_mic.is_none(),
-                        None::<&str>,
-                    )?;
-                    let mic_device_items: Vec<CheckMenuItem<tauri::Wry>> = devices
-                        .iter()
-                        .filter_map(|d| {
-                            let checked = current_mic.map(|id| d.id.as_str() == id).unwrap_or(false);
-                            // Use device name as menu ID for stable identification across sessions
-                            CheckMenuItem::with_id(
-                                app,
-                                format!("tray_mic_dev_{}", d.id),
-                                &d.name,
-                                true,
-                                checked,
-                                None::<&str>,
-                            )
-                            .ok()
-                        })
-                        .collect();
-                    let mut mic_items: Vec<CheckMenuItem<tauri::Wry>> = vec![mic_default_i];
-                    mic_items.extend(mic_device_items);
+                    let mic_items = build_tray_mic_items(app.handle(), &devices, current_mic)?;
                     let mic_refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> =
                         mic_items.iter().map(|i| i as &dyn tauri::menu::IsMenuItem<tauri::Wry>).collect();
                     let mic_submenu = Submenu::with_id_and_items(
@@ -1222,11 +1416,26 @@ pub fn run() {
                     )?;
 
                     let tray_check_items = TrayCheckItems {
-                        mic_items,
+                        mic_submenu: mic_submenu.clone(),
+                        mic_items: Mutex::new(mic_items),
                         lang_items,
                     };
                     app.manage(tray_check_items);
 
+                    // Hot-plug: the audio device watcher reports added/removed microphones.
+                    {
+                        use tauri::Listener;
+                        let handle = app.handle().clone();
+                        app.listen_any("input_devices_changed", move |_| {
+                            let h = handle.clone();
+                            let _ = handle.run_on_main_thread(move || {
+                                if let Some(state) = h.try_state::<TrayCheckItems>() {
+                                    state.rebuild_mic_items(&h);
+                                }
+                            });
+                        });
+                    }
+
                     let sep3 = PredefinedMenuItem::separator(app)?;
                     let help_i = MenuItem::with_id(app, "tray_help", "Help Center", true, None::<&str>)?;
                     let support_i = MenuItem::with_id(app, "tray_support", "Talk to support", true, None::<&str>)?;
@@ -1310,6 +1519,9 @@ pub fn run() {
                                         if let Some(state) = app.try_state::<TrayCheckItems>() {
                                             state.set_mic_checked(id);
                                         }
+                                        if let Some(state) = app.try_state::<audio::RecorderState>() {
+                                            state.sync_warm();
+                                        }
                                         let _ = app.emit("preferences-updated", ());
                                     }
                                 }
@@ -1320,6 +1532,9 @@ pub fn run() {
                                         if let Some(state) = app.try_state::<TrayCheckItems>() {
                                             state.set_mic_checked(id);
                                         }
+                                        if let Some(state) = app.try_state::<audio::RecorderState>() {
+                                            state.sync_warm();
+                                        }
                                         let _ = app.emit("preferences-updated", ());
                                     }
                                 }
@@ -1398,8 +1613,10 @@ pub fn run() {
         .manage(audio::RecorderState::default())
         .manage(prompt_state::ActivePromptState::default())
         .manage(PipelineCancel(Mutex::new(None)))
+        .manage(FileTranscriptionCancel(PipelineCancel(Mutex::new(None))))
         .manage(CursorInsideSince(Mutex::new(None)))
         .manage(LastOutputState(Mutex::new(None)))
+        .manage(LastOutputApp(Mutex::new(None)))
         .manage(PasteInProgress(std::sync::atomic::AtomicBool::new(false)))
         .invoke_handler(tauri::generate_handler![
             get_last_output,
@@ -1415,6 +1632,14 @@ pub fn run() {
             debug_log,
             start_recording,
             stop_recording,
+            pause_recording,
+            resume_recording,
+            transcribe_file,
+            list_recordings,
+            delete_recording,
+            retranscribe_recording,
+            export_subtitles,
+            export_recording_subtitles,
             set_openai_key,
             has_openai_key,
             delete_openai_key,
@@ -1424,6 +1649,7 @@ pub fn run() {
             set_active_api_key,
             get_all_api_keys,
             get_usage_stats,
+            get_usage_costs,
             reset_usage_stats,
             diagnose_keys,
             get_app_preferences,
@@ -1441,6 +1667,10 @@ pub fn run() {
             reregister_shortcuts,
             check_shortcut_available,
             list_audio_input_devices,
+            list_transcription_providers,
+            list_local_models,
+            download_local_model,
+            delete_local_model,
             transform_selection,
             improve_system_prompt,
             transform_text_direct,
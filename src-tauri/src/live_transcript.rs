@@ -0,0 +1,309 @@
+/// Live partial transcripts while the key is still held (opt-in, `live_partials`).
+///
+/// Every `PARTIAL_INTERVAL_MS` the part of the buffer not yet committed is transcribed and
+/// the running text is emitted as `partial_transcript`. Once that part grows past
+/// `COMMIT_AFTER_SECS`, it is cut at a pause and the text before the cut is committed:
+/// later ticks only send what follows, and the final pass reuses the committed text
+/// instead of uploading that audio again, so long dictations reach the LLM sooner.
+use crate::audio::{
+    detect_voice_activity, resample, split_at_silences, AudioEncoder, EncodedAudio,
+    RecorderState, WHISPER_SAMPLE_RATE,
+};
+use crate::transcribe::{dominant_language, stitch_segments, Transcription};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{Emitter, Manager};
+
+const PARTIAL_INTERVAL_MS: u64 = 1500;
+/// Below this much new audio there is nothing worth sending yet.
+const MIN_PARTIAL_MS: u32 = 1000;
+/// Uncommitted audio longer than this is cut at its quietest pause and committed.
+const COMMIT_AFTER_SECS: u32 = 20;
+
+/// Start of the take that is already transcribed for good.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommittedPrefix {
+    pub transcription: Transcription,
+    /// Length of the committed audio, in 16 kHz samples of the untrimmed take.
+    pub samples: usize,
+}
+
+#[derive(Debug, Default)]
+struct TakeState {
+    finished: bool,
+    texts: Vec<String>,
+    languages: Vec<Option<String>>,
+    samples: usize,
+    last_emitted: String,
+}
+
+/// Live transcription state of one recording. Shared between the worker, which finishes
+/// it on Stop, and the partial loop, which may still have a request in flight by then.
+#[derive(Debug, Default)]
+pub struct LiveTake {
+    state: Mutex<TakeState>,
+}
+
+impl LiveTake {
+    fn lock(&self) -> std::sync::MutexGuard<'_, TakeState> {
+        self.state.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn is_finished(&self) -> bool {
+        self.lock().finished
+    }
+
+    /// 16 kHz samples committed so far and the committed text (used as prompt).
+    fn position(&self) -> (usize, String) {
+        let state = self.lock();
+        (state.samples, stitch_segments(&state.texts))
+    }
+
+    /// Commit `samples` more audio transcribed as `transcription`. Ignored once finished:
+    /// the final pass already decided what it reuses.
+    fn commit(&self, samples: usize, transcription: Transcription) {
+        let mut state = self.lock();
+        if state.finished {
+            return;
+        }
+        state.samples += samples;
+        state.texts.push(transcription.text);
+        state.languages.push(transcription.language);
+    }
+
+    /// Committed text followed by `tail`, when it differs from the last one emitted.
+    fn next_partial(&self, tail: &str) -> Option<String> {
+        let mut state = self.lock();
+        if state.finished {
+            return None;
+        }
+        let mut texts = state.texts.clone();
+        texts.push(tail.to_string());
+        let text = stitch_segments(&texts);
+        if text.is_empty() || text == state.last_emitted {
+            return None;
+        }
+        state.last_emitted = text.clone();
+        Some(text)
+    }
+
+    /// End the take: no partial is emitted after this. Returns what the final pass can
+    /// reuse, if anything was committed.
+    pub fn finish(&self) -> Option<CommittedPrefix> {
+        let mut state = self.lock();
+        state.finished = true;
+        (state.samples > 0).then(|| CommittedPrefix {
+            transcription: Transcription {
+                text: stitch_segments(&state.texts),
+                language: dominant_language(&state.languages),
+                ..Default::default()
+            },
+            samples: state.samples,
+        })
+    }
+}
+
+/// Native-rate audio after the committed part, brought to 16 kHz.
+fn uncommitted_audio(buffer: &Mutex<Vec<f32>>, sample_rate: &AtomicU32, committed: usize) -> Vec<f32> {
+    let (native, rate) = {
+        let Ok(buffer) = buffer.lock() else {
+            return Vec::new();
+        };
+        // Read under the buffer lock: a device failover resamples the buffer and updates the rate together
+        let rate = sample_rate.load(Ordering::SeqCst).max(1);
+        let start = (committed as u64 * rate as u64 / WHISPER_SAMPLE_RATE as u64) as usize;
+        match buffer.get(start..) {
+            Some(native) => (native.to_vec(), rate),
+            None => return Vec::new(),
+        }
+    };
+    // Resample the copy: the capture callback blocks on the same lock
+    resample(&native, rate, WHISPER_SAMPLE_RATE)
+}
+
+/// Split off the first pause-bounded chunk once `audio` is long enough to commit.
+fn commit_cut(audio: &[f32]) -> Option<usize> {
+    let max_len = (COMMIT_AFTER_SECS * WHISPER_SAMPLE_RATE) as usize;
+    if audio.len() <= max_len {
+        return None;
+    }
+    split_at_silences(audio, WHISPER_SAMPLE_RATE, max_len)
+        .first()
+        .map(|range| range.end)
+}
+
+/// Speech of `audio` (long pauses trimmed, like the final pass), encoded for upload.
+/// None when there is no speech at all.
+fn encode_speech(audio: &[f32], encoder: AudioEncoder) -> Result<Option<EncodedAudio>, String> {
+    let vad = detect_voice_activity(audio, WHISPER_SAMPLE_RATE);
+    if !vad.has_speech() {
+        return Ok(None);
+    }
+    EncodedAudio::encode(&vad.samples, WHISPER_SAMPLE_RATE, encoder).map(Some)
+}
+
+/// What one tick sends: the chunk to commit (with its length), then the live tail.
+struct TickAudio {
+    commit: Option<(usize, Option<EncodedAudio>)>,
+    tail: Option<EncodedAudio>,
+}
+
+fn prepare_tick(audio: &[f32], encoder: AudioEncoder) -> Result<TickAudio, String> {
+    let (commit, tail) = match commit_cut(audio) {
+        Some(cut) => (Some((cut, encode_speech(&audio[..cut], encoder)?)), &audio[cut..]),
+        None => (None, audio),
+    };
+    let tail = if tail.len() >= (MIN_PARTIAL_MS * WHISPER_SAMPLE_RATE / 1000) as usize {
+        encode_speech(tail, encoder)?
+    } else {
+        None
+    };
+    Ok(TickAudio { commit, tail })
+}
+
+/// Partial loop of one recording. Returns once the take is stopped or finished.
+pub async fn run(
+    app: tauri::AppHandle,
+    buffer: Arc<Mutex<Vec<f32>>>,
+    sample_rate: Arc<AtomicU32>,
+    take: Arc<LiveTake>,
+) {
+    let prefs = crate::preferences::get_preferences(&app).unwrap_or_default();
+    let encoder = AudioEncoder::from_pref(&prefs.transcription.upload_format);
+    loop {
+        tokio::time::sleep(Duration::from_millis(PARTIAL_INTERVAL_MS)).await;
+        let Some(state) = app.try_state::<RecorderState>() else {
+            return;
+        };
+        if !state.is_capturing() || take.is_finished() {
+            return;
+        }
+        if state.is_paused() {
+            continue;
+        }
+        let (committed, committed_text) = take.position();
+        // Resampling and encoding are CPU-bound: keep them off the async runtime.
+        let (buffer, sample_rate) = (buffer.clone(), sample_rate.clone());
+        let tick = tauri::async_runtime::spawn_blocking(move || {
+            prepare_tick(&uncommitted_audio(&buffer, &sample_rate, committed), encoder)
+        })
+        .await;
+        let Ok(Ok(tick)) = tick else {
+            continue;
+        };
+
+        let mut previous = committed_text;
+        // Nothing committed yet: the take continues the text before it
+        let context = crate::whisper_prompt::dictation_context(&app);
+        let prompt = |previous: &str| match previous {
+            "" => context.clone(),
+            text => Some(text.to_string()),
+        };
+        if let Some((cut, audio)) = tick.commit {
+            let transcription = match audio {
+                Some(audio) => {
+                    crate::transcribe::transcribe_partial(audio, &app, &prefs, prompt(&previous).as_deref()).await
+                }
+                None => Ok(Transcription::default()),
+            };
+            match transcription {
+                Ok(t) => {
+                    previous = stitch_segments(&[previous, t.text.clone()]);
+                    take.commit(cut, t);
+                }
+                // Not committed: the next tick sends this audio again
+                Err(_e) => {
+                    #[cfg(debug_assertions)]
+                    eprintln!("[live] commit failed: {}", _e);
+                    continue;
+                }
+            }
+        }
+        let tail = match tick.tail {
+            Some(audio) => crate::transcribe::transcribe_partial(audio, &app, &prefs, prompt(&previous).as_deref())
+                .await
+                .map(|t| t.text)
+                .unwrap_or_default(),
+            None => String::new(),
+        };
+        if let Some(text) = take.next_partial(&tail) {
+            let _ = app.emit("partial_transcript", text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcription(text: &str, language: Option<&str>) -> Transcription {
+        Transcription {
+            text: text.to_string(),
+            language: language.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    // ── LiveTake ────────────────────────────────────────────────────
+
+    #[test]
+    fn test_finish_without_commit_reuses_nothing() {
+        let take = LiveTake::default();
+        assert_eq!(take.next_partial("Bonjour"), Some("Bonjour".to_string()));
+        assert_eq!(take.finish(), None);
+    }
+
+    #[test]
+    fn test_commits_are_stitched_for_the_final_pass() {
+        let take = LiveTake::default();
+        take.commit(320_000, transcription("Première partie.", Some("fr")));
+        take.commit(160_000, transcription("Deuxième partie.", Some("fr")));
+        assert_eq!(take.position(), (480_000, "Première partie. Deuxième partie.".to_string()));
+        assert_eq!(
+            take.finish(),
+            Some(CommittedPrefix {
+                transcription: transcription("Première partie. Deuxième partie.", Some("fr")),
+                samples: 480_000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_nothing_changes_after_finish() {
+        let take = LiveTake::default();
+        take.commit(16_000, transcription("Un.", None));
+        take.finish();
+        take.commit(16_000, transcription("Deux.", None));
+        assert_eq!(take.next_partial("Trois"), None);
+        assert_eq!(take.finish().map(|c| c.samples), Some(16_000));
+    }
+
+    #[test]
+    fn test_next_partial_skips_repeats() {
+        let take = LiveTake::default();
+        take.commit(16_000, transcription("Un.", None));
+        assert_eq!(take.next_partial("deux"), Some("Un. deux".to_string()));
+        assert_eq!(take.next_partial("deux"), None);
+        assert_eq!(take.next_partial(""), Some("Un.".to_string()));
+    }
+
+    // ── commit_cut / uncommitted_audio ──────────────────────────────
+
+    #[test]
+    fn test_commit_cut_only_past_the_limit() {
+        let limit = (COMMIT_AFTER_SECS * WHISPER_SAMPLE_RATE) as usize;
+        assert_eq!(commit_cut(&vec![0.1; limit]), None);
+        let cut = commit_cut(&vec![0.1; limit + 16_000]).expect("cut");
+        assert!(cut > 0 && cut <= limit);
+    }
+
+    #[test]
+    fn test_uncommitted_audio_maps_offset_to_native_rate() {
+        let buffer = Mutex::new((0..48_000).map(|i| i as f32).collect::<Vec<_>>());
+        let rate = AtomicU32::new(48_000);
+        // 0.5 s committed at 16 kHz = 24 000 native samples; the other 0.5 s comes back at 16 kHz
+        assert_eq!(uncommitted_audio(&buffer, &rate, 8_000).len(), 8_000);
+        assert!(uncommitted_audio(&buffer, &rate, 32_000).is_empty());
+    }
+}
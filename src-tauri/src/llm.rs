@@ -1,54 +1,40 @@
-use crate::http_client;
-use futures_util::StreamExt;
-use serde::{Deserialize, Serialize};
+use crate::fallback::FallbackTrail;
+use crate::llm_provider::{self, LlmProvider, LlmRequest, OnDelta};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 use tauri::Emitter;
 
 const MAX_RETRIES: u32 = 3;
 
-#[derive(Serialize)]
-struct ChatRequest {
-    model: String,
-    messages: Vec<Message>,
-    temperature: f32,
-    max_tokens: u32,
-    stream: bool,
+/// Chat formats wrap each message in role markers and separators.
+const MESSAGE_OVERHEAD_TOKENS: u64 = 4;
+
+/// Tokens billed for one call, as reported by the provider, or counted locally
+/// (`estimate_tokens`: exact BPE for OpenAI models, a rough heuristic otherwise) when it
+/// reported none.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input: u64,
+    pub output: u64,
+    pub estimated: bool,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-struct Message {
-    role: String,
-    content: String,
+/// `llm_delta` payload; `first` marks the first delta of an attempt, whose text replaces
+/// what a failed attempt streamed.
+#[derive(Clone, serde::Serialize)]
+struct LlmDelta<'a> {
+    text: &'a str,
+    first: bool,
 }
 
-#[derive(Deserialize)]
-struct StreamChunk {
-    choices: Option<Vec<StreamChoice>>,
-}
-
-#[derive(Deserialize)]
-struct StreamChoice {
-    delta: StreamDelta,
-}
-
-#[derive(Deserialize, Default)]
-struct StreamDelta {
-    content: Option<String>,
-}
-
-#[derive(Deserialize)]
-struct ChatCompletionResponse {
-    choices: Option<Vec<ChatChoice>>,
-}
-
-#[derive(Deserialize)]
-struct ChatChoice {
-    message: ChatMessage,
-}
-
-#[derive(Deserialize)]
-struct ChatMessage {
-    content: Option<String>,
+/// Transformed text and the backend that produced it (the fallback's after a fallback).
+#[derive(Debug, Clone, PartialEq)]
+pub struct LlmOutput {
+    pub text: String,
+    pub provider: String,
+    pub model: String,
+    /// None when the provider did not report it.
+    pub usage: Option<TokenUsage>,
 }
 
 const IMPROVE_SYSTEM_PROMPT_META: &str = r#"You are an expert at improving system prompts. The prompt you will improve is used by a prompt-enhancer app: it instructs a model to rewrite the user's raw voice input. The enhancer's output may be either (a) a REQUEST to paste into another AI tool, or (b) a final deliverable (e.g. Slack message, email) ready to paste — the improved prompt must match what the user asked for.
@@ -73,362 +59,182 @@ OUTPUT:
 /// Transformation async avec streaming : accumule le contenu puis retourne le texte complet.
 /// Si `cancel` est déclenché, retourne Err("Annulé").
 /// `temperature_override` permet de forcer une température pour les modes built-in.
-/// If the primary provider (e.g. Groq) fails after all retries and an OpenAI key exists,
-/// automatically falls back to OpenAI and emits a `provider_fallback` event.
+/// Once the configured provider has failed after all retries, each hop of the fallback
+/// chain (`LlmPrefs::fallback_chain`) gets one attempt, announced by a `provider_fallback`
+/// event naming it.
+/// `on_delta` receives the streamed text as it arrives; since it may act on it for good
+/// (typing into the focused field), a failure after its first delta is final. With
+/// `behavior.stream_preview`, deltas also go to the floating window as `llm_delta`.
 pub async fn transform_text_streaming(
     text: &str,
     mode_prompt: &str,
     app: &tauri::AppHandle,
     cancel: tokio_util::sync::CancellationToken,
     temperature_override: Option<f32>,
-) -> Result<String, String> {
+    on_delta: Option<OnDelta<'_>>,
+) -> Result<LlmOutput, String> {
     if mode_prompt.is_empty() {
-        return Ok(text.to_string());
+        return Ok(LlmOutput {
+            text: text.to_string(),
+            provider: String::new(),
+            model: String::new(),
+            usage: None,
+        });
     }
 
-    let mut attempt = 0;
-    let primary_error = loop {
-        match transform_text_streaming_internal(text, mode_prompt, app, cancel.clone(), temperature_override).await {
-            Ok(result) => return Ok(result),
-            Err(e) if e == "Annulé" => return Err(e),
-            Err(e) if attempt < MAX_RETRIES => {
-                attempt += 1;
-                let backoff = Duration::from_millis(150 * 2u64.pow(attempt));
-                #[cfg(debug_assertions)]
-                eprintln!(
-                    "Transformation LLM tentative {}/{} échouée: {}",
-                    attempt, MAX_RETRIES, e
-                );
-                tokio::time::sleep(backoff).await;
-            }
-            Err(e) => {
-                break e;
-            }
+    let prefs = crate::preferences::get_preferences(app).unwrap_or_default();
+    let system = build_system_prompt(mode_prompt);
+    let request = LlmRequest {
+        system: &system,
+        text,
+        temperature: temperature_override.unwrap_or(prefs.llm.temperature).clamp(0.0, 2.0),
+        max_tokens: prefs.llm.max_tokens.clamp(100, 4096),
+    };
+    let preview = prefs.behavior.stream_preview;
+    let delivered = AtomicBool::new(false);
+
+    let mut trail = match llm_provider::primary(&prefs) {
+        // Missing key or base URL: retrying cannot help
+        Err(e) => FallbackTrail::new(&prefs.llm.provider, None, e),
+        Ok(provider) => {
+            let mut attempt = 0;
+            let error = loop {
+                match stream_attempt(&*provider, &request, app, cancel.clone(), preview, on_delta, &delivered).await {
+                    Ok(result) => return Ok(account(result, &request, app)),
+                    Err(e) if e == "Annulé" => return Err(e),
+                    // Another attempt would type its text after the partial one
+                    Err(e) if delivered.load(Ordering::Relaxed) => return Err(e),
+                    Err(e) if attempt < MAX_RETRIES => {
+                        attempt += 1;
+                        let backoff = Duration::from_millis(150 * 2u64.pow(attempt));
+                        #[cfg(debug_assertions)]
+                        eprintln!(
+                            "Transformation LLM tentative {}/{} échouée ({}): {}",
+                            attempt, MAX_RETRIES, provider.name(), e
+                        );
+                        tokio::time::sleep(backoff).await;
+                    }
+                    Err(e) => break e,
+                }
+            };
+            FallbackTrail::new(provider.name(), Some((provider.id(), provider.model())), error)
         }
     };
 
-    // Provider fallback: if primary was Groq, try OpenAI
-    let prefs = crate::preferences::get_preferences(app).unwrap_or_default();
-    if prefs.llm.provider == "groq" && crate::secrets::get_key_for_provider("openai").or_else(|_| crate::secrets::get_api_key_cached()).is_ok() {
-        #[cfg(debug_assertions)]
-        eprintln!("Groq LLM failed, falling back to OpenAI: {}", primary_error);
-        let _ = app.emit("provider_fallback", "Groq → OpenAI");
-        match transform_text_streaming_openai_fallback(text, mode_prompt, app, cancel, temperature_override).await {
-            Ok(result) => return Ok(result),
-            Err(fallback_err) => {
-                return Err(format!(
-                    "Échec transformation après {} tentatives (Groq: {}, OpenAI fallback: {})",
-                    MAX_RETRIES, primary_error, fallback_err
-                ));
+    for hop in prefs.llm.fallback_chain() {
+        let provider = match llm_provider::for_hop(&hop, &prefs) {
+            Ok(provider) => provider,
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("LLM fallback {} skipped: {}", hop.provider, _e);
+                continue;
             }
+        };
+        let Some(label) = trail.hop(provider.id(), provider.name(), provider.model()) else {
+            continue;
+        };
+        #[cfg(debug_assertions)]
+        eprintln!("LLM fallback: {}", label);
+        let _ = app.emit("provider_fallback", label);
+        match stream_attempt(&*provider, &request, app, cancel.clone(), preview, on_delta, &delivered).await {
+            Ok(result) => return Ok(account(result, &request, app)),
+            Err(e) if e == "Annulé" => return Err(e),
+            Err(e) if delivered.load(Ordering::Relaxed) => return Err(e),
+            Err(e) => trail.failed(e),
         }
     }
 
-    Err(format!(
-        "Échec transformation après {} tentatives: {}",
-        MAX_RETRIES, primary_error
-    ))
+    Err(trail.into_error("transformation", MAX_RETRIES))
 }
 
-/// Single-attempt LLM call using OpenAI as fallback provider.
-async fn transform_text_streaming_openai_fallback(
-    text: &str,
-    mode_prompt: &str,
+/// One streamed call, forwarding its deltas; `delivered` is set once `on_delta` got text.
+async fn stream_attempt(
+    provider: &dyn LlmProvider,
+    request: &LlmRequest<'_>,
     app: &tauri::AppHandle,
     cancel: tokio_util::sync::CancellationToken,
-    temperature_override: Option<f32>,
-) -> Result<String, String> {
-    let prefs = crate::preferences::get_preferences(app).unwrap_or_default();
-    let (api_key, base_url, model) = resolve_openai_fallback_config(&prefs)?;
-    let timeout_secs = prefs.llm.timeout_secs.clamp(15, 120);
-    let url = format!("{}/v1/chat/completions", base_url);
-    let temperature = temperature_override.unwrap_or(prefs.llm.temperature).clamp(0.0, 2.0);
-
-    let request = ChatRequest {
-        model,
-        messages: vec![
-            Message {
-                role: "system".to_string(),
-                content: build_system_prompt(mode_prompt),
-            },
-            Message {
-                role: "user".to_string(),
-                content: text.to_string(),
-            },
-        ],
-        temperature,
-        max_tokens: prefs.llm.max_tokens.clamp(100, 4096),
-        stream: true,
-    };
-
-    let resp = http_client::client()
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .timeout(Duration::from_secs(timeout_secs))
-        .send()
-        .await
-        .map_err(|e| format!("OpenAI fallback LLM request error: {}", e))?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_default();
-        return Err(format!("OpenAI fallback LLM error {}: {}", status, body));
-    }
-
-    let mut stream = resp.bytes_stream();
-    let mut buf = Vec::<u8>::new();
-    let mut content = String::new();
-
-    loop {
-        let chunk_result = tokio::select! {
-            _ = cancel.cancelled() => return Err("Annulé".to_string()),
-            next = stream.next() => next,
-        };
-        let Some(chunk_result) = chunk_result else {
-            break;
-        };
-        let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
-        buf.extend_from_slice(&chunk);
-
-        while let Some((line, rest)) = parse_one_line(&buf) {
-            buf = rest;
-            if line.is_empty() {
-                continue;
-            }
-            if line.starts_with("data: ") {
-                let data = line.trim_start_matches("data: ").trim();
-                if data == "[DONE]" {
-                    return Ok(content.trim().to_string());
-                }
-                if let Ok(parsed) = serde_json::from_str::<StreamChunk>(data) {
-                    if let Some(choices) = parsed.choices {
-                        if let Some(choice) = choices.first() {
-                            if let Some(ref delta) = choice.delta.content {
-                                content.push_str(delta);
-                                let _ = app.emit("llm_chunk", content.trim().to_string());
-                            }
-                        }
-                    }
-                }
-            }
+    preview: bool,
+    on_delta: Option<OnDelta<'_>>,
+    delivered: &AtomicBool,
+) -> Result<LlmOutput, String> {
+    let first = AtomicBool::new(true);
+    let forward = |delta: &str| {
+        if preview {
+            let first = first.swap(false, Ordering::Relaxed);
+            let _ = app.emit_to("floating", "llm_delta", LlmDelta { text: delta, first });
         }
-    }
-
-    Ok(content.trim().to_string())
+        if let Some(on_delta) = on_delta {
+            delivered.store(true, Ordering::Relaxed);
+            on_delta(delta);
+        }
+    };
+    provider.stream(request, app, cancel, &forward).await
 }
 
 fn build_system_prompt(mode_prompt: &str) -> String {
     mode_prompt.to_string()
 }
 
-/// Validate that a base URL uses HTTPS. Rejects plain HTTP to protect API keys in transit.
-fn validate_base_url(url: &str) -> Result<(), String> {
-    if url.starts_with("https://") {
-        Ok(())
-    } else if url.starts_with("http://") {
-        Err("Insecure base URL rejected: plain HTTP is not allowed for API endpoints that transmit API keys. Use https:// instead.".to_string())
-    } else {
-        Err(format!("Invalid base URL '{}': must start with https://", url))
-    }
-}
-
-/// Resolve LLM provider config: API key, base URL, and model.
-fn resolve_llm_config(prefs: &crate::preferences::Preferences) -> Result<(String, String, String), String> {
-    let provider = prefs.llm.provider.as_str();
-    match provider {
-        "groq" => {
-            let key = crate::secrets::get_key_for_provider("groq")
-                .map_err(|_| "No Groq API key found. Add a Groq key (gsk_...) in Settings → API Keys.".to_string())?;
-            let url = prefs.advanced.llm_base_url
-                .as_deref()
-                .unwrap_or("https://api.groq.com/openai")
-                .trim_end_matches('/')
-                .to_string();
-            validate_base_url(&url)?;
-            // Default to llama-3.1-8b-instant if user hasn't picked a Groq model
-            let model = if prefs.llm.model.starts_with("llama") || prefs.llm.model.starts_with("mixtral") || prefs.llm.model.starts_with("qwen") {
-                prefs.llm.model.clone()
-            } else {
-                "llama-3.1-8b-instant".to_string()
-            };
-            Ok((key, url, model))
-        }
+/// Local token count, for providers that report no usage. OpenAI models are counted with
+/// their own BPE (o200k / cl100k); other families (Llama, Claude, Ollama models) have
+/// tokenizers of their own, so they only get a rough heuristic: about 4 ASCII characters
+/// per token, accented and non-Latin characters cost more.
+pub fn estimate_tokens(model: &str, text: &str) -> u64 {
+    use tiktoken_rs::tokenizer::{get_tokenizer, Tokenizer};
+    // "openai/gpt-4o" on routers
+    let model = model.rsplit('/').next().unwrap_or(model);
+    let bpe = match get_tokenizer(model) {
+        Some(Tokenizer::O200kBase) => tiktoken_rs::o200k_base_singleton(),
+        Some(Tokenizer::Cl100kBase) => tiktoken_rs::cl100k_base_singleton(),
         _ => {
-            let key = crate::secrets::get_key_for_provider("openai")
-                .or_else(|_| crate::secrets::get_api_key_cached())?;
-            let url = prefs.advanced.llm_base_url
-                .as_deref()
-                .unwrap_or("https://api.openai.com")
-                .trim_end_matches('/')
-                .to_string();
-            validate_base_url(&url)?;
-            Ok((key, url, prefs.llm.model.clone()))
+            let ascii = text.chars().filter(char::is_ascii).count() as u64;
+            let other = text.chars().count() as u64 - ascii;
+            return (ascii + 3) / 4 + (other + 1) / 2;
         }
-    }
-}
-
-/// Resolve LLM config specifically for OpenAI fallback (ignores current provider setting).
-fn resolve_openai_fallback_config(prefs: &crate::preferences::Preferences) -> Result<(String, String, String), String> {
-    let key = crate::secrets::get_key_for_provider("openai")
-        .or_else(|_| crate::secrets::get_api_key_cached())?;
-    let url = prefs.advanced.llm_base_url
-        .as_deref()
-        .unwrap_or("https://api.openai.com")
-        .trim_end_matches('/')
-        .to_string();
-    validate_base_url(&url)?;
-    Ok((key, url, "gpt-4o-mini".to_string()))
-}
-
-async fn transform_text_streaming_internal(
-    text: &str,
-    mode_prompt: &str,
-    app: &tauri::AppHandle,
-    cancel: tokio_util::sync::CancellationToken,
-    temperature_override: Option<f32>,
-) -> Result<String, String> {
-    let prefs = crate::preferences::get_preferences(app).unwrap_or_default();
-    let (api_key, base_url, model) = resolve_llm_config(&prefs)?;
-    let timeout_secs = prefs.llm.timeout_secs.clamp(15, 120);
-    let url = format!("{}/v1/chat/completions", base_url);
-
-    let temperature = temperature_override.unwrap_or(prefs.llm.temperature).clamp(0.0, 2.0);
-
-    let request = ChatRequest {
-        model,
-        messages: vec![
-            Message {
-                role: "system".to_string(),
-                content: build_system_prompt(mode_prompt),
-            },
-            Message {
-                role: "user".to_string(),
-                content: text.to_string(),
-            },
-        ],
-        temperature,
-        max_tokens: prefs.llm.max_tokens.clamp(100, 4096),
-        stream: true,
     };
+    let count = bpe.lock().encode_ordinary(text).len();
+    count as u64
+}
 
-    let resp = http_client::client()
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .timeout(Duration::from_secs(timeout_secs))
-        .send()
-        .await
-        .map_err(|e| format!("Erreur requête API LLM: {}", e))?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_default();
-        return Err(format!("API LLM error {}: {}", status, body));
-    }
-
-    let mut stream = resp.bytes_stream();
-    let mut buf = Vec::<u8>::new();
-    let mut content = String::new();
-
-    loop {
-        let chunk_result = tokio::select! {
-            _ = cancel.cancelled() => return Err("Annulé".to_string()),
-            next = stream.next() => next,
-        };
-        let Some(chunk_result) = chunk_result else {
-            break;
-        };
-        let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
-        buf.extend_from_slice(&chunk);
-
-        while let Some((line, rest)) = parse_one_line(&buf) {
-            buf = rest;
-            if line.is_empty() {
-                continue;
-            }
-            if line.starts_with("data: ") {
-                let data = line.trim_start_matches("data: ").trim();
-                if data == "[DONE]" {
-                    return Ok(content.trim().to_string());
-                }
-                if let Ok(parsed) = serde_json::from_str::<StreamChunk>(data) {
-                    if let Some(choices) = parsed.choices {
-                        if let Some(choice) = choices.first() {
-                            if let Some(ref delta) = choice.delta.content {
-                                content.push_str(delta);
-                                let _ = app.emit("llm_chunk", content.trim().to_string());
-                            }
-                        }
-                    }
-                }
-            }
-        }
+/// `output` with its usage, estimated from the request when the provider reported none.
+fn with_usage(mut output: LlmOutput, request: &LlmRequest<'_>) -> LlmOutput {
+    if output.usage.is_none() {
+        output.usage = Some(TokenUsage {
+            input: estimate_tokens(&output.model, request.system)
+                + estimate_tokens(&output.model, request.text)
+                + 2 * MESSAGE_OVERHEAD_TOKENS,
+            output: estimate_tokens(&output.model, &output.text),
+            estimated: true,
+        });
     }
+    output
+}
 
-    Ok(content.trim().to_string())
+/// Complete the usage of a successful call and add it to the usage statistics.
+fn account(output: LlmOutput, request: &LlmRequest<'_>, app: &tauri::AppHandle) -> LlmOutput {
+    let output = with_usage(output, request);
+    crate::usage::record_llm(app, &output);
+    output
 }
 
 /// Améliore un system prompt (meta: clarté, structure, concision, alignement Ghosty). Appel non-streaming.
 pub async fn improve_system_prompt(prompt: &str, app: &tauri::AppHandle) -> Result<String, String> {
     let prefs = crate::preferences::get_preferences(app).unwrap_or_default();
-    let (api_key, base_url, model) = resolve_llm_config(&prefs)?;
-    let timeout_secs = prefs.llm.timeout_secs.clamp(15, 120);
-    let url = format!("{}/v1/chat/completions", base_url);
-
-    let request = ChatRequest {
-        model,
-        messages: vec![
-            Message {
-                role: "system".to_string(),
-                content: IMPROVE_SYSTEM_PROMPT_META.to_string(),
-            },
-            Message {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            },
-        ],
+    let provider = llm_provider::primary(&prefs)?;
+    let request = LlmRequest {
+        system: IMPROVE_SYSTEM_PROMPT_META,
+        text: prompt,
         temperature: 0.3,
         max_tokens: prefs.llm.max_tokens.clamp(500, 8192),
-        stream: false,
     };
-
-    let resp = http_client::client()
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .json(&request)
-        .timeout(Duration::from_secs(timeout_secs))
-        .send()
-        .await
-        .map_err(|e| format!("Erreur requête API LLM: {}", e))?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_default();
-        return Err(format!("API LLM error {}: {}", status, body));
-    }
-
-    let parsed: ChatCompletionResponse = resp
-        .json()
-        .await
-        .map_err(|e| format!("Réponse API invalide: {}", e))?;
-
-    let content = parsed
-        .choices
-        .and_then(|c| c.into_iter().next())
-        .and_then(|c| c.message.content)
-        .map(|s| s.trim().to_string())
+    let output = account(provider.complete(&request).await?, &request, app);
+    Some(output.text)
         .filter(|s| !s.is_empty())
-        .ok_or_else(|| "Réponse LLM vide".to_string())?;
-
-    Ok(content)
+        .ok_or_else(|| "Réponse LLM vide".to_string())
 }
 
 /// Retourne une ligne (jusqu'à \n) et le reste du buffer.
-fn parse_one_line(buf: &[u8]) -> Option<(String, Vec<u8>)> {
+pub fn parse_one_line(buf: &[u8]) -> Option<(String, Vec<u8>)> {
     let pos = buf.iter().position(|&b| b == b'\n')?;
     let line = String::from_utf8_lossy(&buf[..pos]).to_string();
     let rest = buf[pos + 1..].to_vec();
@@ -513,49 +319,48 @@ mod tests {
         assert_eq!(build_system_prompt(""), "");
     }
 
-    // ── ChatRequest serialization ───────────────────────────────────
-
-    #[test]
-    fn test_chat_request_serialization() {
-        let req = ChatRequest {
-            model: "gpt-4o-mini".to_string(),
-            messages: vec![
-                Message { role: "system".to_string(), content: "You are helpful.".to_string() },
-                Message { role: "user".to_string(), content: "Hello".to_string() },
-            ],
-            temperature: 0.3,
-            max_tokens: 1024,
-            stream: true,
-        };
-        let json = serde_json::to_value(&req).unwrap();
-        assert_eq!(json["model"], "gpt-4o-mini");
-        assert_eq!(json["stream"], true);
-        assert_eq!(json["messages"].as_array().unwrap().len(), 2);
-    }
-
-    // ── StreamChunk deserialization ──────────────────────────────────
+    // ── estimate_tokens ─────────────────────────────────────────────
 
     #[test]
-    fn test_stream_chunk_deserialization() {
-        let json = r#"{"choices":[{"delta":{"content":"Hello"}}]}"#;
-        let chunk: StreamChunk = serde_json::from_str(json).unwrap();
-        let binding = chunk.choices.unwrap();
-        let content = binding[0].delta.content.as_ref().unwrap();
-        assert_eq!(content, "Hello");
+    fn test_estimate_tokens_heuristic() {
+        let model = "llama-3.1-8b-instant";
+        assert_eq!(estimate_tokens(model, ""), 0);
+        assert_eq!(estimate_tokens(model, "Hello world!"), 3);
+        // Accented characters cost more than ASCII
+        assert!(estimate_tokens(model, "élève éphémère") > estimate_tokens(model, "eleve ephemere"));
     }
 
     #[test]
-    fn test_stream_chunk_empty_delta() {
-        let json = r#"{"choices":[{"delta":{}}]}"#;
-        let chunk: StreamChunk = serde_json::from_str(json).unwrap();
-        assert!(chunk.choices.unwrap()[0].delta.content.is_none());
+    fn test_estimate_tokens_openai_bpe() {
+        assert_eq!(estimate_tokens("gpt-4o-mini", ""), 0);
+        assert_eq!(estimate_tokens("gpt-4o-mini", "Hello world!"), 3);
+        assert_eq!(estimate_tokens("gpt-3.5-turbo", "Hello world!"), 3);
+        assert_eq!(estimate_tokens("openai/gpt-4o", "Hello world!"), 3);
+        // A long repeated word is one token per repetition, far from chars / 4
+        let text = " hello".repeat(50);
+        assert_eq!(estimate_tokens("gpt-4o", &text), 50);
     }
 
     #[test]
-    fn test_stream_chunk_no_choices() {
-        let json = r#"{}"#;
-        let chunk: StreamChunk = serde_json::from_str(json).unwrap();
-        assert!(chunk.choices.is_none());
+    fn test_with_usage_estimates_only_when_missing() {
+        let request = LlmRequest {
+            system: "Corrige.",
+            text: "bonjour tout le monde",
+            temperature: 0.3,
+            max_tokens: 1024,
+        };
+        let output = |usage| LlmOutput {
+            text: "Bonjour tout le monde.".to_string(),
+            provider: "groq".to_string(),
+            model: "llama-3.1-8b-instant".to_string(),
+            usage,
+        };
+        let estimated = with_usage(output(None), &request).usage.unwrap();
+        assert!(estimated.estimated);
+        assert_eq!(estimated.input, 2 + 6 + 2 * MESSAGE_OVERHEAD_TOKENS);
+        assert_eq!(estimated.output, 6);
+        let reported = TokenUsage { input: 30, output: 7, estimated: false };
+        assert_eq!(with_usage(output(Some(reported)), &request).usage, Some(reported));
     }
 
     // ── IMPROVE_SYSTEM_PROMPT_META ──────────────────────────────────
@@ -565,25 +370,4 @@ mod tests {
         assert!(!IMPROVE_SYSTEM_PROMPT_META.is_empty());
         assert!(IMPROVE_SYSTEM_PROMPT_META.contains("expert"));
     }
-
-    // ── validate_base_url ──────────────────────────────────────────
-
-    #[test]
-    fn test_validate_base_url_https_ok() {
-        assert!(validate_base_url("https://api.openai.com").is_ok());
-        assert!(validate_base_url("https://api.groq.com/openai").is_ok());
-    }
-
-    #[test]
-    fn test_validate_base_url_http_rejected() {
-        let result = validate_base_url("http://api.openai.com");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("plain HTTP"));
-    }
-
-    #[test]
-    fn test_validate_base_url_invalid_scheme() {
-        assert!(validate_base_url("ftp://example.com").is_err());
-        assert!(validate_base_url("api.openai.com").is_err());
-    }
 }
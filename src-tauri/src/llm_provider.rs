@@ -0,0 +1,549 @@
+/// LLM backends behind one trait, looked up by the provider id in `preferences.llm` and its fallback chain.
+use crate::http_client;
+use crate::llm::{parse_one_line, LlmOutput, TokenUsage};
+use crate::preferences::{FallbackHop, Preferences};
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::Emitter;
+use tokio_util::sync::CancellationToken;
+
+/// One call, independent of the backend: a system prompt and a single user turn.
+pub struct LlmRequest<'a> {
+    pub system: &'a str,
+    pub text: &'a str,
+    pub temperature: f32,
+    pub max_tokens: u32,
+}
+
+/// Receives each streamed text delta, in order, as it arrives.
+pub type OnDelta<'a> = &'a (dyn Fn(&str) + Send + Sync);
+
+pub trait LlmProvider: Send + Sync {
+    /// Registry id, as stored in preferences ("openai", "groq"…).
+    fn id(&self) -> &'static str;
+    /// Human-readable name for logs, error messages and `provider_fallback` events.
+    fn name(&self) -> &'static str;
+    /// Model actually requested.
+    fn model(&self) -> &str;
+    /// Streamed call: each delta goes to `on_delta`, then the accumulated text (trimmed)
+    /// is emitted as `llm_chunk`. Err("Annulé") once `cancel` fires; the returned text is
+    /// trimmed.
+    fn stream<'a>(
+        &'a self,
+        request: &'a LlmRequest<'a>,
+        app: &'a tauri::AppHandle,
+        cancel: CancellationToken,
+        on_delta: OnDelta<'a>,
+    ) -> BoxFuture<'a, Result<LlmOutput, String>>;
+    /// Non-streamed call (prompt improvement); the text is trimmed.
+    fn complete<'a>(&'a self, request: &'a LlmRequest<'a>) -> BoxFuture<'a, Result<LlmOutput, String>>;
+}
+
+/// Where a provider is called: from `LlmPrefs` and the Advanced base URL for the primary,
+/// from its `FallbackHop` for a hop.
+pub struct Endpoint {
+    /// None = the provider's default model.
+    pub model: Option<String>,
+    /// None = the vendor's endpoint.
+    pub base_url: Option<String>,
+    pub timeout: Duration,
+}
+
+impl Endpoint {
+    /// `base_url` if set, else the vendor's default; validated.
+    pub fn resolve_base_url(&self, default: &str) -> Result<String, String> {
+        let url = self
+            .base_url
+            .as_deref()
+            .unwrap_or(default)
+            .trim_end_matches('/')
+            .to_string();
+        http_client::validate_base_url(&url)?;
+        Ok(url)
+    }
+}
+
+#[derive(Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<Message>,
+    temperature: f32,
+    max_tokens: u32,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<StreamOptions>,
+}
+
+#[derive(Serialize)]
+struct StreamOptions {
+    /// Adds a last chunk, with no choices, carrying the usage of the whole call.
+    include_usage: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct StreamChunk {
+    choices: Option<Vec<StreamChoice>>,
+    /// Final chunk only, with `stream_options.include_usage`.
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+    /// Groq reports the usage here on the final chunk, unasked.
+    #[serde(default)]
+    x_groq: Option<GroqExtension>,
+}
+
+#[derive(Deserialize)]
+struct GroqExtension {
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct ChatUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
+}
+
+impl From<ChatUsage> for TokenUsage {
+    fn from(usage: ChatUsage) -> Self {
+        Self {
+            input: usage.prompt_tokens,
+            output: usage.completion_tokens,
+            estimated: false,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    delta: StreamDelta,
+}
+
+#[derive(Deserialize, Default)]
+struct StreamDelta {
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatCompletionResponse {
+    choices: Option<Vec<ChatChoice>>,
+    #[serde(default)]
+    usage: Option<ChatUsage>,
+}
+
+#[derive(Deserialize)]
+struct ChatChoice {
+    message: ChatMessage,
+}
+
+#[derive(Deserialize)]
+struct ChatMessage {
+    content: Option<String>,
+}
+
+/// Any endpoint implementing OpenAI's chat completions API.
+pub struct ChatCompletions {
+    id: &'static str,
+    name: &'static str,
+    /// Without the `/v1/...` suffix.
+    base_url: String,
+    /// Local servers often need none.
+    api_key: Option<String>,
+    model: String,
+    timeout: Duration,
+    /// Accepts `stream_options.include_usage`.
+    stream_usage: bool,
+}
+
+impl ChatCompletions {
+    fn chat_request(&self, request: &LlmRequest<'_>, stream: bool) -> ChatRequest {
+        ChatRequest {
+            model: self.model.clone(),
+            messages: vec![
+                Message {
+                    role: "system".to_string(),
+                    content: request.system.to_string(),
+                },
+                Message {
+                    role: "user".to_string(),
+                    content: request.text.to_string(),
+                },
+            ],
+            temperature: request.temperature,
+            max_tokens: request.max_tokens,
+            stream,
+            stream_options: (stream && self.stream_usage).then_some(StreamOptions { include_usage: true }),
+        }
+    }
+
+    async fn send(&self, request: &LlmRequest<'_>, stream: bool) -> Result<reqwest::Response, String> {
+        let resp = http_client::post(&self.base_url, "/v1/chat/completions", self.api_key.as_deref())?
+            .header("Content-Type", "application/json")
+            .json(&self.chat_request(request, stream))
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(|e| format!("Erreur requête API LLM ({}): {}", self.name, e))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(format!("{} API error {}: {}", self.name, status, body));
+        }
+        Ok(resp)
+    }
+
+    fn output(&self, text: &str, usage: Option<ChatUsage>) -> LlmOutput {
+        LlmOutput {
+            text: text.trim().to_string(),
+            provider: self.id.to_string(),
+            model: self.model.clone(),
+            usage: usage.map(TokenUsage::from),
+        }
+    }
+}
+
+impl LlmProvider for ChatCompletions {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn stream<'a>(
+        &'a self,
+        request: &'a LlmRequest<'a>,
+        app: &'a tauri::AppHandle,
+        cancel: CancellationToken,
+        on_delta: OnDelta<'a>,
+    ) -> BoxFuture<'a, Result<LlmOutput, String>> {
+        Box::pin(async move {
+            let resp = self.send(request, true).await?;
+            let mut stream = resp.bytes_stream();
+            let mut buf = Vec::<u8>::new();
+            let mut content = String::new();
+            let mut usage = None;
+
+            loop {
+                let chunk_result = tokio::select! {
+                    _ = cancel.cancelled() => return Err("Annulé".to_string()),
+                    next = stream.next() => next,
+                };
+                let Some(chunk_result) = chunk_result else {
+                    break;
+                };
+                let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
+                buf.extend_from_slice(&chunk);
+
+                while let Some((line, rest)) = parse_one_line(&buf) {
+                    buf = rest;
+                    if line.is_empty() {
+                        continue;
+                    }
+                    if line.starts_with("data: ") {
+                        let data = line.trim_start_matches("data: ").trim();
+                        if data == "[DONE]" {
+                            return Ok(self.output(&content, usage));
+                        }
+                        if let Ok(parsed) = serde_json::from_str::<StreamChunk>(data) {
+                            if let Some(reported) = parsed.usage.or(parsed.x_groq.and_then(|x| x.usage)) {
+                                usage = Some(reported);
+                            }
+                            if let Some(choices) = parsed.choices {
+                                if let Some(choice) = choices.first() {
+                                    if let Some(ref delta) = choice.delta.content {
+                                        content.push_str(delta);
+                                        on_delta(delta);
+                                        let _ = app.emit("llm_chunk", content.trim().to_string());
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+
+            Ok(self.output(&content, usage))
+        })
+    }
+
+    fn complete<'a>(&'a self, request: &'a LlmRequest<'a>) -> BoxFuture<'a, Result<LlmOutput, String>> {
+        Box::pin(async move {
+            let parsed: ChatCompletionResponse = self
+                .send(request, false)
+                .await?
+                .json()
+                .await
+                .map_err(|e| format!("Réponse API invalide: {}", e))?;
+            let content = parsed
+                .choices
+                .and_then(|c| c.into_iter().next())
+                .and_then(|c| c.message.content)
+                .unwrap_or_default();
+            Ok(self.output(&content, parsed.usage))
+        })
+    }
+}
+
+fn build_openai(endpoint: &Endpoint) -> Result<Box<dyn LlmProvider>, String> {
+    let base_url = endpoint.resolve_base_url("https://api.openai.com")?;
+    let key = crate::secrets::get_key_for_provider("openai")
+        .or_else(|_| crate::secrets::get_api_key_cached());
+    Ok(Box::new(ChatCompletions {
+        id: "openai",
+        name: "OpenAI",
+        api_key: http_client::key_unless_local(key, &base_url)?,
+        base_url,
+        model: endpoint.model.clone().unwrap_or_else(|| "gpt-4o-mini".to_string()),
+        timeout: endpoint.timeout,
+        stream_usage: true,
+    }))
+}
+
+fn build_groq(endpoint: &Endpoint) -> Result<Box<dyn LlmProvider>, String> {
+    let base_url = endpoint.resolve_base_url("https://api.groq.com/openai")?;
+    let key = crate::secrets::get_key_for_provider("groq")
+        .map_err(|_| "No Groq API key found. Add a Groq key (gsk_...) in Settings → API Keys.".to_string());
+    // Default to llama-3.1-8b-instant if user hasn't picked a Groq model
+    let model = endpoint
+        .model
+        .clone()
+        .filter(|m| m.starts_with("llama") || m.starts_with("mixtral") || m.starts_with("qwen"))
+        .unwrap_or_else(|| "llama-3.1-8b-instant".to_string());
+    Ok(Box::new(ChatCompletions {
+        id: "groq",
+        name: "Groq",
+        api_key: http_client::key_unless_local(key, &base_url)?,
+        base_url,
+        model,
+        timeout: endpoint.timeout,
+        // Usage comes in `x_groq` anyway
+        stream_usage: false,
+    }))
+}
+
+/// Ollama's OpenAI-compatible API, on this machine unless a base URL says otherwise.
+/// Optional key stored under "ollama" (for a server behind an authenticating proxy).
+fn build_ollama(endpoint: &Endpoint) -> Result<Box<dyn LlmProvider>, String> {
+    Ok(Box::new(ChatCompletions {
+        id: "ollama",
+        name: "Ollama",
+        base_url: endpoint.resolve_base_url("http://127.0.0.1:11434")?,
+        api_key: crate::secrets::get_own_key_for_provider("ollama").ok(),
+        model: endpoint.model.clone().unwrap_or_else(|| "llama3.2".to_string()),
+        timeout: endpoint.timeout,
+        stream_usage: true,
+    }))
+}
+
+type ProviderBuilder = fn(&Endpoint) -> Result<Box<dyn LlmProvider>, String>;
+
+/// Registered providers by id, in display order. The first one is the default.
+const REGISTRY: &[(&str, ProviderBuilder)] = &[
+    ("openai", build_openai),
+    ("groq", build_groq),
+    ("anthropic", crate::anthropic::build),
+    ("ollama", build_ollama),
+];
+
+/// Ids accepted in `preferences.llm.provider` and in fallback hops.
+pub fn provider_ids() -> Vec<&'static str> {
+    REGISTRY.iter().map(|(id, _)| *id).collect()
+}
+
+/// The configured provider: `llm.provider` (unknown ids fall back to OpenAI, as before the
+/// registry existed), `llm.model` and the Advanced base URL. Fails when its key is missing.
+pub fn primary(prefs: &Preferences) -> Result<Box<dyn LlmProvider>, String> {
+    let (_, build) = REGISTRY
+        .iter()
+        .find(|(registered, _)| *registered == prefs.llm.provider)
+        .unwrap_or(&REGISTRY[0]);
+    build(&Endpoint {
+        model: Some(prefs.llm.model.clone()),
+        base_url: prefs.advanced.llm_base_url.clone(),
+        timeout: Duration::from_secs(prefs.llm.timeout_secs.clamp(15, 120)),
+    })
+}
+
+/// The provider of one fallback hop. Hops fail fast, so their timeout may go below the
+/// primary's 15 s floor.
+pub fn for_hop(hop: &FallbackHop, prefs: &Preferences) -> Result<Box<dyn LlmProvider>, String> {
+    let (_, build) = REGISTRY
+        .iter()
+        .find(|(registered, _)| *registered == hop.provider)
+        .ok_or_else(|| format!("Unknown LLM provider '{}'", hop.provider))?;
+    build(&Endpoint {
+        model: hop.model.clone(),
+        base_url: hop.base_url.clone(),
+        timeout: Duration::from_secs(hop.timeout_secs.unwrap_or(prefs.llm.timeout_secs).clamp(5, 120)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hop(provider: &str, model: Option<&str>) -> FallbackHop {
+        FallbackHop {
+            provider: provider.to_string(),
+            model: model.map(str::to_string),
+            timeout_secs: None,
+            base_url: None,
+        }
+    }
+
+    // ── registry ────────────────────────────────────────────────────
+
+    #[test]
+    fn test_registry_ids_unique_and_openai_first() {
+        let ids = provider_ids();
+        assert_eq!(ids[0], "openai");
+        let mut dedup = ids.clone();
+        dedup.sort();
+        dedup.dedup();
+        assert_eq!(dedup.len(), ids.len());
+        assert!(ids.contains(&"anthropic"));
+        assert!(ids.contains(&"ollama"));
+    }
+
+    #[test]
+    fn test_hop_defaults_to_provider_model() {
+        let prefs = Preferences::default();
+        let provider = for_hop(&hop("ollama", None), &prefs).unwrap();
+        assert_eq!(provider.id(), "ollama");
+        assert_eq!(provider.model(), "llama3.2");
+        let provider = for_hop(&hop("ollama", Some("qwen2.5:7b")), &prefs).unwrap();
+        assert_eq!(provider.model(), "qwen2.5:7b");
+    }
+
+    #[test]
+    fn test_hop_unknown_provider_is_an_error() {
+        let err = for_hop(&hop("mistral", None), &Preferences::default()).err().unwrap();
+        assert!(err.contains("mistral"));
+    }
+
+    #[test]
+    fn test_hop_rejects_plain_http_remote() {
+        let mut remote = hop("ollama", None);
+        remote.base_url = Some("http://ollama.example.com".to_string());
+        assert!(for_hop(&remote, &Preferences::default()).is_err());
+    }
+
+    #[test]
+    fn test_primary_local_endpoint_needs_no_key() {
+        let mut prefs = Preferences::default();
+        prefs.advanced.llm_base_url = Some("http://127.0.0.1:8080/".to_string());
+        let provider = primary(&prefs).unwrap();
+        assert_eq!(provider.id(), "openai");
+        assert_eq!(provider.model(), "gpt-4o-mini");
+    }
+
+    // ── ChatRequest serialization ───────────────────────────────────
+
+    #[test]
+    fn test_chat_request_serialization() {
+        let req = ChatRequest {
+            model: "gpt-4o-mini".to_string(),
+            messages: vec![
+                Message { role: "system".to_string(), content: "You are helpful.".to_string() },
+                Message { role: "user".to_string(), content: "Hello".to_string() },
+            ],
+            temperature: 0.3,
+            max_tokens: 1024,
+            stream: true,
+            stream_options: None,
+        };
+        let json = serde_json::to_value(&req).unwrap();
+        assert_eq!(json["model"], "gpt-4o-mini");
+        assert_eq!(json["stream"], true);
+        assert_eq!(json["messages"].as_array().unwrap().len(), 2);
+        assert!(json.get("stream_options").is_none());
+    }
+
+    #[test]
+    fn test_chat_request_asks_usage_when_streaming() {
+        let provider = ChatCompletions {
+            id: "openai",
+            name: "OpenAI",
+            base_url: "https://api.openai.com".to_string(),
+            api_key: None,
+            model: "gpt-4o-mini".to_string(),
+            timeout: Duration::from_secs(30),
+            stream_usage: true,
+        };
+        let request = LlmRequest { system: "Corrige.", text: "bonjour", temperature: 0.3, max_tokens: 1024 };
+        let streamed = serde_json::to_value(provider.chat_request(&request, true)).unwrap();
+        assert_eq!(streamed["stream_options"]["include_usage"], true);
+        let complete = serde_json::to_value(provider.chat_request(&request, false)).unwrap();
+        assert!(complete.get("stream_options").is_none());
+    }
+
+    // ── StreamChunk deserialization ──────────────────────────────────
+
+    #[test]
+    fn test_stream_chunk_deserialization() {
+        let json = r#"{"choices":[{"delta":{"content":"Hello"}}]}"#;
+        let chunk: StreamChunk = serde_json::from_str(json).unwrap();
+        let binding = chunk.choices.unwrap();
+        let content = binding[0].delta.content.as_ref().unwrap();
+        assert_eq!(content, "Hello");
+    }
+
+    #[test]
+    fn test_stream_chunk_empty_delta() {
+        let json = r#"{"choices":[{"delta":{}}]}"#;
+        let chunk: StreamChunk = serde_json::from_str(json).unwrap();
+        assert!(chunk.choices.unwrap()[0].delta.content.is_none());
+    }
+
+    #[test]
+    fn test_stream_chunk_no_choices() {
+        let json = r#"{}"#;
+        let chunk: StreamChunk = serde_json::from_str(json).unwrap();
+        assert!(chunk.choices.is_none());
+    }
+
+    #[test]
+    fn test_stream_chunk_final_usage() {
+        let json = r#"{"id":"chatcmpl-1","choices":[],"usage":{"prompt_tokens":42,"completion_tokens":17,"total_tokens":59}}"#;
+        let chunk: StreamChunk = serde_json::from_str(json).unwrap();
+        assert!(chunk.choices.unwrap().is_empty());
+        let usage = TokenUsage::from(chunk.usage.unwrap());
+        assert_eq!(usage, TokenUsage { input: 42, output: 17, estimated: false });
+        // Every other chunk has `"usage": null`
+        let chunk: StreamChunk = serde_json::from_str(r#"{"choices":[{"delta":{"content":"a"}}],"usage":null}"#).unwrap();
+        assert!(chunk.usage.is_none());
+    }
+
+    #[test]
+    fn test_stream_chunk_groq_usage() {
+        let json = r#"{"choices":[{"delta":{},"finish_reason":"stop"}],"x_groq":{"id":"req_1","usage":{"prompt_tokens":30,"completion_tokens":8}}}"#;
+        let chunk: StreamChunk = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            chunk.x_groq.and_then(|x| x.usage),
+            Some(ChatUsage { prompt_tokens: 30, completion_tokens: 8 })
+        );
+    }
+
+    #[test]
+    fn test_completion_response_usage() {
+        let json = r#"{"choices":[{"message":{"role":"assistant","content":"Ok"}}],"usage":{"prompt_tokens":12,"completion_tokens":1}}"#;
+        let parsed: ChatCompletionResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.usage, Some(ChatUsage { prompt_tokens: 12, completion_tokens: 1 }));
+    }
+}
@@ -0,0 +1,469 @@
+/// Transcription locale (whisper.cpp) : catalogue et téléchargement des modèles ggml dans
+/// le dossier de données de l'app, et — avec la feature `local-whisper` — le provider "local"
+/// qui transcrit sans réseau.
+use futures_util::StreamExt;
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+
+const MODELS_DIRNAME: &str = "models";
+const MODELS_BASE_URL: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main";
+
+/// Set once at startup: provider builders only receive the preferences.
+static MODELS_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Ids being downloaded, so a second click doesn't race on the same `.part` file.
+static DOWNLOADING: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+pub struct LocalModel {
+    /// Stored in `TranscriptionPrefs::local_model`.
+    pub id: &'static str,
+    file_name: &'static str,
+    pub size_mb: u32,
+    /// Hex sha256 of the published file; a download that doesn't match is discarded.
+    sha256: &'static str,
+}
+
+/// ggml models published by whisper.cpp, smallest first.
+pub const MODELS: &[LocalModel] = &[
+    LocalModel {
+        id: "tiny",
+        file_name: "ggml-tiny.bin",
+        size_mb: 75,
+        sha256: "be07e048e1e599ad46341c8d2a135645097a538221678b7acdd1b1919c6e1b21",
+    },
+    LocalModel {
+        id: "base",
+        file_name: "ggml-base.bin",
+        size_mb: 142,
+        sha256: "60ed5bc3dd14eea856493d334349b405782ddcaf0028d4b5df4088345fba2efe",
+    },
+    LocalModel {
+        id: "small",
+        file_name: "ggml-small.bin",
+        size_mb: 466,
+        sha256: "1be3a9b2063867b937e64e2ec7483364a79917e157fa98c5d94b5c1fffea987b",
+    },
+    LocalModel {
+        id: "large-v3-turbo-q5_0",
+        file_name: "ggml-large-v3-turbo-q5_0.bin",
+        size_mb: 547,
+        sha256: "394221709cd5ad1f40c46e6031ca61bce88931e6e088c188294c6d5a55ffa7e2",
+    },
+    LocalModel {
+        id: "medium",
+        file_name: "ggml-medium.bin",
+        size_mb: 1463,
+        sha256: "6c14d5adee5f86394037b4e4e8b59f1673b6cee10e3cf0b11bbdbee79c156208",
+    },
+    LocalModel {
+        id: "large-v3-turbo",
+        file_name: "ggml-large-v3-turbo.bin",
+        size_mb: 1549,
+        sha256: "1fc70f774d38eb169993ac391eea357ef47c88757ef72ee5943879b7e8e2bc69",
+    },
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalModelStatus {
+    pub id: String,
+    pub size_mb: u32,
+    pub downloaded: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DownloadProgress<'a> {
+    id: &'a str,
+    downloaded: u64,
+    total: Option<u64>,
+}
+
+fn find_model(id: &str) -> Result<&'static LocalModel, String> {
+    MODELS
+        .iter()
+        .find(|m| m.id == id)
+        .ok_or_else(|| format!("Modèle local inconnu : {}", id))
+}
+
+/// Record the models directory; called from `setup`.
+pub fn init(app: &tauri::AppHandle) {
+    if let Ok(dir) = app.path().app_data_dir() {
+        let _ = MODELS_DIR.set(dir.join(MODELS_DIRNAME));
+    }
+}
+
+fn models_dir() -> Result<&'static Path, String> {
+    MODELS_DIR
+        .get()
+        .map(PathBuf::as_path)
+        .ok_or_else(|| "Local models directory not initialised".to_string())
+}
+
+fn model_path_in(dir: &Path, model: &LocalModel) -> PathBuf {
+    dir.join(model.file_name)
+}
+
+/// Path of a downloaded model, or an error telling the user to download it.
+#[cfg_attr(not(feature = "local-whisper"), allow(dead_code))]
+pub fn model_path(id: &str) -> Result<PathBuf, String> {
+    let model = find_model(id)?;
+    let path = model_path_in(models_dir()?, model);
+    if path.is_file() {
+        Ok(path)
+    } else {
+        Err(format!(
+            "Modèle local « {} » absent. Téléchargez-le dans Réglages → Transcription.",
+            id
+        ))
+    }
+}
+
+fn statuses_in(dir: &Path) -> Vec<LocalModelStatus> {
+    MODELS
+        .iter()
+        .map(|m| LocalModelStatus {
+            id: m.id.to_string(),
+            size_mb: m.size_mb,
+            downloaded: model_path_in(dir, m).is_file(),
+        })
+        .collect()
+}
+
+pub fn list_models() -> Result<Vec<LocalModelStatus>, String> {
+    Ok(statuses_in(models_dir()?))
+}
+
+pub fn delete_model(id: &str) -> Result<(), String> {
+    let path = model_path_in(models_dir()?, find_model(id)?);
+    match std::fs::remove_file(&path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+/// Download a model into the models directory, emitting `local_model_download_progress`.
+/// Written to `<file>.part` and renamed at the end once its sha256 matches the catalog, so
+/// an interrupted or altered download is never mistaken for a model.
+pub async fn download_model(app: &tauri::AppHandle, id: &str) -> Result<(), String> {
+    let model = find_model(id)?;
+    {
+        let mut downloading = DOWNLOADING.lock().map_err(|e| e.to_string())?;
+        if !downloading.get_or_insert_with(HashSet::new).insert(id.to_string()) {
+            return Err(format!("Téléchargement du modèle « {} » déjà en cours", id));
+        }
+    }
+    let result = download_to(app, model).await;
+    if let Ok(mut downloading) = DOWNLOADING.lock() {
+        if let Some(set) = downloading.as_mut() {
+            set.remove(id);
+        }
+    }
+    result
+}
+
+async fn download_to(app: &tauri::AppHandle, model: &LocalModel) -> Result<(), String> {
+    let dir = models_dir()?;
+    std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    let dest = model_path_in(dir, model);
+    let part = dest.with_extension("bin.part");
+
+    let resp = crate::http_client::client()
+        .get(format!("{}/{}", MODELS_BASE_URL, model.file_name))
+        .send()
+        .await
+        .map_err(|e| format!("Erreur téléchargement modèle: {}", e))?;
+    if !resp.status().is_success() {
+        return Err(format!("Model download error {}", resp.status()));
+    }
+    let total = resp.content_length();
+
+    let mut file = std::fs::File::create(&part).map_err(|e| format!("{}: {}", part.display(), e))?;
+    let mut stream = resp.bytes_stream();
+    let mut hasher = Sha256::new();
+    let mut downloaded: u64 = 0;
+    let mut last_emit: u64 = 0;
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(c) => c,
+            Err(e) => {
+                let _ = std::fs::remove_file(&part);
+                return Err(format!("Erreur téléchargement modèle: {}", e));
+            }
+        };
+        if let Err(e) = file.write_all(&chunk) {
+            let _ = std::fs::remove_file(&part);
+            return Err(format!("{}: {}", part.display(), e));
+        }
+        hasher.update(&chunk);
+        downloaded += chunk.len() as u64;
+        // One event per MB is plenty for a progress bar
+        if downloaded - last_emit >= 1024 * 1024 || Some(downloaded) == total {
+            last_emit = downloaded;
+            let _ = app.emit(
+                "local_model_download_progress",
+                DownloadProgress { id: model.id, downloaded, total },
+            );
+        }
+    }
+    drop(file);
+    if total.is_some_and(|t| t != downloaded) {
+        let _ = std::fs::remove_file(&part);
+        return Err("Téléchargement du modèle incomplet".to_string());
+    }
+    if let Err(e) = check_digest(model, hasher) {
+        let _ = std::fs::remove_file(&part);
+        return Err(e);
+    }
+    std::fs::rename(&part, &dest).map_err(|e| format!("{}: {}", dest.display(), e))
+}
+
+/// Compare the hash of the downloaded bytes with the one pinned for `model`.
+fn check_digest(model: &LocalModel, hasher: Sha256) -> Result<(), String> {
+    let actual: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    if actual == model.sha256 {
+        Ok(())
+    } else {
+        Err(format!(
+            "Modèle « {} » corrompu (sha256 {} au lieu de {})",
+            model.id, actual, model.sha256
+        ))
+    }
+}
+
+#[cfg(feature = "local-whisper")]
+pub use engine::build_local;
+
+#[cfg(feature = "local-whisper")]
+mod engine {
+    use crate::audio::EncodedAudio;
+    use crate::preferences::Preferences;
+    use crate::transcription_provider::{
+        ProviderCapabilities, TimedWord, TranscribeOptions, Transcript, TranscriptionProvider,
+    };
+    use futures_util::future::BoxFuture;
+    use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
+    use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+    /// Loaded model, kept between dictations: loading takes longer than transcribing.
+    static CONTEXT: Mutex<Option<(PathBuf, Arc<WhisperContext>)>> = Mutex::new(None);
+
+    fn context_for(path: &Path) -> Result<Arc<WhisperContext>, String> {
+        let mut cached = CONTEXT.lock().map_err(|e| e.to_string())?;
+        if let Some((loaded, ctx)) = cached.as_ref() {
+            if loaded == path {
+                return Ok(ctx.clone());
+            }
+        }
+        let path_str = path.to_str().ok_or("Chemin du modèle invalide")?;
+        let ctx = WhisperContext::new_with_params(path_str, WhisperContextParameters::default())
+            .map_err(|e| format!("Chargement du modèle local impossible: {}", e))?;
+        let ctx = Arc::new(ctx);
+        *cached = Some((path.to_path_buf(), ctx.clone()));
+        Ok(ctx)
+    }
+
+    /// Decode the encoded segment back to 16 kHz mono, as whisper.cpp expects.
+    fn to_whisper_samples(audio: &EncodedAudio) -> Result<Vec<f32>, String> {
+        let ext = audio
+            .encoder
+            .file_name()
+            .rsplit('.')
+            .next()
+            .unwrap_or("wav");
+        let decoded = crate::audio_file::decode_bytes(audio.bytes.clone(), ext)?;
+        Ok(crate::audio::resample(
+            &decoded.samples,
+            decoded.sample_rate,
+            crate::audio::WHISPER_SAMPLE_RATE,
+        ))
+    }
+
+    fn run(model_path: &Path, samples: &[f32], opts: &TranscribeOptions) -> Result<Transcript, String> {
+        let ctx = context_for(model_path)?;
+        let mut state = ctx.create_state().map_err(|e| e.to_string())?;
+
+        let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+        let threads = std::thread::available_parallelism().map_or(4, |n| n.get().min(8));
+        params.set_n_threads(threads as i32);
+        let language = opts.language.as_deref().filter(|l| !l.is_empty()).unwrap_or("auto");
+        params.set_language(Some(language));
+        if !opts.prompt.is_empty() {
+            // Same dictionary conditioning as the cloud providers
+            params.set_initial_prompt(&opts.prompt.replace('\0', ""));
+        }
+        if opts.timestamps {
+            // One segment per word: whisper.cpp's way of producing word timings
+            params.set_token_timestamps(true);
+            params.set_max_len(1);
+            params.set_split_on_word(true);
+        } else {
+            params.set_no_timestamps(true);
+        }
+        params.set_print_special(false);
+        params.set_print_progress(false);
+        params.set_print_realtime(false);
+        params.set_print_timestamps(false);
+
+        state.full(params, samples).map_err(|e| format!("Transcription locale échouée: {}", e))?;
+        let n = state.full_n_segments().map_err(|e| e.to_string())?;
+        let mut text = String::new();
+        let mut words = opts.timestamps.then(Vec::new);
+        for i in 0..n {
+            let segment = state.full_get_segment_text_lossy(i).map_err(|e| e.to_string())?;
+            if let Some(words) = words.as_mut().filter(|_| !segment.trim().is_empty()) {
+                // t0/t1 are in centiseconds
+                let t0 = state.full_get_segment_t0(i).map_err(|e| e.to_string())?;
+                let t1 = state.full_get_segment_t1(i).map_err(|e| e.to_string())?;
+                words.push(TimedWord {
+                    word: segment.trim().to_string(),
+                    start: t0 as f64 / 100.0,
+                    end: t1 as f64 / 100.0,
+                });
+            }
+            text.push_str(&segment);
+        }
+        // Only meaningful when the language was left to detection
+        let language = state
+            .full_lang_id_from_state()
+            .ok()
+            .and_then(whisper_rs::get_lang_str)
+            .map(str::to_string);
+        // No per-segment no_speech statistics: the phrase list applies
+        Ok(Transcript {
+            text: text.trim().to_string(),
+            segments: None,
+            language,
+            words,
+        })
+    }
+
+    /// whisper.cpp on this machine; no network, no key.
+    struct LocalWhisper {
+        model_id: String,
+        model_path: PathBuf,
+    }
+
+    impl TranscriptionProvider for LocalWhisper {
+        fn id(&self) -> &'static str {
+            "local"
+        }
+
+        fn name(&self) -> &'static str {
+            "Local (whisper.cpp)"
+        }
+
+        fn model(&self) -> &str {
+            &self.model_id
+        }
+
+        fn capabilities(&self) -> ProviderCapabilities {
+            ProviderCapabilities {
+                prompt: true,
+                language: true,
+                verbose_json: false,
+                timestamps: true,
+            }
+        }
+
+        fn transcribe<'a>(
+            &'a self,
+            audio: &'a EncodedAudio,
+            opts: &'a TranscribeOptions,
+        ) -> BoxFuture<'a, Result<Transcript, String>> {
+            Box::pin(async move {
+                let samples = to_whisper_samples(audio)?;
+                let model_path = self.model_path.clone();
+                let opts = opts.clone();
+                // CPU-bound for seconds: keep it off the async workers
+                tokio::task::spawn_blocking(move || run(&model_path, &samples, &opts))
+                    .await
+                    .map_err(|e| e.to_string())?
+            })
+        }
+    }
+
+    pub fn build_local(prefs: &Preferences) -> Result<Box<dyn TranscriptionProvider>, String> {
+        Ok(Box::new(LocalWhisper {
+            model_id: prefs.transcription.local_model.clone(),
+            model_path: super::model_path(&prefs.transcription.local_model)?,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ── catalog ─────────────────────────────────────────────────────
+
+    #[test]
+    fn test_model_ids_unique() {
+        let mut ids: Vec<_> = MODELS.iter().map(|m| m.id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), MODELS.len());
+    }
+
+    #[test]
+    fn test_default_local_model_in_catalog() {
+        let prefs = crate::preferences::TranscriptionPrefs::default();
+        assert!(find_model(&prefs.local_model).is_ok());
+        assert!(find_model("huge").is_err());
+    }
+
+    #[test]
+    fn test_model_digests_are_sha256_hex() {
+        for m in MODELS {
+            assert_eq!(m.sha256.len(), 64, "{}", m.id);
+            assert!(m.sha256.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b)), "{}", m.id);
+        }
+    }
+
+    // ── check_digest ────────────────────────────────────────────────
+
+    fn model_with_digest(sha256: &'static str) -> LocalModel {
+        LocalModel { id: "test", file_name: "ggml-test.bin", size_mb: 1, sha256 }
+    }
+
+    #[test]
+    fn test_check_digest_accepts_matching_bytes() {
+        // sha256("abc")
+        let model = model_with_digest("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let mut hasher = Sha256::new();
+        hasher.update(b"ab");
+        hasher.update(b"c");
+        assert!(check_digest(&model, hasher).is_ok());
+    }
+
+    #[test]
+    fn test_check_digest_rejects_altered_bytes() {
+        let model = model_with_digest("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        let mut hasher = Sha256::new();
+        hasher.update(b"abd");
+        let err = check_digest(&model, hasher).unwrap_err();
+        assert!(err.contains("corrompu"));
+    }
+
+    // ── statuses_in ─────────────────────────────────────────────────
+
+    #[test]
+    fn test_statuses_reflect_downloaded_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("ggml-base.bin"), b"ggml").unwrap();
+        // An unfinished download doesn't count
+        std::fs::write(dir.path().join("ggml-tiny.bin.part"), b"gg").unwrap();
+        let statuses = statuses_in(dir.path());
+        assert_eq!(statuses.len(), MODELS.len());
+        let downloaded: Vec<_> = statuses.iter().filter(|s| s.downloaded).map(|s| s.id.as_str()).collect();
+        assert_eq!(downloaded, vec!["base"]);
+    }
+}
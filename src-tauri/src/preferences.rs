@@ -1,5 +1,6 @@
 /// Préférences utilisateur persistées (settings style Vercel)
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 use tauri::Manager;
 
@@ -73,6 +74,36 @@ pub struct RecordingPrefs {
     pub max_duration_minutes: u32,
     #[serde(default)]
     pub input_device_id: Option<String>,
+    /// Auto-stop after this many seconds of silence following speech (hands-free). None = off.
+    #[serde(default)]
+    pub silence_timeout_secs: Option<u32>,
+    /// Keep the mic open between recordings and prepend the last ~500 ms on start,
+    /// so the first syllable isn't clipped. Memory only, never persisted. Opt-in.
+    #[serde(default)]
+    pub always_warm: bool,
+    /// Include a coarse band spectrum in live `audio_level` events (for a visualizer).
+    #[serde(default)]
+    pub level_spectrum: bool,
+    /// Keep each recording (FLAC, app data dir) so it can be re-transcribed. Opt-in.
+    #[serde(default)]
+    pub archive_enabled: bool,
+    /// Archive retention: recordings older than this are deleted. None = no age limit.
+    #[serde(default = "default_archive_max_age_days")]
+    pub archive_max_age_days: Option<u32>,
+    /// Archive retention: keep at most this many recordings. None = no count limit.
+    #[serde(default)]
+    pub archive_max_count: Option<u32>,
+    /// Archive retention: total size cap in MB, oldest deleted first. None = no size limit.
+    #[serde(default = "default_archive_max_size_mb")]
+    pub archive_max_size_mb: Option<u32>,
+}
+
+fn default_archive_max_age_days() -> Option<u32> {
+    Some(30)
+}
+
+fn default_archive_max_size_mb() -> Option<u32> {
+    Some(500)
 }
 
 impl Default for RecordingPrefs {
@@ -80,6 +111,13 @@ impl Default for RecordingPrefs {
         Self {
             max_duration_minutes: 2,
             input_device_id: None,
+            silence_timeout_secs: None,
+            always_warm: false,
+            level_spectrum: false,
+            archive_enabled: false,
+            archive_max_age_days: default_archive_max_age_days(),
+            archive_max_count: None,
+            archive_max_size_mb: default_archive_max_size_mb(),
         }
     }
 }
@@ -91,15 +129,128 @@ pub struct TranscriptionPrefs {
     pub timeout_secs: u64,
     #[serde(default)]
     pub language: Option<String>,
-    /// Transcription provider: "openai" or "groq"
+    /// Transcription provider id, see `transcription_provider::provider_ids` ("openai", "groq", "openai-compatible", "local" with the `local-whisper` feature)
     #[serde(default = "default_transcription_provider")]
     pub provider: String,
+    /// Upload encoding: "wav" (default), "flac" (lossless) or "opus"
+    #[serde(default = "default_upload_format")]
+    pub upload_format: String,
+    /// whisper.cpp model for the "local" provider, see `local_whisper::MODELS`
+    #[serde(default = "default_local_model")]
+    pub local_model: String,
+    /// Hallucination guard thresholds by provider id; providers not listed use the defaults
+    #[serde(default)]
+    pub hallucination_thresholds: HashMap<String, HallucinationThresholds>,
+    /// Mode id by detected language (e.g. {"en": "medium"}); other languages keep the active mode
+    #[serde(default)]
+    pub language_modes: HashMap<String, String>,
+    /// Transcribe while the key is held and emit `partial_transcript` events (more API calls)
+    #[serde(default)]
+    pub live_partials: bool,
+    /// Read the text before the cursor in the focused field (Accessibility) as Whisper prompt
+    #[serde(default)]
+    pub cursor_context: bool,
+    /// Upload the segments of a long take one after the other, each prompted with the
+    /// transcript of the one before (better continuity, slower); off = concurrent uploads
+    #[serde(default)]
+    pub chain_segments: bool,
+    /// Providers tried in order once `provider` has failed; None = Groq → OpenAI (whisper-1)
+    #[serde(default)]
+    pub fallback: Option<Vec<FallbackHop>>,
+}
+
+/// `TranscriptionPrefs::language` value that lets the provider detect the language, like
+/// `null` (what the settings and tray "Auto-detect" entries store).
+pub const AUTO_LANGUAGE: &str = "auto";
+
+impl TranscriptionPrefs {
+    /// Language sent to the provider; None lets it detect the language.
+    pub fn language_hint(&self) -> Option<String> {
+        self.language
+            .as_deref()
+            .filter(|lang| !lang.is_empty() && *lang != AUTO_LANGUAGE)
+            .map(str::to_string)
+    }
+
+    /// Configured fallback chain, or the historical one: Groq → OpenAI.
+    pub fn fallback_chain(&self) -> Vec<FallbackHop> {
+        self.fallback
+            .clone()
+            .unwrap_or_else(|| legacy_fallback(&self.provider, "whisper-1"))
+    }
+
+    pub fn thresholds_for(&self, provider: &str) -> HallucinationThresholds {
+        self.hallucination_thresholds
+            .get(provider)
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Limits on Whisper's `verbose_json` segment statistics. A segment is dropped when it is
+/// probably silence (`no_speech_prob` above and `avg_logprob` below their limits, Whisper's
+/// own rule) or a repetition loop (`compression_ratio` above its limit).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HallucinationThresholds {
+    pub no_speech_prob: f32,
+    pub avg_logprob: f32,
+    pub compression_ratio: f32,
+}
+
+impl Default for HallucinationThresholds {
+    fn default() -> Self {
+        Self {
+            no_speech_prob: 0.6,
+            avg_logprob: -1.0,
+            compression_ratio: 2.4,
+        }
+    }
+}
+
+/// One hop of a provider fallback chain (transcription or LLM), tried once after the
+/// primary provider and every earlier hop have failed. Hops without their key are skipped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FallbackHop {
+    /// Provider id, as in the `provider` field of the same section
+    pub provider: String,
+    /// None = the provider's default model
+    #[serde(default)]
+    pub model: Option<String>,
+    /// None = the section's `timeout_secs`
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// None = the vendor's endpoint (the Advanced base URLs only apply to the primary)
+    #[serde(default)]
+    pub base_url: Option<String>,
+}
+
+/// Chain used before it was configurable: only Groq fell back, to OpenAI.
+fn legacy_fallback(provider: &str, openai_model: &str) -> Vec<FallbackHop> {
+    match provider {
+        "groq" => vec![FallbackHop {
+            provider: "openai".into(),
+            model: Some(openai_model.into()),
+            timeout_secs: None,
+            base_url: None,
+        }],
+        _ => Vec::new(),
+    }
 }
 
 fn default_transcription_provider() -> String {
     "openai".into()
 }
 
+fn default_upload_format() -> String {
+    "wav".into()
+}
+
+fn default_local_model() -> String {
+    "base".into()
+}
+
 impl Default for TranscriptionPrefs {
     fn default() -> Self {
         Self {
@@ -107,6 +258,14 @@ impl Default for TranscriptionPrefs {
             timeout_secs: 20,
             language: Some("fr".into()),
             provider: "openai".into(),
+            upload_format: default_upload_format(),
+            local_model: default_local_model(),
+            hallucination_thresholds: HashMap::new(),
+            language_modes: HashMap::new(),
+            live_partials: false,
+            cursor_context: false,
+            chain_segments: false,
+            fallback: None,
         }
     }
 }
@@ -118,9 +277,22 @@ pub struct LlmPrefs {
     pub temperature: f32,
     pub max_tokens: u32,
     pub timeout_secs: u64,
-    /// LLM provider: "openai" or "groq"
+    /// LLM provider id, see `llm_provider::provider_ids` ("openai", "groq", "anthropic", "ollama")
     #[serde(default = "default_llm_provider")]
     pub provider: String,
+    /// Providers tried in order once `provider` has failed (e.g. Ollama → Groq → OpenAI);
+    /// None = Groq → OpenAI (gpt-4o-mini)
+    #[serde(default)]
+    pub fallback: Option<Vec<FallbackHop>>,
+}
+
+impl LlmPrefs {
+    /// Configured fallback chain, or the historical one: Groq → OpenAI.
+    pub fn fallback_chain(&self) -> Vec<FallbackHop> {
+        self.fallback
+            .clone()
+            .unwrap_or_else(|| legacy_fallback(&self.provider, "gpt-4o-mini"))
+    }
 }
 
 fn default_llm_provider() -> String {
@@ -135,6 +307,7 @@ impl Default for LlmPrefs {
             max_tokens: 1024,
             timeout_secs: 45,
             provider: "openai".into(),
+            fallback: None,
         }
     }
 }
@@ -151,6 +324,18 @@ pub struct BehaviorPrefs {
     /// Coller Original + Result (format fixe) quand un mode avec traitement est utilisé.
     #[serde(default)]
     pub paste_input_and_output: bool,
+    /// Insertion de la sortie LLM d'une dictée : "paste" (tout le texte à la fin) ou
+    /// "stream" (tapée phrase par phrase pendant la génération). Sans effet avec
+    /// `paste_input_and_output`.
+    #[serde(default = "default_insertion_mode")]
+    pub insertion_mode: String,
+    /// Envoyer chaque token à la fenêtre flottante (`llm_delta`) pendant la génération.
+    #[serde(default)]
+    pub stream_preview: bool,
+}
+
+fn default_insertion_mode() -> String {
+    "paste".to_string()
 }
 
 fn behavior_default_true() -> bool {
@@ -165,6 +350,8 @@ impl Default for BehaviorPrefs {
             system_notification: false,
             auto_paste_after_transform: true,
             paste_input_and_output: false,
+            insertion_mode: default_insertion_mode(),
+            stream_preview: false,
         }
     }
 }
@@ -201,6 +388,23 @@ pub struct AdvancedPrefs {
     pub transcription_base_url: Option<String>,
     #[serde(default)]
     pub llm_base_url: Option<String>,
+    /// Price overrides for the usage cost estimate, see `pricing::price_for`
+    #[serde(default)]
+    pub pricing: Vec<ModelPrice>,
+}
+
+/// Price of one provider/model in USD. `model` is matched as a prefix, "*" = every model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ModelPrice {
+    pub provider: String,
+    pub model: String,
+    /// Per million LLM input tokens
+    pub input_per_mtok: f64,
+    /// Per million LLM output tokens
+    pub output_per_mtok: f64,
+    /// Per minute of transcribed audio
+    pub per_minute: f64,
 }
 
 fn preferences_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
@@ -357,6 +561,8 @@ mod tests {
         assert!(!prefs.system_notification);
         assert!(prefs.auto_paste_after_transform);
         assert!(!prefs.paste_input_and_output);
+        assert_eq!(prefs.insertion_mode, "paste");
+        assert!(!prefs.stream_preview);
     }
 
     #[test]
@@ -373,6 +579,38 @@ mod tests {
         let prefs = AdvancedPrefs::default();
         assert!(prefs.transcription_base_url.is_none());
         assert!(prefs.llm_base_url.is_none());
+        assert!(prefs.pricing.is_empty());
+    }
+
+    #[test]
+    fn test_default_recording_prefs_no_silence_timeout() {
+        let prefs = RecordingPrefs::default();
+        assert!(prefs.silence_timeout_secs.is_none());
+        // Older preferences.json files without the field still load
+        let json = r#"{"maxDurationMinutes": 5}"#;
+        let prefs: RecordingPrefs = serde_json::from_str(json).unwrap();
+        assert!(prefs.silence_timeout_secs.is_none());
+    }
+
+    #[test]
+    fn test_default_recording_prefs_not_always_warm() {
+        // Opt-in: the mic must not stay open unless the user asked for it
+        assert!(!RecordingPrefs::default().always_warm);
+        let json = r#"{"maxDurationMinutes": 2, "inputDeviceId": null}"#;
+        let prefs: RecordingPrefs = serde_json::from_str(json).unwrap();
+        assert!(!prefs.always_warm);
+    }
+
+    #[test]
+    fn test_default_recording_prefs_archive_off_with_limits() {
+        // Opt-in, and bounded on disk once enabled
+        let json = r#"{"maxDurationMinutes": 2}"#;
+        let prefs: RecordingPrefs = serde_json::from_str(json).unwrap();
+        assert!(!prefs.archive_enabled);
+        assert_eq!(prefs.archive_max_age_days, Some(30));
+        assert_eq!(prefs.archive_max_count, None);
+        assert_eq!(prefs.archive_max_size_mb, Some(500));
+        assert_eq!(RecordingPrefs::default().archive_max_size_mb, Some(500));
     }
 
     #[test]
@@ -381,6 +619,74 @@ mod tests {
         assert_eq!(prefs.language, Some("fr".into()));
     }
 
+    #[test]
+    fn test_default_upload_format_wav() {
+        // Compressed formats are opt-in: existing preferences keep uploading WAV
+        assert_eq!(TranscriptionPrefs::default().upload_format, "wav");
+        let json = r#"{"model": "whisper-1", "timeoutSecs": 20}"#;
+        let prefs: TranscriptionPrefs = serde_json::from_str(json).unwrap();
+        assert_eq!(prefs.upload_format, "wav");
+    }
+
+    #[test]
+    fn test_thresholds_for_provider_falls_back_to_defaults() {
+        let json = r#"{"model": "whisper-1", "timeoutSecs": 20,
+            "hallucinationThresholds": {"groq": {"noSpeechProb": 0.8}}}"#;
+        let prefs: TranscriptionPrefs = serde_json::from_str(json).unwrap();
+        let groq = prefs.thresholds_for("groq");
+        assert_eq!(groq.no_speech_prob, 0.8);
+        assert_eq!(groq.compression_ratio, 2.4);
+        assert_eq!(prefs.thresholds_for("openai"), HallucinationThresholds::default());
+    }
+
+    #[test]
+    fn test_language_hint() {
+        let mut prefs = TranscriptionPrefs::default();
+        assert_eq!(prefs.language_hint(), Some("fr".to_string()));
+        prefs.language = Some("auto".into());
+        assert_eq!(prefs.language_hint(), None);
+        prefs.language = Some(String::new());
+        assert_eq!(prefs.language_hint(), None);
+        prefs.language = Some("en".into());
+        assert_eq!(prefs.language_hint(), Some("en".to_string()));
+        prefs.language = None;
+        assert_eq!(prefs.language_hint(), None);
+    }
+
+    #[test]
+    fn test_default_local_model() {
+        let json = r#"{"model": "whisper-1", "timeoutSecs": 20}"#;
+        let prefs: TranscriptionPrefs = serde_json::from_str(json).unwrap();
+        assert_eq!(prefs.local_model, "base");
+    }
+
+    #[test]
+    fn test_fallback_chain_defaults_to_groq_then_openai() {
+        let mut prefs = Preferences::default();
+        assert!(prefs.llm.fallback_chain().is_empty());
+        prefs.llm.provider = "groq".into();
+        prefs.transcription.provider = "groq".into();
+        let llm = prefs.llm.fallback_chain();
+        assert_eq!(llm.len(), 1);
+        assert_eq!(llm[0].provider, "openai");
+        assert_eq!(llm[0].model.as_deref(), Some("gpt-4o-mini"));
+        assert_eq!(prefs.transcription.fallback_chain()[0].model.as_deref(), Some("whisper-1"));
+    }
+
+    #[test]
+    fn test_fallback_chain_configured() {
+        let json = r#"{"model": "llama3.2", "temperature": 0.3, "maxTokens": 1024, "timeoutSecs": 45,
+            "provider": "ollama", "fallback": [{"provider": "groq", "timeoutSecs": 10}, {"provider": "openai"}]}"#;
+        let prefs: LlmPrefs = serde_json::from_str(json).unwrap();
+        let chain = prefs.fallback_chain();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[0].timeout_secs, Some(10));
+        assert!(chain[1].model.is_none());
+        // An empty list turns the fallback off, even for Groq
+        let prefs = LlmPrefs { provider: "groq".into(), fallback: Some(Vec::new()), ..LlmPrefs::default() };
+        assert!(prefs.fallback_chain().is_empty());
+    }
+
     // ── Serialization / deserialization roundtrip ────────────────────
 
     #[test]
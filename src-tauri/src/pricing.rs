@@ -0,0 +1,109 @@
+/// Provider prices (USD) for the usage cost estimate, overridable per provider/model in `advanced.pricing`.
+use crate::preferences::ModelPrice;
+
+/// (provider, model, input $/M tokens, output $/M tokens, $/audio minute). The model is a
+/// prefix (dated snapshots match their family); "*" covers every model of the provider.
+const LIST_PRICES: &[(&str, &str, f64, f64, f64)] = &[
+    ("openai", "whisper-1", 0.0, 0.0, 0.006),
+    ("openai", "gpt-4o-transcribe", 0.0, 0.0, 0.006),
+    ("openai", "gpt-4o-mini-transcribe", 0.0, 0.0, 0.003),
+    ("openai", "gpt-4o-mini", 0.15, 0.60, 0.0),
+    ("openai", "gpt-4o", 2.50, 10.00, 0.0),
+    ("openai", "gpt-4-turbo", 10.00, 30.00, 0.0),
+    ("groq", "whisper-large-v3-turbo", 0.0, 0.0, 0.04 / 60.0),
+    ("groq", "whisper-large-v3", 0.0, 0.0, 0.111 / 60.0),
+    ("groq", "llama-3.1-8b-instant", 0.05, 0.08, 0.0),
+    ("groq", "llama-3.3-70b-versatile", 0.59, 0.79, 0.0),
+    ("groq", "mixtral-8x7b-32768", 0.24, 0.24, 0.0),
+    ("anthropic", "claude-haiku-4-5", 1.00, 5.00, 0.0),
+    ("anthropic", "claude-sonnet-4-5", 3.00, 15.00, 0.0),
+    // Runs on this machine
+    ("ollama", "*", 0.0, 0.0, 0.0),
+    ("local", "*", 0.0, 0.0, 0.0),
+];
+
+/// How well `pattern` matches `model`: exact beats the longest prefix, which beats "*".
+fn match_rank(pattern: &str, model: &str) -> Option<usize> {
+    if pattern == model {
+        Some(usize::MAX)
+    } else if pattern == "*" {
+        Some(0)
+    } else if model.starts_with(pattern) {
+        Some(pattern.len())
+    } else {
+        None
+    }
+}
+
+/// Price of `provider`/`model`: the best matching override, else the best matching list
+/// price. None when unknown (self-hosted servers, unlisted models): its cost is left out.
+pub fn price_for(provider: &str, model: &str, overrides: &[ModelPrice]) -> Option<ModelPrice> {
+    let overridden = overrides
+        .iter()
+        .filter(|p| p.provider == provider)
+        .filter_map(|p| match_rank(&p.model, model).map(|rank| (rank, p)))
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, p)| p.clone());
+    overridden.or_else(|| {
+        LIST_PRICES
+            .iter()
+            .filter(|(p, ..)| *p == provider)
+            .filter_map(|entry| match_rank(entry.1, model).map(|rank| (rank, entry)))
+            .max_by_key(|(rank, _)| *rank)
+            .map(|(_, (provider, model, input, output, minute))| ModelPrice {
+                provider: provider.to_string(),
+                model: model.to_string(),
+                input_per_mtok: *input,
+                output_per_mtok: *output,
+                per_minute: *minute,
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(provider: &str, model: &str, per_minute: f64) -> ModelPrice {
+        ModelPrice {
+            provider: provider.to_string(),
+            model: model.to_string(),
+            per_minute,
+            ..Default::default()
+        }
+    }
+
+    // ── price_for ───────────────────────────────────────────────────
+
+    #[test]
+    fn test_list_prices() {
+        assert_eq!(price_for("openai", "whisper-1", &[]).unwrap().per_minute, 0.006);
+        let mini = price_for("openai", "gpt-4o-mini", &[]).unwrap();
+        assert_eq!((mini.input_per_mtok, mini.output_per_mtok), (0.15, 0.60));
+        assert!((price_for("groq", "whisper-large-v3-turbo", &[]).unwrap().per_minute - 0.000667).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_longest_prefix_wins() {
+        // Not gpt-4o's price
+        let snapshot = price_for("openai", "gpt-4o-mini-2024-07-18", &[]).unwrap();
+        assert_eq!(snapshot.model, "gpt-4o-mini");
+        assert_eq!(price_for("groq", "whisper-large-v3", &[]).unwrap().model, "whisper-large-v3");
+    }
+
+    #[test]
+    fn test_wildcard_and_unknown() {
+        assert_eq!(price_for("ollama", "qwen2.5:7b", &[]).unwrap().input_per_mtok, 0.0);
+        assert!(price_for("openai-compatible", "whisper-1", &[]).is_none());
+        assert!(price_for("openai", "o9-preview", &[]).is_none());
+    }
+
+    #[test]
+    fn test_override_beats_list_price() {
+        let overrides = [price("openai", "whisper-1", 0.004), price("openai-compatible", "*", 0.001)];
+        assert_eq!(price_for("openai", "whisper-1", &overrides).unwrap().per_minute, 0.004);
+        assert_eq!(price_for("openai-compatible", "large-v3", &overrides).unwrap().per_minute, 0.001);
+        // Other models keep their list price
+        assert_eq!(price_for("openai", "gpt-4o-mini-transcribe", &overrides).unwrap().per_minute, 0.003);
+    }
+}
@@ -490,6 +490,25 @@ pub fn get_key_for_provider(_provider: &str) -> Result<String, String> {
     get_api_key()
 }
 
+/// Key stored for exactly this provider, without the cross-provider fallbacks of
+/// `get_key_for_provider`. For endpoints (self-hosted…) that must never see another vendor's key.
+#[cfg(target_os = "macos")]
+pub fn get_own_key_for_provider(provider: &str) -> Result<String, String> {
+    let config = get_keys_config()?;
+    config
+        .active_key_ids
+        .get(provider)
+        .and_then(|active_id| config.keys.iter().find(|k| &k.id == active_id))
+        .or_else(|| config.keys.iter().find(|k| k.provider == provider))
+        .map(|entry| entry.key.clone())
+        .ok_or_else(|| format!("Aucune clé pour le provider {}", provider))
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn get_own_key_for_provider(provider: &str) -> Result<String, String> {
+    Err(format!("Aucune clé pour le provider {}", provider))
+}
+
 // ============================================================================
 // LEGACY FUNCTIONS (Compatibilité)
 // ============================================================================
@@ -513,9 +532,15 @@ pub async fn test_api_key(key: &str, provider: &str) -> Result<(), String> {
         .map_err(|e| format!("Erreur création client: {}", e))?;
 
     let url = format!("{}/v1/models", base_url);
-    let resp = client
-        .get(&url)
-        .header("Authorization", format!("Bearer {}", key))
+    let request = match provider {
+        // Anthropic ne prend pas de Bearer : clé dans x-api-key + version d'API
+        "anthropic" => client
+            .get(&url)
+            .header("x-api-key", key)
+            .header("anthropic-version", "2023-06-01"),
+        _ => client.get(&url).header("Authorization", format!("Bearer {}", key)),
+    };
+    let resp = request
         .send()
         .await
         .map_err(|e| {
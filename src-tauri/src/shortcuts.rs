@@ -28,6 +28,10 @@ pub enum ShortcutAction {
     PushToTalk,
     /// Press toggles recording on/off.
     ToggleRecording,
+    /// Pause the current take without ending it.
+    PauseRecording,
+    /// Continue a paused take.
+    ResumeRecording,
     OpenDashboard,
     ToggleFloatingBar,
     PasteLastOutput,
@@ -435,6 +439,8 @@ mod tests {
             ShortcutAction::StopRecording,
             ShortcutAction::PushToTalk,
             ShortcutAction::ToggleRecording,
+            ShortcutAction::PauseRecording,
+            ShortcutAction::ResumeRecording,
             ShortcutAction::OpenDashboard,
             ShortcutAction::ToggleFloatingBar,
             ShortcutAction::PasteLastOutput,
@@ -0,0 +1,377 @@
+/// "Type as it streams" insertion (`behavior.insertion_mode = "stream"`): LLM output typed into the focused field as it is generated.
+use std::sync::mpsc;
+use std::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+/// A clause ("…, ") only ends a chunk past this many bytes, so short ones are typed together.
+const MIN_CLAUSE_BYTES: usize = 24;
+
+/// Without any punctuation, a chunk is cut at a space past this many bytes.
+const MAX_CHUNK_BYTES: usize = 160;
+
+/// Longest line that can still turn out to be a "--- REFLECTION ---" separator.
+const SEPARATOR_MAX_BYTES: usize = 40;
+
+/// Pause after a clipboard paste, so the target app reads the clipboard before the next
+/// chunk replaces it.
+const PASTE_SETTLE_MS: u64 = 80;
+
+/// Cuts streamed text into chunks to type: up to a sentence end, a clause end (long enough)
+/// or a line break. Stops at the reflection separator: the thoughts after it are shown in
+/// the app, never typed.
+#[derive(Debug, Default)]
+struct ChunkBuffer {
+    pending: String,
+    /// Something was typed already: `pending` no longer starts the output.
+    started: bool,
+    /// The separator was reached: the rest is dropped.
+    done: bool,
+}
+
+impl ChunkBuffer {
+    /// Add a delta; returns the chunks it completed.
+    fn push(&mut self, delta: &str) -> Vec<String> {
+        let mut chunks = Vec::new();
+        if self.done {
+            return chunks;
+        }
+        self.pending.push_str(delta);
+        loop {
+            if self.at_line_start() {
+                let line = self.pending.trim_start();
+                if line.starts_with('-') {
+                    let offset = self.pending.len() - line.len();
+                    match line.find('\n') {
+                        Some(end) if self.is_separator(&self.pending[..offset + end]) => {
+                            self.stop();
+                            return chunks;
+                        }
+                        Some(_) => {}
+                        // Wait for the end of the line, unless too long for a separator
+                        None if line.len() <= SEPARATOR_MAX_BYTES => return chunks,
+                        None => {}
+                    }
+                }
+            }
+            match chunk_end(&self.pending) {
+                Some(end) => {
+                    let rest = self.pending.split_off(end);
+                    let chunk = std::mem::replace(&mut self.pending, rest);
+                    chunks.push(self.take(chunk));
+                }
+                None => return chunks,
+            }
+        }
+    }
+
+    /// End of the stream: what is left, unless only whitespace or the separator.
+    fn finish(&mut self) -> Option<String> {
+        if self.done {
+            return None;
+        }
+        let line = self.pending.trim();
+        if line.is_empty() || (line.starts_with('-') && self.is_separator(&self.pending)) {
+            self.stop();
+            return None;
+        }
+        let rest = std::mem::take(&mut self.pending);
+        self.done = true;
+        Some(self.take(rest.trim_end().to_string()))
+    }
+
+    fn at_line_start(&self) -> bool {
+        !self.started || self.pending.starts_with('\n')
+    }
+
+    /// `text` (the pending line, with the line break before it) is the separator, as
+    /// `audio::find_reflection_separator` will find it in the whole output.
+    fn is_separator(&self, text: &str) -> bool {
+        self.at_line_start() && crate::audio::find_reflection_separator(text).is_some()
+    }
+
+    /// The output is trimmed: the first chunk loses its leading whitespace.
+    fn take(&mut self, chunk: String) -> String {
+        if self.started {
+            chunk
+        } else {
+            self.started = true;
+            chunk.trim_start().to_string()
+        }
+    }
+
+    fn stop(&mut self) {
+        self.pending.clear();
+        self.done = true;
+    }
+}
+
+/// Byte offset where the first complete chunk of `text` ends: after a sentence end or a
+/// long enough clause followed by whitespace, or before a line break. None until one
+/// arrives (the whitespace after the punctuation included).
+fn chunk_end(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    let mut last_space = None;
+    while let Some((i, c)) = chars.next() {
+        let next_is_space = chars.peek().is_some_and(|(_, n)| n.is_whitespace());
+        let end = i + c.len_utf8();
+        match c {
+            '\n' if !text[..i].trim().is_empty() => return Some(i),
+            '.' | '!' | '?' | '…' if next_is_space => return Some(end),
+            ',' | ';' | ':' if next_is_space && end >= MIN_CLAUSE_BYTES => return Some(end),
+            ' ' if !text[..i].trim().is_empty() => last_space = Some(i),
+            _ => {}
+        }
+        if end > MAX_CHUNK_BYTES {
+            if let Some(space) = last_space {
+                return Some(space);
+            }
+        }
+    }
+    None
+}
+
+/// Types chunks into the focused field on a dedicated thread (insertion blocks) as the
+/// deltas are pushed. Once `cancel` fires nothing more is typed; the chunk being typed is
+/// completed, so the field never ends mid-word. The route is chosen on the first chunk
+/// (see `Route`).
+pub struct StreamTyper {
+    state: Mutex<TyperState>,
+    worker: std::thread::JoinHandle<bool>,
+    cancel: CancellationToken,
+}
+
+struct TyperState {
+    chunks: ChunkBuffer,
+    /// Every delta pushed, the thoughts after the separator included.
+    received: String,
+    /// To the worker; dropped by `finish` to let it end.
+    tx: mpsc::Sender<String>,
+}
+
+impl StreamTyper {
+    pub fn start(cancel: CancellationToken, app: tauri::AppHandle) -> Self {
+        let (tx, rx) = mpsc::channel::<String>();
+        let worker_cancel = cancel.clone();
+        let worker = std::thread::spawn(move || {
+            let mut route = Route::default();
+            let mut inserted = true;
+            for chunk in rx {
+                if worker_cancel.is_cancelled() {
+                    break;
+                }
+                inserted &= route.insert(&chunk, &app);
+            }
+            let finished = route.finish(worker_cancel.is_cancelled(), &app);
+            inserted && finished
+        });
+        Self {
+            state: Mutex::new(TyperState {
+                chunks: ChunkBuffer::default(),
+                received: String::new(),
+                tx,
+            }),
+            worker,
+            cancel,
+        }
+    }
+
+    /// Queue the chunks `delta` completes; meant as the `on_delta` of
+    /// `llm::transform_text_streaming`.
+    pub fn push(&self, delta: &str) {
+        let mut state = self.lock();
+        state.received.push_str(delta);
+        for chunk in state.chunks.push(delta) {
+            let _ = state.tx.send(chunk);
+        }
+    }
+
+    /// True once a delta was pushed: from then on the output is (being) typed.
+    pub fn started(&self) -> bool {
+        !self.lock().received.is_empty()
+    }
+
+    /// Text received so far, trimmed like an LLM output.
+    pub fn received(&self) -> String {
+        self.lock().received.trim().to_string()
+    }
+
+    /// Type what is left and wait for the worker. Ok(false) when a chunk could not be
+    /// inserted (no text field focused); Err("Annulé") when typing was cancelled.
+    pub async fn finish(self) -> Result<bool, String> {
+        let Self { state, worker, cancel } = self;
+        let mut state = state.into_inner().unwrap_or_else(std::sync::PoisonError::into_inner);
+        if let Some(rest) = state.chunks.finish() {
+            let _ = state.tx.send(rest);
+        }
+        drop(state);
+        let inserted = tauri::async_runtime::spawn_blocking(move || worker.join().unwrap_or(false))
+            .await
+            .map_err(|e| e.to_string())?;
+        if cancel.is_cancelled() {
+            return Err("Annulé".to_string());
+        }
+        Ok(inserted)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, TyperState> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+}
+
+/// How the chunks reach the focused app, decided on the first chunk so the focus is
+/// checked once per stream.
+#[derive(Default)]
+enum Route {
+    #[default]
+    Undecided,
+    /// Inserted at the cursor through Accessibility.
+    Ax,
+    /// Clipboard + Cmd+V per chunk; the user's clipboard text, kept aside, is put back at
+    /// the end.
+    Paste { saved: Option<String> },
+    /// No text field focused: the chunks are kept and pasted once at the end.
+    Deferred(String),
+}
+
+impl Route {
+    /// Insert one chunk; returns false when it could not be inserted. Deferred chunks
+    /// only count at `finish`.
+    fn insert(&mut self, chunk: &str, app: &tauri::AppHandle) -> bool {
+        match self {
+            Route::Deferred(text) => {
+                text.push_str(chunk);
+                return true;
+            }
+            Route::Paste { .. } => return paste_chunk(chunk, app),
+            Route::Undecided | Route::Ax => {}
+        }
+        if let Ok(true) = crate::clipboard::insert_text_via_ax(chunk) {
+            *self = Route::Ax;
+            return true;
+        }
+        if matches!(self, Route::Undecided) && !crate::clipboard::has_focused_text_field() {
+            crate::clipboard::log_debug("[stream_insert] no focused text field, pasting once at the end");
+            *self = Route::Deferred(chunk.to_string());
+            return true;
+        }
+        *self = Route::Paste {
+            saved: crate::clipboard::get_clipboard_text(app).ok(),
+        };
+        paste_chunk(chunk, app)
+    }
+
+    /// End of the stream: paste what was deferred (unless cancelled) and put the user's
+    /// clipboard back. Returns false when the text did not reach a text field.
+    fn finish(self, cancelled: bool, app: &tauri::AppHandle) -> bool {
+        match self {
+            Route::Deferred(text) => {
+                if !cancelled {
+                    paste_chunk(&text, app);
+                }
+                false
+            }
+            Route::Paste { saved } => {
+                if let Some(saved) = saved {
+                    if let Err(e) = crate::clipboard::copy_to_clipboard(&saved, app) {
+                        crate::clipboard::log_debug(&format!("[stream_insert] clipboard restore FAILED: {}", e));
+                    }
+                }
+                true
+            }
+            Route::Undecided | Route::Ax => true,
+        }
+    }
+}
+
+/// Paste `text` from the clipboard (Cmd+V) and give the target app time to read it.
+fn paste_chunk(text: &str, app: &tauri::AppHandle) -> bool {
+    if let Err(e) = crate::clipboard::copy_to_clipboard(text, app) {
+        crate::clipboard::log_debug(&format!("[stream_insert] clipboard FAILED: {}", e));
+        return false;
+    }
+    match crate::clipboard::send_paste_keystroke() {
+        Ok(()) => {
+            std::thread::sleep(std::time::Duration::from_millis(PASTE_SETTLE_MS));
+            true
+        }
+        Err(e) => {
+            crate::clipboard::log_debug(&format!("[stream_insert] Cmd+V FAILED: {}", e));
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feed `deltas` then finish, as the typer does.
+    fn chunks_of(deltas: &[&str]) -> Vec<String> {
+        let mut buffer = ChunkBuffer::default();
+        let mut chunks: Vec<String> = deltas.iter().flat_map(|d| buffer.push(d)).collect();
+        chunks.extend(buffer.finish());
+        chunks
+    }
+
+    // ── chunk_end ───────────────────────────────────────────────────
+
+    #[test]
+    fn test_chunk_end_waits_for_the_space_after_a_sentence() {
+        assert_eq!(chunk_end("Bonjour."), None);
+        assert_eq!(chunk_end("Bonjour. Ça"), Some(8));
+        assert_eq!(chunk_end("Pi vaut 3.14 environ"), None);
+    }
+
+    #[test]
+    fn test_chunk_end_only_cuts_long_clauses() {
+        assert_eq!(chunk_end("Oui, merci"), None);
+        let text = "Pour la réunion de demain, prévoir";
+        assert_eq!(chunk_end(text), Some(text.find(',').unwrap() + 1));
+    }
+
+    #[test]
+    fn test_chunk_end_before_line_breaks() {
+        assert_eq!(chunk_end("Objet : point hebdo\nBonjour"), Some(19));
+        assert_eq!(chunk_end("\n\nBonjour"), None);
+    }
+
+    #[test]
+    fn test_chunk_end_cuts_long_runs_at_a_space() {
+        let text = "mot ".repeat(60);
+        let end = chunk_end(&text).unwrap();
+        assert!(end <= MAX_CHUNK_BYTES);
+        assert_eq!(&text[end..end + 1], " ");
+    }
+
+    // ── ChunkBuffer ─────────────────────────────────────────────────
+
+    #[test]
+    fn test_chunks_rebuild_the_trimmed_output() {
+        let deltas = ["  Bonjour à", " tous. Voici le", " point de la semaine", ", en bref : tout", " va bien.\n\n- Budget validé\n", "- Recrutement en cours  "];
+        let chunks = chunks_of(&deltas);
+        assert_eq!(chunks[0], "Bonjour à tous.");
+        assert!(chunks.len() > 3);
+        assert_eq!(chunks.concat(), deltas.concat().trim());
+    }
+
+    #[test]
+    fn test_chunks_stop_at_the_reflection_separator() {
+        let chunks = chunks_of(&["Message prêt.", "\n\n--- REFLE", "CTION ---\n", "J'ai raccourci. Et reformulé."]);
+        assert_eq!(chunks, vec!["Message prêt."]);
+        let chunks = chunks_of(&["Message prêt.\n---RÉFLEXION---"]);
+        assert_eq!(chunks, vec!["Message prêt."]);
+    }
+
+    #[test]
+    fn test_list_items_are_not_mistaken_for_the_separator() {
+        let chunks = chunks_of(&["Courses :\n- pain\n", "- lait"]);
+        assert_eq!(chunks.concat(), "Courses :\n- pain\n- lait");
+    }
+
+    #[test]
+    fn test_whitespace_only_output_types_nothing() {
+        assert!(chunks_of(&["  ", "\n"]).is_empty());
+    }
+}
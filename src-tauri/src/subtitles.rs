@@ -0,0 +1,381 @@
+/// Subtitle export (SRT, WebVTT, JSON) for imported files and archived recordings.
+/// The file is transcribed again with word and segment timings — pauses are not trimmed, so
+/// the timings follow the file — and dictionary corrections apply to the timed words too.
+use crate::dictionary::DictionaryEntry;
+use crate::transcription_provider::{language_code, TimedWord, Transcript};
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Two lines of `MAX_LINE_CHARS`, the usual subtitle limit.
+const MAX_CUE_CHARS: usize = 84;
+const MAX_LINE_CHARS: usize = 42;
+/// Longest time a cue stays on screen.
+const MAX_CUE_SECS: f64 = 7.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+    Json,
+}
+
+impl SubtitleFormat {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "srt" => Ok(Self::Srt),
+            "vtt" | "webvtt" => Ok(Self::Vtt),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("Format de sous-titres inconnu : {}", value)),
+        }
+    }
+}
+
+/// A stretch of text and when it is spoken, in seconds from the start of the file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Cue {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+}
+
+/// Transcript of a whole file with its timings (the JSON export).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TimedTranscript {
+    pub text: String,
+    pub language: Option<String>,
+    /// Whisper segments; empty for backends that only return word timings (whisper.cpp).
+    pub segments: Vec<Cue>,
+    pub words: Vec<TimedWord>,
+}
+
+impl TimedTranscript {
+    /// Append the transcript of an upload segment starting `offset` seconds into the file.
+    fn push(&mut self, transcript: Transcript, offset: f64) {
+        for segment in transcript.segments.unwrap_or_default() {
+            let text = segment.text.trim();
+            if !text.is_empty() {
+                self.segments.push(Cue {
+                    start: segment.start + offset,
+                    end: segment.end + offset,
+                    text: text.to_string(),
+                });
+            }
+        }
+        for word in transcript.words.unwrap_or_default() {
+            self.words.push(TimedWord {
+                start: word.start + offset,
+                end: word.end + offset,
+                ..word
+            });
+        }
+        self.text = crate::transcribe::stitch_segments(&[std::mem::take(&mut self.text), transcript.text]);
+    }
+
+    /// Dictionary corrections on the text, every segment and the timed words.
+    fn correct(&mut self, entries: &[DictionaryEntry]) {
+        self.text = crate::dictionary::correct_text(entries, &self.text);
+        for segment in &mut self.segments {
+            segment.text = crate::dictionary::correct_text(entries, &segment.text);
+        }
+        crate::dictionary::correct_words(entries, &mut self.words);
+    }
+}
+
+/// Transcribe `path` with timings and render it as `format`.
+pub async fn export(path: PathBuf, format: SubtitleFormat, app: &tauri::AppHandle) -> Result<String, String> {
+    let prefs = crate::preferences::get_preferences(app).unwrap_or_default();
+    let encoder = crate::audio::AudioEncoder::from_pref(&prefs.transcription.upload_format);
+    // Decoding, resampling and encoding are CPU-bound: keep them off the async runtime.
+    let segments = tauri::async_runtime::spawn_blocking(move || crate::audio::load_timed_segments(&path, encoder))
+        .await
+        .map_err(|e| e.to_string())??;
+    if segments.is_empty() {
+        return Err("Aucune parole détectée dans le fichier.".to_string());
+    }
+
+    // One segment after the other: each is prompted with the text so far
+    let mut timed = TimedTranscript::default();
+    let mut languages = Vec::new();
+    for (offset, audio) in segments {
+        let previous = (!timed.text.is_empty()).then(|| timed.text.clone());
+        let transcript = crate::transcribe::transcribe_timed(&audio, app, &prefs, previous.as_deref()).await?;
+        languages.push(transcript.language.as_deref().and_then(language_code));
+        timed.push(transcript, offset);
+    }
+    timed.language =
+        crate::transcribe::dominant_language(&languages).or_else(|| prefs.transcription.language_hint());
+    let entries = crate::dictionary::get_all_entries(app).unwrap_or_default();
+    timed.correct(&entries);
+    render(&timed, format)
+}
+
+pub fn render(timed: &TimedTranscript, format: SubtitleFormat) -> Result<String, String> {
+    match format {
+        SubtitleFormat::Json => serde_json::to_string_pretty(timed).map_err(|e| e.to_string()),
+        SubtitleFormat::Srt => Ok(to_srt(&build_cues(timed))),
+        SubtitleFormat::Vtt => Ok(to_vtt(&build_cues(timed))),
+    }
+}
+
+/// Subtitle cues: Whisper segments, long ones split at word timings; grouped words when
+/// the backend returned no segments.
+fn build_cues(timed: &TimedTranscript) -> Vec<Cue> {
+    if timed.segments.is_empty() {
+        return group(timed.words.iter().map(|w| (w.word.trim(), w.start, w.end)));
+    }
+    timed
+        .segments
+        .iter()
+        .flat_map(|segment| split_cue(segment, &timed.words))
+        .collect()
+}
+
+/// Split a segment too long to read at once. The words inside its time span give the
+/// timings; when they don't line up with the segment text (one word per whitespace-separated
+/// token), the segment stays whole.
+fn split_cue(segment: &Cue, words: &[TimedWord]) -> Vec<Cue> {
+    if segment.text.chars().count() <= MAX_CUE_CHARS && segment.end - segment.start <= MAX_CUE_SECS {
+        return vec![segment.clone()];
+    }
+    let tokens: Vec<&str> = segment.text.split_whitespace().collect();
+    let inside: Vec<&TimedWord> = words
+        .iter()
+        .filter(|w| {
+            let mid = (w.start + w.end) / 2.0;
+            segment.start <= mid && mid <= segment.end
+        })
+        .collect();
+    if tokens.is_empty() || tokens.len() != inside.len() {
+        return vec![segment.clone()];
+    }
+    group(tokens.into_iter().zip(inside).map(|(token, w)| (token, w.start, w.end)))
+}
+
+fn ends_sentence(text: &str) -> bool {
+    text.ends_with(['.', '?', '!', '…'])
+}
+
+/// Words into cues of at most `MAX_CUE_CHARS` and `MAX_CUE_SECS`, ending at sentence ends.
+fn group<'a>(words: impl Iterator<Item = (&'a str, f64, f64)>) -> Vec<Cue> {
+    let mut cues = Vec::new();
+    let mut current: Option<Cue> = None;
+    for (text, start, end) in words.filter(|(text, _, _)| !text.is_empty()) {
+        let fits = current.as_ref().is_some_and(|cue| {
+            cue.text.chars().count() + 1 + text.chars().count() <= MAX_CUE_CHARS && end - cue.start <= MAX_CUE_SECS
+        });
+        match current.as_mut() {
+            Some(cue) if fits => {
+                cue.text.push(' ');
+                cue.text.push_str(text);
+                cue.end = end;
+            }
+            _ => {
+                cues.extend(current.take());
+                current = Some(Cue {
+                    start,
+                    end,
+                    text: text.to_string(),
+                });
+            }
+        }
+        if ends_sentence(text) {
+            cues.extend(current.take());
+        }
+    }
+    cues.extend(current);
+    cues
+}
+
+/// Break a cue longer than one line at the space closest to its middle.
+fn wrap_lines(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= MAX_LINE_CHARS {
+        return text.to_string();
+    }
+    let middle = chars.len() / 2;
+    let Some(split) = chars
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| **c == ' ')
+        .map(|(i, _)| i)
+        .min_by_key(|i| i.abs_diff(middle))
+    else {
+        return text.to_string();
+    };
+    let (first, second) = chars.split_at(split);
+    format!("{}\n{}", first.iter().collect::<String>(), second[1..].iter().collect::<String>())
+}
+
+/// `HH:MM:SS,mmm` (SRT) or `HH:MM:SS.mmm` (WebVTT).
+fn timestamp(secs: f64, separator: char) -> String {
+    let ms = (secs.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+fn to_srt(cues: &[Cue]) -> String {
+    cues.iter()
+        .enumerate()
+        .map(|(i, cue)| {
+            format!(
+                "{}\n{} --> {}\n{}\n\n",
+                i + 1,
+                timestamp(cue.start, ','),
+                timestamp(cue.end, ','),
+                wrap_lines(&cue.text)
+            )
+        })
+        .collect()
+}
+
+fn to_vtt(cues: &[Cue]) -> String {
+    let mut out = String::from("WEBVTT\n\n");
+    for cue in cues {
+        out.push_str(&format!(
+            "{} --> {}\n{}\n\n",
+            timestamp(cue.start, '.'),
+            timestamp(cue.end, '.'),
+            wrap_lines(&cue.text)
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcription_provider::SegmentStats;
+
+    fn cue(start: f64, end: f64, text: &str) -> Cue {
+        Cue {
+            start,
+            end,
+            text: text.to_string(),
+        }
+    }
+
+    fn word(word: &str, start: f64, end: f64) -> TimedWord {
+        TimedWord {
+            word: word.to_string(),
+            start,
+            end,
+        }
+    }
+
+    // ── SubtitleFormat ──────────────────────────────────────────────
+
+    #[test]
+    fn test_parse_format() {
+        assert_eq!(SubtitleFormat::parse("SRT"), Ok(SubtitleFormat::Srt));
+        assert_eq!(SubtitleFormat::parse("webvtt"), Ok(SubtitleFormat::Vtt));
+        assert_eq!(SubtitleFormat::parse("json"), Ok(SubtitleFormat::Json));
+        assert!(SubtitleFormat::parse("ass").is_err());
+    }
+
+    // ── TimedTranscript ─────────────────────────────────────────────
+
+    #[test]
+    fn test_push_shifts_timings_by_segment_offset() {
+        let mut timed = TimedTranscript::default();
+        timed.push(
+            Transcript {
+                text: "Bonjour.".to_string(),
+                segments: Some(vec![SegmentStats {
+                    text: " Bonjour.".to_string(),
+                    start: 0.0,
+                    end: 1.0,
+                    no_speech_prob: 0.0,
+                    avg_logprob: 0.0,
+                    compression_ratio: 1.0,
+                }]),
+                language: None,
+                words: Some(vec![word("Bonjour", 0.1, 0.8)]),
+            },
+            120.0,
+        );
+        timed.push(Transcript::from("Suite.".to_string()), 240.0);
+        assert_eq!(timed.text, "Bonjour. Suite.");
+        assert_eq!(timed.segments, vec![cue(120.0, 121.0, "Bonjour.")]);
+        assert_eq!(timed.words, vec![word("Bonjour", 120.1, 120.8)]);
+    }
+
+    // ── cues ────────────────────────────────────────────────────────
+
+    #[test]
+    fn test_short_segments_are_cues() {
+        let timed = TimedTranscript {
+            segments: vec![cue(0.0, 2.0, "Bonjour à tous."), cue(2.0, 4.0, "On commence.")],
+            ..Default::default()
+        };
+        assert_eq!(build_cues(&timed), timed.segments);
+    }
+
+    #[test]
+    fn test_long_segment_split_at_word_timings() {
+        let text = "Aujourd'hui on va voir comment déployer le service. Ensuite on regardera les journaux ensemble";
+        let tokens: Vec<&str> = text.split_whitespace().collect();
+        let words: Vec<TimedWord> = tokens
+            .iter()
+            .enumerate()
+            .map(|(i, t)| word(t.trim_end_matches('.'), i as f64 * 0.5, i as f64 * 0.5 + 0.4))
+            .collect();
+        let segment = cue(0.0, tokens.len() as f64 * 0.5, text);
+        let cues = split_cue(&segment, &words);
+        assert_eq!(cues.len(), 2);
+        assert_eq!(cues[0].text, "Aujourd'hui on va voir comment déployer le service.");
+        assert_eq!(cues[0].end, 3.9);
+        assert_eq!(cues[1].start, 4.0);
+    }
+
+    #[test]
+    fn test_long_segment_kept_when_words_do_not_line_up() {
+        let segment = cue(0.0, 9.0, "Un texte un peu long qui dure plus que sept secondes - sans mots alignés");
+        assert_eq!(split_cue(&segment, &[word("Un", 0.0, 0.2)]), vec![segment.clone()]);
+    }
+
+    #[test]
+    fn test_words_grouped_without_segments() {
+        let timed = TimedTranscript {
+            words: vec![word("Oui.", 0.0, 0.3), word("On", 0.5, 0.6), word("y", 0.6, 0.7), word("va", 0.7, 0.9)],
+            ..Default::default()
+        };
+        assert_eq!(build_cues(&timed), vec![cue(0.0, 0.3, "Oui."), cue(0.5, 0.9, "On y va")]);
+    }
+
+    // ── rendering ───────────────────────────────────────────────────
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(3723.4567, ','), "01:02:03,457");
+        assert_eq!(timestamp(0.0, '.'), "00:00:00.000");
+    }
+
+    #[test]
+    fn test_wrap_lines_at_middle_space() {
+        assert_eq!(wrap_lines("Court."), "Court.");
+        let wrapped = wrap_lines("Une phrase assez longue pour tenir sur deux lignes.");
+        assert_eq!(wrapped, "Une phrase assez longue\npour tenir sur deux lignes.");
+    }
+
+    #[test]
+    fn test_render_srt_and_vtt() {
+        let timed = TimedTranscript {
+            segments: vec![cue(0.0, 1.5, "Bonjour."), cue(1.5, 3.0, "Merci.")],
+            ..Default::default()
+        };
+        assert_eq!(
+            render(&timed, SubtitleFormat::Srt).unwrap(),
+            "1\n00:00:00,000 --> 00:00:01,500\nBonjour.\n\n2\n00:00:01,500 --> 00:00:03,000\nMerci.\n\n"
+        );
+        assert_eq!(
+            render(&timed, SubtitleFormat::Vtt).unwrap(),
+            "WEBVTT\n\n00:00:00.000 --> 00:00:01.500\nBonjour.\n\n00:00:01.500 --> 00:00:03.000\nMerci.\n\n"
+        );
+    }
+}
@@ -1,20 +1,32 @@
-use crate::http_client;
+use crate::audio::EncodedAudio;
+use crate::fallback::FallbackTrail;
+use crate::preferences::{HallucinationThresholds, Preferences};
+use crate::transcription_provider::{
+    language_code, provider_for, provider_for_hop, SegmentStats, TranscribeOptions, Transcript,
+    TranscriptionProvider,
+};
 use std::time::Duration;
 
 const MAX_RETRIES: u32 = 3;
 
+/// Max segments of a long recording in flight at once when they are not prompted with
+/// their predecessor — enough to hide upload latency without tripping rate limits.
+const SEGMENT_PARALLELISM: usize = 3;
+
+const INAUDIBLE_MSG: &str = "Transcription vide ou inaudible. Essayez de parler plus fort ou plus longtemps.";
+
+/// Reject garbage: only punctuation/dots, single characters, or empty.
+fn is_garbage(text: &str) -> bool {
+    let stripped: String = text.trim().chars().filter(|c| c.is_alphanumeric()).collect();
+    // "...", "A.", "!!!!", "......................" etc.
+    stripped.len() <= 1
+}
+
 /// Known Whisper hallucination patterns — boilerplate text from training data
 /// that Whisper outputs when audio is too short, silent, or inaudible.
 fn is_whisper_hallucination(text: &str) -> bool {
     let trimmed = text.trim();
-
-    // Reject garbage: only punctuation/dots, single characters, or empty
-    if trimmed.is_empty() {
-        return true;
-    }
-    let stripped: String = trimmed.chars().filter(|c| c.is_alphanumeric()).collect();
-    if stripped.len() <= 1 {
-        // "...", "A.", "!!!!", "......................" etc.
+    if is_garbage(trimmed) {
         return true;
     }
 
@@ -50,226 +62,402 @@ fn is_whisper_hallucination(text: &str) -> bool {
     short_hallucinations.iter().any(|h| trimmed_lower == *h)
 }
 
-/// Transcription async à partir du WAV en mémoire. Retries avec backoff.
-/// If the primary provider (e.g. Groq) fails after all retries and an OpenAI key exists,
-/// automatically falls back to OpenAI and emits a `provider_fallback` event.
+/// Guarded transcription text and the language it was spoken in, when known.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Transcription {
+    pub text: String,
+    /// ISO 639-1 code: detected by the provider, or the language that was requested.
+    pub language: Option<String>,
+    /// Provider id and model that answered (the fallback's after a fallback); for a
+    /// multi-segment take, those of the last segment.
+    pub provider: Option<String>,
+    pub model: Option<String>,
+}
+
+impl Transcription {
+    fn answered_by(self, provider: &dyn TranscriptionProvider) -> Self {
+        Self {
+            provider: Some(provider.id().to_string()),
+            model: Some(provider.model().to_string()),
+            ..self
+        }
+    }
+}
+
+/// Most frequent language across segments; ties go to the earliest.
+pub fn dominant_language(languages: &[Option<String>]) -> Option<String> {
+    let mut best: Option<(&String, usize)> = None;
+    for lang in languages.iter().flatten() {
+        let count = languages.iter().flatten().filter(|l| *l == lang).count();
+        if !matches!(best, Some((_, n)) if n >= count) {
+            best = Some((lang, count));
+        }
+    }
+    best.map(|(lang, _)| lang.clone())
+}
+
+/// Transcribe a recording split into segments (see `audio::split_at_silences`), stitched
+/// back in order. They go up concurrently, each prompted with the head text; with
+/// `transcription.chain_segments` they go up one after the other instead, each prompted with
+/// its predecessor's transcript (see `transcribe_in_order`).
+///
+/// `committed` is the start of the take already transcribed by live partials (see
+/// `live_transcript`): `segments` then only cover the rest, and are stitched after it.
+/// `context` is the text the recording continues (see `whisper_prompt::dictation_context`),
+/// used as prompt for the first segment when nothing was committed.
+pub async fn transcribe_segments(
+    segments: Vec<EncodedAudio>,
+    app: &tauri::AppHandle,
+    prefs: &Preferences,
+    committed: Option<Transcription>,
+    context: Option<&str>,
+) -> Result<Transcription, String> {
+    let committed = committed.filter(|c| !c.text.trim().is_empty());
+    if segments.len() <= 1 && committed.is_none() {
+        let audio = segments
+            .into_iter()
+            .next()
+            .ok_or_else(|| "aucun audio enregistré".to_string())?;
+        return transcribe_bytes(audio, app, prefs, context).await;
+    }
+
+    let head = committed.unwrap_or_default();
+    let head_text = match head.text.as_str() {
+        "" => context.unwrap_or_default(),
+        text => text,
+    };
+    let chained = prefs.transcription.chain_segments;
+    let results = transcribe_in_order(segments, head_text, chained, |audio, previous| async move {
+        match transcribe_bytes(audio, app, prefs, previous.as_deref()).await {
+            // A segment that is only breathing or a trailing "merci" is dropped, not fatal
+            Err(e) if e == INAUDIBLE_MSG => Ok(Transcription::default()),
+            r => r,
+        }
+    })
+    .await;
+
+    // Each segment already went through the hallucination guard
+    let parts = std::iter::once(Ok(head))
+        .chain(results)
+        .collect::<Result<Vec<_>, _>>()?;
+    let (provider, model) = parts
+        .iter()
+        .rev()
+        .find(|t| t.provider.is_some())
+        .map(|t| (t.provider.clone(), t.model.clone()))
+        .unwrap_or_default();
+    let (texts, languages): (Vec<String>, Vec<Option<String>>) =
+        parts.into_iter().map(|t| (t.text, t.language)).unzip();
+    let text = stitch_segments(&texts);
+    if is_garbage(&text) {
+        Err(INAUDIBLE_MSG.to_string())
+    } else {
+        Ok(Transcription {
+            text,
+            language: dominant_language(&languages),
+            provider,
+            model,
+        })
+    }
+}
+
+/// Run `transcribe` on each segment, results in order. `chained`: each segment waits for
+/// the one before it and is prompted with its transcript (the last non-empty one when it
+/// was inaudible), the first one with `head`; the chain stops at the first error.
+/// Otherwise segments run concurrently, at most `SEGMENT_PARALLELISM` at a time, each
+/// prompted with `head` (the dictionary terms are added to every prompt anyway).
+async fn transcribe_in_order<F, Fut>(
+    segments: Vec<EncodedAudio>,
+    head: &str,
+    chained: bool,
+    transcribe: F,
+) -> Vec<Result<Transcription, String>>
+where
+    F: Fn(EncodedAudio, Option<String>) -> Fut,
+    Fut: std::future::Future<Output = Result<Transcription, String>>,
+{
+    use futures_util::stream::{self, StreamExt};
+
+    if !chained {
+        return stream::iter(segments)
+            .map(|audio| transcribe(audio, Some(head.to_string())))
+            .buffered(SEGMENT_PARALLELISM)
+            .collect()
+            .await;
+    }
+    let mut previous = head.to_string();
+    let mut results = Vec::with_capacity(segments.len());
+    for audio in segments {
+        let result = transcribe(audio, Some(previous.clone())).await;
+        match &result {
+            Ok(t) if !t.text.trim().is_empty() => previous = t.text.clone(),
+            Ok(_) => {}
+            Err(_) => {
+                results.push(result);
+                break;
+            }
+        }
+        results.push(result);
+    }
+    results
+}
+
+pub fn stitch_segments(texts: &[String]) -> String {
+    texts
+        .iter()
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Transcription async à partir de l'audio encodé en mémoire. Retries avec backoff.
+/// Once the configured provider has failed after all retries, each hop of the fallback
+/// chain (`TranscriptionPrefs::fallback_chain`) gets one attempt, announced by a
+/// `provider_fallback` event naming it.
+/// `prefs` are the caller's preferences (possibly overridden for a re-transcription).
 pub async fn transcribe_bytes(
-    wav_bytes: Vec<u8>,
+    audio: EncodedAudio,
     app: &tauri::AppHandle,
-) -> Result<String, String> {
+    prefs: &Preferences,
+    previous_text: Option<&str>,
+) -> Result<Transcription, String> {
     use tauri::Emitter;
 
-    let mut attempt = 0;
-    let primary_error = loop {
-        match transcribe_bytes_internal(&wav_bytes, app).await {
-            Ok(result) => return guard_hallucination(result),
-            Err(e) if attempt < MAX_RETRIES => {
-                attempt += 1;
-                let backoff = Duration::from_millis(100 * 2u64.pow(attempt));
-                #[cfg(debug_assertions)]
-                eprintln!(
-                    "Transcription tentative {}/{} échouée: {}",
-                    attempt, MAX_RETRIES, e
-                );
-                tokio::time::sleep(backoff).await;
-            }
-            Err(e) => {
-                break e;
-            }
+    let opts = transcribe_options(app, prefs, previous_text);
+
+    let mut trail = match provider_for(&prefs.transcription.provider, prefs) {
+        // Missing key or base URL: retrying cannot help
+        Err(e) => FallbackTrail::new(&prefs.transcription.provider, None, e),
+        Ok(provider) => {
+            let mut attempt = 0;
+            let error = loop {
+                match provider.transcribe(&audio, &opts).await {
+                    Ok(result) => {
+                        record_usage(app, &*provider, &audio);
+                        let limits = prefs.transcription.thresholds_for(provider.id());
+                        return guard_transcript(result, &limits, &opts).map(|t| t.answered_by(&*provider));
+                    }
+                    Err(e) if attempt < MAX_RETRIES => {
+                        attempt += 1;
+                        let backoff = Duration::from_millis(100 * 2u64.pow(attempt));
+                        #[cfg(debug_assertions)]
+                        eprintln!(
+                            "Transcription tentative {}/{} échouée ({}): {}",
+                            attempt, MAX_RETRIES, provider.name(), e
+                        );
+                        tokio::time::sleep(backoff).await;
+                    }
+                    Err(e) => break e,
+                }
+            };
+            FallbackTrail::new(provider.name(), Some((provider.id(), provider.model())), error)
         }
     };
 
-    // Provider fallback: if primary was Groq, try OpenAI
-    let prefs = crate::preferences::get_preferences(app).unwrap_or_default();
-    if prefs.transcription.provider == "groq" && crate::secrets::get_key_for_provider("openai").or_else(|_| crate::secrets::get_api_key_cached()).is_ok() {
+    for hop in prefs.transcription.fallback_chain() {
+        let provider = match provider_for_hop(&hop, prefs) {
+            Ok(provider) => provider,
+            Err(_e) => {
+                #[cfg(debug_assertions)]
+                eprintln!("Transcription fallback {} skipped: {}", hop.provider, _e);
+                continue;
+            }
+        };
+        let Some(label) = trail.hop(provider.id(), provider.name(), provider.model()) else {
+            continue;
+        };
         #[cfg(debug_assertions)]
-        eprintln!("Groq transcription failed, falling back to OpenAI: {}", primary_error);
-        let _ = app.emit("provider_fallback", "Groq → OpenAI");
-        match transcribe_bytes_openai_fallback(&wav_bytes, app).await {
-            Ok(result) => return guard_hallucination(result),
-            Err(fallback_err) => {
-                return Err(format!(
-                    "Échec transcription après {} tentatives (Groq: {}, OpenAI fallback: {})",
-                    MAX_RETRIES, primary_error, fallback_err
-                ));
+        eprintln!("Transcription fallback: {}", label);
+        let _ = app.emit("provider_fallback", label);
+        let hop_opts = TranscribeOptions {
+            timeout: hop.timeout_secs.map(|s| Duration::from_secs(s.clamp(5, 120))).unwrap_or(opts.timeout),
+            ..opts.clone()
+        };
+        match provider.transcribe(&audio, &hop_opts).await {
+            Ok(result) => {
+                record_usage(app, &*provider, &audio);
+                let limits = prefs.transcription.thresholds_for(provider.id());
+                return guard_transcript(result, &limits, &hop_opts).map(|t| t.answered_by(&*provider));
             }
+            Err(e) => trail.failed(e),
         }
     }
 
-    Err(format!(
-        "Échec transcription après {} tentatives: {}",
-        MAX_RETRIES, primary_error
-    ))
+    Err(trail.into_error("transcription", MAX_RETRIES))
 }
 
-/// Single guard point for hallucination filtering — every transcription path goes through here.
-fn guard_hallucination(text: String) -> Result<String, String> {
-    if is_whisper_hallucination(&text) {
-        Err("Transcription vide ou inaudible. Essayez de parler plus fort ou plus longtemps.".to_string())
-    } else {
-        Ok(text)
-    }
+/// Single attempt with the configured provider, no retry nor fallback: a live partial is
+/// superseded by the next one anyway, and must not hold the final transcription back.
+pub async fn transcribe_partial(
+    audio: EncodedAudio,
+    app: &tauri::AppHandle,
+    prefs: &Preferences,
+    previous_text: Option<&str>,
+) -> Result<Transcription, String> {
+    let opts = transcribe_options(app, prefs, previous_text);
+    let provider = provider_for(&prefs.transcription.provider, prefs)?;
+    let result = provider.transcribe(&audio, &opts).await?;
+    record_usage(app, &*provider, &audio);
+    guard_transcript(result, &prefs.transcription.thresholds_for(provider.id()), &opts)
+        .map(|t| t.answered_by(&*provider))
 }
 
-/// Fallback transcription using OpenAI when primary provider fails.
-async fn transcribe_bytes_openai_fallback(
-    wav_bytes: &[u8],
-    app: &tauri::AppHandle,
-) -> Result<String, String> {
-    let prefs = crate::preferences::get_preferences(app).unwrap_or_default();
-    let api_key = crate::secrets::get_key_for_provider("openai")
-        .or_else(|_| crate::secrets::get_api_key_cached())?;
-    let base_url = prefs.advanced.transcription_base_url
-        .as_deref()
-        .unwrap_or("https://api.openai.com")
-        .trim_end_matches('/')
-        .to_string();
-    validate_base_url(&base_url)?;
-    let timeout_secs = prefs.transcription.timeout_secs.clamp(10, 120);
-    let url = format!("{}/v1/audio/transcriptions", base_url);
-
-    let part = reqwest::multipart::Part::bytes(wav_bytes.to_vec())
-        .file_name("audio.wav")
-        .mime_str("audio/wav")
-        .map_err(|e| e.to_string())?;
-
-    let mut form = reqwest::multipart::Form::new()
-        .part("file", part)
-        .text("model", "whisper-1".to_string());
-    let lang = prefs.transcription.language.as_deref().unwrap_or("fr");
-    if !lang.is_empty() {
-        form = form.text("language", lang.to_string());
-    }
-
-    let dict_prompt = crate::dictionary::build_whisper_prompt(app);
-    if !dict_prompt.is_empty() {
-        form = form.text("prompt", dict_prompt);
-    }
-
-    let resp = http_client::client()
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .multipart(form)
-        .timeout(Duration::from_secs(timeout_secs))
-        .send()
-        .await
-        .map_err(|e| format!("OpenAI fallback request error: {}", e))?;
-
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_default();
-        return Err(format!("OpenAI fallback API error {}: {}", status, body));
-    }
-
-    #[derive(serde::Deserialize)]
-    struct Transcription {
-        text: String,
-    }
-    let out: Transcription = resp.json().await.map_err(|e| e.to_string())?;
-    Ok(out.text.trim().to_string())
+/// Count an answered upload, billed on its audio even when the guard then drops the text.
+fn record_usage(app: &tauri::AppHandle, provider: &dyn TranscriptionProvider, audio: &EncodedAudio) {
+    crate::usage::record_transcription(app, provider.id(), provider.model(), audio.duration_secs);
 }
 
-/// Validate that a base URL uses HTTPS. Rejects plain HTTP to protect API keys in transit.
-fn validate_base_url(url: &str) -> Result<(), String> {
-    if url.starts_with("https://") {
-        Ok(())
-    } else if url.starts_with("http://") {
-        Err("Insecure base URL rejected: plain HTTP is not allowed for API endpoints that transmit API keys. Use https:// instead.".to_string())
-    } else {
-        Err(format!("Invalid base URL '{}': must start with https://", url))
+fn transcribe_options(
+    app: &tauri::AppHandle,
+    prefs: &Preferences,
+    previous_text: Option<&str>,
+) -> TranscribeOptions {
+    // Send dict words as prompt to guide Whisper transcription.
+    // Don't send conversational text as prompt — Whisper may echo it back. The only
+    // text sent is the tail of what this audio continues.
+    let language = prefs.transcription.language_hint();
+    let prompt = crate::whisper_prompt::build(
+        &crate::dictionary::prompt_terms(app),
+        previous_text,
+        language.as_deref(),
+    );
+    TranscribeOptions {
+        language,
+        prompt,
+        timeout: Duration::from_secs(prefs.transcription.timeout_secs.clamp(10, 120)),
+        timestamps: false,
     }
 }
 
-async fn transcribe_bytes_internal(
-    wav_bytes: &[u8],
+/// Transcribe one segment with word and segment timings, for subtitle export. No provider
+/// fallback: the timings of a file should all come from the same model.
+pub async fn transcribe_timed(
+    audio: &EncodedAudio,
     app: &tauri::AppHandle,
-) -> Result<String, String> {
-    let prefs = crate::preferences::get_preferences(app).unwrap_or_default();
-    let provider = prefs.transcription.provider.as_str();
-    let timeout_secs = prefs.transcription.timeout_secs.clamp(10, 120);
-
-    // Resolve API key and base URL based on provider
-    let (api_key, base_url, model) = match provider {
-        "groq" => {
-            let key = crate::secrets::get_key_for_provider("groq")
-                .map_err(|_| "No Groq API key found. Add a Groq key (gsk_...) in Settings → API Keys.".to_string())?;
-            let url = prefs.advanced.transcription_base_url
-                .as_deref()
-                .unwrap_or("https://api.groq.com/openai")
-                .trim_end_matches('/')
-                .to_string();
-            validate_base_url(&url)?;
-            // Groq supports whisper-large-v3-turbo (fastest) and whisper-large-v3
-            let model = if prefs.transcription.model.starts_with("whisper-large") {
-                prefs.transcription.model.clone()
-            } else {
-                "whisper-large-v3-turbo".to_string()
-            };
-            (key, url, model)
-        }
-        _ => {
-            // OpenAI (default)
-            let key = crate::secrets::get_key_for_provider("openai")
-                .or_else(|_| crate::secrets::get_api_key_cached())?;
-            let url = prefs.advanced.transcription_base_url
-                .as_deref()
-                .unwrap_or("https://api.openai.com")
-                .trim_end_matches('/')
-                .to_string();
-            validate_base_url(&url)?;
-            (key, url, prefs.transcription.model.clone())
-        }
+    prefs: &Preferences,
+    previous_text: Option<&str>,
+) -> Result<Transcript, String> {
+    let opts = TranscribeOptions {
+        timestamps: true,
+        ..transcribe_options(app, prefs, previous_text)
     };
-    let url = format!("{}/v1/audio/transcriptions", base_url);
-
-    let part = reqwest::multipart::Part::bytes(wav_bytes.to_vec())
-        .file_name("audio.wav")
-        .mime_str("audio/wav")
-        .map_err(|e| e.to_string())?;
-
-    let mut form = reqwest::multipart::Form::new()
-        .part("file", part)
-        .text("model", model);
-    let lang = prefs.transcription.language.as_deref().unwrap_or("fr");
-    if !lang.is_empty() {
-        form = form.text("language", lang.to_string());
+    let provider = provider_for(&prefs.transcription.provider, prefs)?;
+    if !provider.capabilities().timestamps {
+        return Err(format!(
+            "{} ne fournit pas d'horodatage avec le modèle {}. Choisissez un modèle Whisper.",
+            provider.name(),
+            prefs.transcription.model
+        ));
     }
-
-    let dict_prompt = crate::dictionary::build_whisper_prompt(app);
-    // Send dict words as prompt to guide Whisper transcription.
-    // Don't send conversational text as prompt — Whisper may echo it back.
-    if !dict_prompt.is_empty() {
-        form = form.text("prompt", dict_prompt);
+    let mut attempt = 0;
+    loop {
+        match provider.transcribe(audio, &opts).await {
+            Ok(result) => {
+                record_usage(app, &*provider, audio);
+                let limits = prefs.transcription.thresholds_for(provider.id());
+                return Ok(guard_timed(result, &limits));
+            }
+            Err(e) if attempt < MAX_RETRIES => {
+                attempt += 1;
+                #[cfg(debug_assertions)]
+                eprintln!("Transcription horodatée {}/{} échouée: {}", attempt, MAX_RETRIES, e);
+                tokio::time::sleep(Duration::from_millis(100 * 2u64.pow(attempt))).await;
+            }
+            Err(e) => {
+                return Err(format!(
+                    "Échec transcription après {} tentatives: {}",
+                    MAX_RETRIES, e
+                ))
+            }
+        }
     }
+}
+
+/// A segment Whisper itself considers silence or a repetition loop.
+fn is_rejected_segment(segment: &SegmentStats, limits: &HallucinationThresholds) -> bool {
+    (segment.no_speech_prob > limits.no_speech_prob && segment.avg_logprob < limits.avg_logprob)
+        || segment.compression_ratio > limits.compression_ratio
+}
 
-    let resp = http_client::client()
-        .post(&url)
-        .header("Authorization", format!("Bearer {}", api_key))
-        .multipart(form)
-        .timeout(Duration::from_secs(timeout_secs))
-        .send()
-        .await
-        .map_err(|e| format!("Erreur requête API Whisper: {}", e))?;
+/// Single guard point for hallucination filtering — every provider response goes through
+/// here. The language is the detected one, else the one that was requested.
+fn guard_transcript(
+    transcript: Transcript,
+    limits: &HallucinationThresholds,
+    opts: &TranscribeOptions,
+) -> Result<Transcription, String> {
+    let language = transcript
+        .language
+        .as_deref()
+        .and_then(language_code)
+        .or_else(|| opts.language.clone());
+    let text = guard_text(transcript, limits)?;
+    Ok(Transcription {
+        text,
+        language,
+        ..Default::default()
+    })
+}
 
-    if !resp.status().is_success() {
-        let status = resp.status();
-        let body = resp.text().await.unwrap_or_default();
-        return Err(format!("API error {}: {}", status, body));
+/// With `verbose_json` statistics, suspicious segments are dropped and short real
+/// dictations ("ok", "merci") pass; without them the phrase list decides.
+fn guard_text(transcript: Transcript, limits: &HallucinationThresholds) -> Result<String, String> {
+    let Some(segments) = transcript.segments else {
+        return guard_hallucination(transcript.text);
+    };
+    let text = if segments.iter().any(|s| is_rejected_segment(s, limits)) {
+        segments
+            .iter()
+            .filter(|s| !is_rejected_segment(s, limits))
+            .map(|s| s.text.trim())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    } else {
+        transcript.text
+    };
+    if is_garbage(&text) {
+        Err(INAUDIBLE_MSG.to_string())
+    } else {
+        Ok(text)
     }
+}
 
-    #[derive(serde::Deserialize)]
-    struct Transcription {
-        text: String,
+/// Hallucination guard for timed transcripts: rejected segments go with the words spoken
+/// during them; without statistics a hallucinated text empties the whole transcript.
+fn guard_timed(mut transcript: Transcript, limits: &HallucinationThresholds) -> Transcript {
+    match transcript.segments.take() {
+        Some(segments) => {
+            let kept: Vec<SegmentStats> = segments
+                .into_iter()
+                .filter(|s| !is_rejected_segment(s, limits))
+                .collect();
+            if let Some(words) = transcript.words.as_mut() {
+                words.retain(|w| {
+                    let mid = (w.start + w.end) / 2.0;
+                    kept.iter().any(|s| s.start <= mid && mid <= s.end)
+                });
+            }
+            transcript.text = stitch_segments(&kept.iter().map(|s| s.text.clone()).collect::<Vec<_>>());
+            transcript.segments = Some(kept);
+        }
+        None if is_whisper_hallucination(&transcript.text) => {
+            transcript.text.clear();
+            transcript.words = None;
+        }
+        None => {}
     }
-    let out: Transcription = resp.json().await.map_err(|e| e.to_string())?;
-    let text = out.text.trim().to_string();
+    transcript
+}
 
-    // Filter known Whisper hallucinations (boilerplate from training data)
+/// Phrase-list guard, for responses without segment statistics.
+fn guard_hallucination(text: String) -> Result<String, String> {
     if is_whisper_hallucination(&text) {
-        return Err("Transcription vide ou inaudible. Essayez de parler plus fort ou plus longtemps.".to_string());
+        Err(INAUDIBLE_MSG.to_string())
+    } else {
+        Ok(text)
     }
-
-    Ok(text)
 }
 
 #[cfg(test)]
@@ -438,25 +626,219 @@ mod tests {
         assert!(is_whisper_hallucination("C'est parti."));
     }
 
-    // ── validate_base_url ──────────────────────────────────────────
+    // ── transcribe_in_order ─────────────────────────────────────────
+
+    fn encoded_segment(index: u8) -> EncodedAudio {
+        EncodedAudio {
+            bytes: vec![index],
+            encoder: crate::audio::AudioEncoder::Wav,
+            duration_secs: 1.0,
+        }
+    }
+
+    /// Transcribes segment n as "segment n" (empty for `inaudible`), recording its prompt.
+    async fn run(chained: bool, inaudible: u8) -> (Vec<Result<Transcription, String>>, Vec<(u8, Option<String>)>) {
+        let prompts = std::sync::Mutex::new(Vec::new());
+        let segments = (0..4).map(encoded_segment).collect();
+        let results = transcribe_in_order(segments, "début", chained, |audio, previous| {
+            let index = audio.bytes[0];
+            prompts.lock().unwrap().push((index, previous));
+            async move {
+                tokio::task::yield_now().await;
+                let text = if index == inaudible { String::new() } else { format!("segment {}", index) };
+                Ok(Transcription { text, ..Default::default() })
+            }
+        })
+        .await;
+        (results, prompts.into_inner().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_chained_segments_get_the_previous_transcript() {
+        let (results, prompts) = run(true, 2).await;
+        let texts: Vec<String> = results.into_iter().map(|r| r.unwrap().text).collect();
+        assert_eq!(texts, vec!["segment 0", "segment 1", "", "segment 3"]);
+        let prompts: Vec<Option<&str>> = prompts.iter().map(|(_, p)| p.as_deref()).collect();
+        // Segment 2 was inaudible: segment 3 continues segment 1
+        assert_eq!(prompts, vec![Some("début"), Some("segment 0"), Some("segment 1"), Some("segment 1")]);
+    }
+
+    #[tokio::test]
+    async fn test_unchained_segments_keep_their_order() {
+        let (results, prompts) = run(false, 9).await;
+        let texts: Vec<String> = results.into_iter().map(|r| r.unwrap().text).collect();
+        assert_eq!(texts, vec!["segment 0", "segment 1", "segment 2", "segment 3"]);
+        assert!(prompts.iter().all(|(_, p)| p.as_deref() == Some("début")));
+    }
+
+    // ── stitch_segments ─────────────────────────────────────────────
 
     #[test]
-    fn test_validate_base_url_https_ok() {
-        assert!(validate_base_url("https://api.openai.com").is_ok());
-        assert!(validate_base_url("https://api.groq.com/openai").is_ok());
+    fn test_stitch_segments_in_order_skipping_empty() {
+        let texts = vec![" Première partie.".to_string(), String::new(), "Deuxième partie. ".to_string()];
+        assert_eq!(stitch_segments(&texts), "Première partie. Deuxième partie.");
     }
 
     #[test]
-    fn test_validate_base_url_http_rejected() {
-        let result = validate_base_url("http://api.openai.com");
-        assert!(result.is_err());
-        assert!(result.unwrap_err().contains("plain HTTP"));
+    fn test_guard_hallucination_message_matches_segment_filter() {
+        assert_eq!(guard_hallucination("...".to_string()), Err(INAUDIBLE_MSG.to_string()));
+    }
+
+    // ── guard_text ─────────────────────────────────────────────────
+
+    fn segment(text: &str, no_speech_prob: f32, avg_logprob: f32, compression_ratio: f32) -> SegmentStats {
+        SegmentStats {
+            text: text.to_string(),
+            start: 0.0,
+            end: 0.0,
+            no_speech_prob,
+            avg_logprob,
+            compression_ratio,
+        }
+    }
+
+    fn transcript(segments: Vec<SegmentStats>) -> Transcript {
+        let text = segments.iter().map(|s| s.text.as_str()).collect::<String>().trim().to_string();
+        Transcript {
+            text,
+            segments: Some(segments),
+            language: None,
+            words: None,
+        }
+    }
+
+    #[test]
+    fn test_guard_transcript_confident_short_word_passes() {
+        let limits = HallucinationThresholds::default();
+        let t = transcript(vec![segment(" OK.", 0.05, -0.2, 0.6)]);
+        assert_eq!(guard_text(t, &limits), Ok("OK.".to_string()));
+    }
+
+    #[test]
+    fn test_guard_transcript_drops_silent_segment() {
+        let limits = HallucinationThresholds::default();
+        let t = transcript(vec![
+            segment(" Envoie le rapport à Paul.", 0.02, -0.3, 1.1),
+            segment(" Merci d'avoir regardé.", 0.9, -1.4, 0.9),
+        ]);
+        assert_eq!(guard_text(t, &limits), Ok("Envoie le rapport à Paul.".to_string()));
+    }
+
+    #[test]
+    fn test_guard_transcript_drops_repetition_loop() {
+        let limits = HallucinationThresholds::default();
+        let t = transcript(vec![segment(" la la la la la la la la la la la", 0.1, -0.5, 3.2)]);
+        assert_eq!(guard_text(t, &limits), Err(INAUDIBLE_MSG.to_string()));
+    }
+
+    #[test]
+    fn test_guard_transcript_high_no_speech_but_confident_kept() {
+        // Whisper's rule needs both: a confident decode is kept despite no_speech_prob
+        let limits = HallucinationThresholds::default();
+        let t = transcript(vec![segment(" Oui.", 0.7, -0.3, 0.5)]);
+        assert_eq!(guard_text(t, &limits), Ok("Oui.".to_string()));
+    }
+
+    #[test]
+    fn test_guard_transcript_custom_thresholds() {
+        let strict = HallucinationThresholds {
+            no_speech_prob: 0.5,
+            avg_logprob: -0.2,
+            compression_ratio: 2.4,
+        };
+        let t = transcript(vec![segment(" Oui.", 0.7, -0.3, 0.5)]);
+        assert_eq!(guard_text(t, &strict), Err(INAUDIBLE_MSG.to_string()));
+    }
+
+    #[test]
+    fn test_guard_transcript_without_segments_uses_phrase_list() {
+        let limits = HallucinationThresholds::default();
+        assert_eq!(
+            guard_text(Transcript::from("ok".to_string()), &limits),
+            Err(INAUDIBLE_MSG.to_string())
+        );
+        assert_eq!(
+            guard_text(Transcript::from("Envoie le rapport.".to_string()), &limits),
+            Ok("Envoie le rapport.".to_string())
+        );
+    }
+
+    // ── guard_transcript / dominant_language ──────────────────────
+
+    #[test]
+    fn test_guard_transcript_reports_detected_language() {
+        let limits = HallucinationThresholds::default();
+        let mut t = Transcript::from("Send the report to Paul.".to_string());
+        t.language = Some("english".to_string());
+        let out = guard_transcript(t, &limits, &TranscribeOptions::default()).unwrap();
+        assert_eq!(out.language, Some("en".to_string()));
+    }
+
+    #[test]
+    fn test_guard_transcript_falls_back_to_requested_language() {
+        let limits = HallucinationThresholds::default();
+        let opts = TranscribeOptions {
+            language: Some("fr".to_string()),
+            ..Default::default()
+        };
+        let out = guard_transcript(Transcript::from("Envoie le rapport.".to_string()), &limits, &opts).unwrap();
+        assert_eq!(out.language, Some("fr".to_string()));
+        let auto = guard_transcript(
+            Transcript::from("Envoie le rapport.".to_string()),
+            &limits,
+            &TranscribeOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(auto.language, None);
+    }
+
+    #[test]
+    fn test_dominant_language() {
+        let fr = Some("fr".to_string());
+        let en = Some("en".to_string());
+        assert_eq!(dominant_language(&[en.clone(), fr.clone(), fr.clone()]), fr);
+        assert_eq!(dominant_language(&[None, en.clone(), fr.clone()]), en);
+        assert_eq!(dominant_language(&[None, None]), None);
+    }
+
+    // ── guard_timed ─────────────────────────────────────────────────
+
+    fn word(word: &str, start: f64, end: f64) -> crate::transcription_provider::TimedWord {
+        crate::transcription_provider::TimedWord {
+            word: word.to_string(),
+            start,
+            end,
+        }
     }
 
     #[test]
-    fn test_validate_base_url_invalid_scheme() {
-        assert!(validate_base_url("ftp://example.com").is_err());
-        assert!(validate_base_url("api.openai.com").is_err());
+    fn test_guard_timed_drops_rejected_segments_and_their_words() {
+        let limits = HallucinationThresholds::default();
+        let mut speech = segment(" Bonjour.", 0.02, -0.2, 0.9);
+        speech.end = 1.0;
+        let mut silence = segment(" Merci.", 0.9, -1.5, 0.8);
+        silence.start = 1.0;
+        silence.end = 3.0;
+        let t = Transcript {
+            text: "Bonjour. Merci.".to_string(),
+            segments: Some(vec![speech, silence]),
+            language: None,
+            words: Some(vec![word("Bonjour", 0.1, 0.6), word("Merci", 2.0, 2.4)]),
+        };
+        let out = guard_timed(t, &limits);
+        assert_eq!(out.text, "Bonjour.");
+        assert_eq!(out.segments.map(|s| s.len()), Some(1));
+        assert_eq!(out.words, Some(vec![word("Bonjour", 0.1, 0.6)]));
+    }
+
+    #[test]
+    fn test_guard_timed_without_statistics_uses_phrase_list() {
+        let limits = HallucinationThresholds::default();
+        let mut t = Transcript::from("Sous-titres réalisés par Amara.org".to_string());
+        t.words = Some(vec![word("Sous-titres", 0.0, 0.5)]);
+        let out = guard_timed(t, &limits);
+        assert!(out.text.is_empty());
+        assert_eq!(out.words, None);
     }
 
     #[test]
@@ -0,0 +1,456 @@
+/// Speech-to-text backends behind one trait, looked up by the provider id in `preferences.transcription`.
+use crate::audio::EncodedAudio;
+use crate::http_client;
+use crate::preferences::{FallbackHop, Preferences};
+use futures_util::future::BoxFuture;
+use std::time::Duration;
+
+/// What a backend accepts, so callers only send fields it understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderCapabilities {
+    /// Accepts a `prompt` (dictionary keywords, previous segment tail).
+    pub prompt: bool,
+    /// Accepts a `language` hint.
+    pub language: bool,
+    /// Can answer with `verbose_json` (segments, timings, confidences).
+    pub verbose_json: bool,
+    /// Can return word and segment timings (subtitle export).
+    pub timestamps: bool,
+}
+
+/// Decoder statistics of one `verbose_json` segment.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct SegmentStats {
+    pub text: String,
+    /// Start and end in seconds from the beginning of the uploaded audio.
+    #[serde(default)]
+    pub start: f64,
+    #[serde(default)]
+    pub end: f64,
+    /// Probability that the segment is silence.
+    pub no_speech_prob: f32,
+    pub avg_logprob: f32,
+    /// gzip ratio of the text; high values mean a repetition loop.
+    pub compression_ratio: f32,
+}
+
+/// One word and when it is spoken, in seconds from the beginning of the uploaded audio.
+/// OpenAI returns the words without punctuation; whisper.cpp keeps it attached.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TimedWord {
+    pub word: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Text of one transcribed segment, with Whisper's per-segment statistics when the backend
+/// returned `verbose_json`, and word timings when they were asked for.
+#[derive(Debug, Clone, PartialEq, Default, serde::Deserialize)]
+pub struct Transcript {
+    pub text: String,
+    #[serde(default)]
+    pub segments: Option<Vec<SegmentStats>>,
+    /// Spoken language as reported by the backend ("french" in `verbose_json`), see
+    /// `language_code`.
+    #[serde(default)]
+    pub language: Option<String>,
+    #[serde(default)]
+    pub words: Option<Vec<TimedWord>>,
+}
+
+impl From<String> for Transcript {
+    fn from(text: String) -> Self {
+        Self {
+            text,
+            ..Default::default()
+        }
+    }
+}
+
+/// Whisper language names, as returned in `verbose_json`, for the languages people most
+/// often dictate in.
+const LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("english", "en"), ("french", "fr"), ("german", "de"), ("spanish", "es"),
+    ("italian", "it"), ("portuguese", "pt"), ("dutch", "nl"), ("catalan", "ca"),
+    ("polish", "pl"), ("russian", "ru"), ("ukrainian", "uk"), ("czech", "cs"),
+    ("romanian", "ro"), ("swedish", "sv"), ("danish", "da"), ("norwegian", "no"),
+    ("finnish", "fi"), ("greek", "el"), ("turkish", "tr"), ("arabic", "ar"),
+    ("hebrew", "he"), ("persian", "fa"), ("hindi", "hi"), ("bengali", "bn"),
+    ("chinese", "zh"), ("japanese", "ja"), ("korean", "ko"), ("vietnamese", "vi"),
+    ("thai", "th"), ("indonesian", "id"), ("malay", "ms"), ("tagalog", "tl"),
+    ("hungarian", "hu"), ("bulgarian", "bg"), ("croatian", "hr"), ("serbian", "sr"),
+    ("slovak", "sk"), ("slovenian", "sl"), ("lithuanian", "lt"), ("latvian", "lv"),
+    ("estonian", "et"), ("welsh", "cy"), ("basque", "eu"), ("galician", "gl"),
+    ("breton", "br"), ("occitan", "oc"), ("swahili", "sw"), ("afrikaans", "af"),
+];
+
+/// ISO 639-1 code for a language reported by a backend: codes pass through, Whisper
+/// names are looked up. None for names outside `LANGUAGE_NAMES`.
+pub fn language_code(reported: &str) -> Option<String> {
+    let lower = reported.trim().to_lowercase();
+    if lower.len() == 2 && lower.chars().all(|c| c.is_ascii_lowercase()) {
+        return Some(lower);
+    }
+    LANGUAGE_NAMES
+        .iter()
+        .find(|(name, _)| *name == lower)
+        .map(|(_, code)| code.to_string())
+}
+
+/// Per-request options, independent of the backend.
+#[derive(Debug, Clone, Default)]
+pub struct TranscribeOptions {
+    /// ISO-639-1 code; None or empty lets the backend detect it.
+    pub language: Option<String>,
+    pub prompt: String,
+    pub timeout: Duration,
+    /// Ask for word and segment timings; ignored by backends without `timestamps`.
+    pub timestamps: bool,
+}
+
+pub trait TranscriptionProvider: Send + Sync {
+    /// Registry id, as stored in preferences ("openai", "groq"…).
+    fn id(&self) -> &'static str;
+    /// Human-readable name for logs and error messages.
+    fn name(&self) -> &'static str;
+    /// Model actually requested, for latency statistics.
+    fn model(&self) -> &str;
+    fn capabilities(&self) -> ProviderCapabilities;
+    /// Transcribe one encoded segment; the text is trimmed.
+    fn transcribe<'a>(
+        &'a self,
+        audio: &'a EncodedAudio,
+        opts: &'a TranscribeOptions,
+    ) -> BoxFuture<'a, Result<Transcript, String>>;
+}
+
+/// Any endpoint implementing OpenAI's audio transcription API.
+pub struct OpenAiCompatible {
+    id: &'static str,
+    name: &'static str,
+    /// Without the `/v1/...` suffix.
+    base_url: String,
+    /// Self-hosted servers often need none.
+    api_key: Option<String>,
+    model: String,
+    capabilities: ProviderCapabilities,
+}
+
+impl TranscriptionProvider for OpenAiCompatible {
+    fn id(&self) -> &'static str {
+        self.id
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn capabilities(&self) -> ProviderCapabilities {
+        self.capabilities
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        audio: &'a EncodedAudio,
+        opts: &'a TranscribeOptions,
+    ) -> BoxFuture<'a, Result<Transcript, String>> {
+        Box::pin(async move {
+            let mut form = reqwest::multipart::Form::new()
+                .part("file", audio.multipart_part()?)
+                .text("model", self.model.clone());
+            if self.capabilities.language {
+                if let Some(lang) = opts.language.as_deref().filter(|l| !l.is_empty()) {
+                    form = form.text("language", lang.to_string());
+                }
+            }
+            if self.capabilities.prompt && !opts.prompt.is_empty() {
+                form = form.text("prompt", opts.prompt.clone());
+            }
+            if self.capabilities.verbose_json {
+                // Segment confidences feed the hallucination guard
+                form = form.text("response_format", "verbose_json");
+            }
+            if self.capabilities.timestamps && opts.timestamps {
+                // Words only would drop the segments (and their statistics)
+                form = form
+                    .text("timestamp_granularities[]", "word")
+                    .text("timestamp_granularities[]", "segment");
+            }
+
+            let resp = http_client::post(&self.base_url, "/v1/audio/transcriptions", self.api_key.as_deref())?
+                .multipart(form)
+                .timeout(opts.timeout)
+                .send()
+                .await
+                .map_err(|e| format!("Erreur requête API Whisper ({}): {}", self.name, e))?;
+
+            if !resp.status().is_success() {
+                let status = resp.status();
+                let body = resp.text().await.unwrap_or_default();
+                return Err(format!("{} API error {}: {}", self.name, status, body));
+            }
+
+            let mut out: Transcript = resp.json().await.map_err(|e| e.to_string())?;
+            out.text = out.text.trim().to_string();
+            Ok(out)
+        })
+    }
+}
+
+/// `advanced.transcription_base_url` if set, else the vendor's default; validated.
+fn resolve_base_url(prefs: &Preferences, default: &str) -> Result<String, String> {
+    let url = prefs
+        .advanced
+        .transcription_base_url
+        .as_deref()
+        .unwrap_or(default)
+        .trim_end_matches('/')
+        .to_string();
+    http_client::validate_base_url(&url)?;
+    Ok(url)
+}
+
+/// Whisper-family models accept prompt, language and verbose_json (with timings); the
+/// gpt-4o transcribe models only return plain `json`.
+fn whisper_capabilities(model: &str) -> ProviderCapabilities {
+    let whisper = model.starts_with("whisper");
+    ProviderCapabilities {
+        prompt: true,
+        language: true,
+        verbose_json: whisper,
+        timestamps: whisper,
+    }
+}
+
+fn build_openai(prefs: &Preferences) -> Result<Box<dyn TranscriptionProvider>, String> {
+    let base_url = resolve_base_url(prefs, "https://api.openai.com")?;
+    let key = crate::secrets::get_key_for_provider("openai")
+        .or_else(|_| crate::secrets::get_api_key_cached());
+    let model = prefs.transcription.model.clone();
+    Ok(Box::new(OpenAiCompatible {
+        id: "openai",
+        name: "OpenAI",
+        api_key: http_client::key_unless_local(key, &base_url)?,
+        base_url,
+        capabilities: whisper_capabilities(&model),
+        model,
+    }))
+}
+
+fn build_groq(prefs: &Preferences) -> Result<Box<dyn TranscriptionProvider>, String> {
+    let base_url = resolve_base_url(prefs, "https://api.groq.com/openai")?;
+    let key = crate::secrets::get_key_for_provider("groq")
+        .map_err(|_| "No Groq API key found. Add a Groq key (gsk_...) in Settings → API Keys.".to_string());
+    // Groq supports whisper-large-v3-turbo (fastest) and whisper-large-v3
+    let model = if prefs.transcription.model.starts_with("whisper-large") {
+        prefs.transcription.model.clone()
+    } else {
+        "whisper-large-v3-turbo".to_string()
+    };
+    Ok(Box::new(OpenAiCompatible {
+        id: "groq",
+        name: "Groq",
+        api_key: http_client::key_unless_local(key, &base_url)?,
+        base_url,
+        capabilities: whisper_capabilities(&model),
+        model,
+    }))
+}
+
+/// Self-hosted or third-party server (faster-whisper-server, LocalAI…): base URL from
+/// Advanced settings, model from preferences, optional key stored under "custom".
+fn build_openai_compatible(prefs: &Preferences) -> Result<Box<dyn TranscriptionProvider>, String> {
+    if prefs.advanced.transcription_base_url.is_none() {
+        return Err("The OpenAI-compatible provider needs a transcription base URL (Settings → Advanced).".to_string());
+    }
+    let model = prefs.transcription.model.clone();
+    Ok(Box::new(OpenAiCompatible {
+        id: "openai-compatible",
+        name: "OpenAI-compatible",
+        base_url: resolve_base_url(prefs, "")?,
+        api_key: crate::secrets::get_own_key_for_provider("custom").ok(),
+        capabilities: whisper_capabilities(&model),
+        model,
+    }))
+}
+
+type ProviderBuilder = fn(&Preferences) -> Result<Box<dyn TranscriptionProvider>, String>;
+
+/// Registered providers by id, in display order. The first one is the default.
+const REGISTRY: &[(&str, ProviderBuilder)] = &[
+    ("openai", build_openai),
+    ("groq", build_groq),
+    ("openai-compatible", build_openai_compatible),
+    #[cfg(feature = "local-whisper")]
+    ("local", crate::local_whisper::build_local),
+];
+
+/// Ids accepted in `preferences.transcription.provider`.
+pub fn provider_ids() -> Vec<&'static str> {
+    REGISTRY.iter().map(|(id, _)| *id).collect()
+}
+
+/// Build the provider registered under `id` (unknown ids fall back to OpenAI, as before
+/// the registry existed). Fails when its API key or base URL is missing.
+pub fn provider_for(id: &str, prefs: &Preferences) -> Result<Box<dyn TranscriptionProvider>, String> {
+    let (_, build) = REGISTRY
+        .iter()
+        .find(|(registered, _)| *registered == id)
+        .unwrap_or(&REGISTRY[0]);
+    build(prefs)
+}
+
+/// Build the provider of one fallback hop: its model (OpenAI's default is `whisper-1`, other
+/// providers keep the configured one) and endpoint. Unknown ids are an error here.
+pub fn provider_for_hop(hop: &FallbackHop, prefs: &Preferences) -> Result<Box<dyn TranscriptionProvider>, String> {
+    let (_, build) = REGISTRY
+        .iter()
+        .find(|(registered, _)| *registered == hop.provider)
+        .ok_or_else(|| format!("Unknown transcription provider '{}'", hop.provider))?;
+    let mut hop_prefs = prefs.clone();
+    hop_prefs.transcription.model = match (&hop.model, hop.provider.as_str()) {
+        (Some(model), _) => model.clone(),
+        // The configured model may be another vendor's (Groq's whisper-large-v3-turbo)
+        (None, "openai") => "whisper-1".to_string(),
+        (None, _) => prefs.transcription.model.clone(),
+    };
+    hop_prefs.advanced.transcription_base_url = hop.base_url.clone();
+    build(&hop_prefs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ── registry ────────────────────────────────────────────────────
+
+    #[test]
+    fn test_registry_ids_unique_and_openai_first() {
+        let ids = provider_ids();
+        assert_eq!(ids[0], "openai");
+        let mut dedup = ids.clone();
+        dedup.sort();
+        dedup.dedup();
+        assert_eq!(dedup.len(), ids.len());
+        assert!(ids.contains(&"groq"));
+        assert!(ids.contains(&"openai-compatible"));
+    }
+
+    #[test]
+    fn test_openai_compatible_requires_base_url() {
+        let prefs = Preferences::default();
+        let err = provider_for("openai-compatible", &prefs).err().unwrap();
+        assert!(err.contains("base URL"));
+    }
+
+    #[test]
+    fn test_openai_compatible_uses_configured_endpoint() {
+        let mut prefs = Preferences::default();
+        prefs.advanced.transcription_base_url = Some("https://whisper.example.com/".to_string());
+        let provider = provider_for("openai-compatible", &prefs).unwrap();
+        assert_eq!(provider.id(), "openai-compatible");
+        assert!(provider.capabilities().prompt);
+    }
+
+    #[test]
+    fn test_openai_compatible_rejects_plain_http() {
+        let mut prefs = Preferences::default();
+        prefs.advanced.transcription_base_url = Some("http://whisper.example.com".to_string());
+        assert!(provider_for("openai-compatible", &prefs).is_err());
+    }
+
+    #[test]
+    fn test_loopback_endpoint_needs_no_key() {
+        let mut prefs = Preferences::default();
+        prefs.advanced.transcription_base_url = Some("http://127.0.0.1:8000".to_string());
+        assert!(provider_for("openai-compatible", &prefs).is_ok());
+        assert!(provider_for("openai", &prefs).is_ok());
+    }
+
+    #[test]
+    fn test_hop_uses_its_own_model_and_endpoint() {
+        let mut prefs = Preferences::default();
+        prefs.transcription.model = "whisper-large-v3-turbo".to_string();
+        prefs.advanced.transcription_base_url = Some("http://127.0.0.1:8000".to_string());
+        let hop = FallbackHop {
+            provider: "openai".to_string(),
+            model: None,
+            timeout_secs: None,
+            base_url: Some("http://localhost:9000".to_string()),
+        };
+        let provider = provider_for_hop(&hop, &prefs).unwrap();
+        assert_eq!(provider.model(), "whisper-1");
+        // The primary's base URL does not carry over
+        let hop = FallbackHop { provider: "openai-compatible".to_string(), base_url: None, ..hop };
+        assert!(provider_for_hop(&hop, &prefs).is_err());
+    }
+
+    #[test]
+    fn test_hop_unknown_provider_is_an_error() {
+        let hop = FallbackHop { provider: "deepgram".to_string(), model: None, timeout_secs: None, base_url: None };
+        assert!(provider_for_hop(&hop, &Preferences::default()).is_err());
+    }
+
+    // ── Transcript ──────────────────────────────────────────────────
+
+    #[test]
+    fn test_transcript_parses_verbose_json() {
+        let json = r#"{"task":"transcribe","language":"french","duration":2.1,"text":" Bonjour.",
+            "segments":[{"id":0,"seek":0,"start":0.0,"end":2.1,"text":" Bonjour.","tokens":[1],
+            "temperature":0.0,"avg_logprob":-0.25,"compression_ratio":0.8,"no_speech_prob":0.01}]}"#;
+        let t: Transcript = serde_json::from_str(json).unwrap();
+        let segments = t.segments.unwrap();
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].text, " Bonjour.");
+        assert!((segments[0].avg_logprob + 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_transcript_parses_word_timestamps() {
+        let json = r#"{"text":"Bonjour tout le monde.","words":[{"word":"Bonjour","start":0.0,"end":0.42},
+            {"word":"tout","start":0.42,"end":0.6}],"segments":[{"start":0.0,"end":1.5,"text":" Bonjour tout le monde.",
+            "avg_logprob":-0.2,"compression_ratio":0.9,"no_speech_prob":0.02}]}"#;
+        let t: Transcript = serde_json::from_str(json).unwrap();
+        let words = t.words.unwrap();
+        assert_eq!(words[1], TimedWord { word: "tout".to_string(), start: 0.42, end: 0.6 });
+        assert_eq!(t.segments.unwrap()[0].end, 1.5);
+    }
+
+    #[test]
+    fn test_transcript_parses_detected_language() {
+        let t: Transcript = serde_json::from_str(r#"{"text":"Hello.","language":"english"}"#).unwrap();
+        assert_eq!(t.language.as_deref().and_then(language_code), Some("en".to_string()));
+    }
+
+    #[test]
+    fn test_language_code() {
+        assert_eq!(language_code("French"), Some("fr".to_string()));
+        assert_eq!(language_code("en"), Some("en".to_string()));
+        assert_eq!(language_code(" DE "), Some("de".to_string()));
+        assert_eq!(language_code("klingon"), None);
+    }
+
+    #[test]
+    fn test_transcript_parses_plain_json() {
+        let t: Transcript = serde_json::from_str(r#"{"text":"Bonjour."}"#).unwrap();
+        assert_eq!(t, Transcript::from("Bonjour.".to_string()));
+    }
+
+    // ── capabilities ────────────────────────────────────────────────
+
+    #[test]
+    fn test_whisper_capabilities() {
+        assert!(whisper_capabilities("whisper-1").verbose_json);
+        assert!(whisper_capabilities("whisper-1").timestamps);
+        assert!(whisper_capabilities("whisper-large-v3-turbo").verbose_json);
+        let gpt4o = whisper_capabilities("gpt-4o-transcribe");
+        assert!(!gpt4o.verbose_json);
+        assert!(!gpt4o.timestamps);
+        assert!(gpt4o.prompt && gpt4o.language);
+    }
+}
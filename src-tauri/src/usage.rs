@@ -1,10 +1,156 @@
-/// Suivi d'usage API (requêtes, tokens, coûts estimés)
+/// Suivi d'usage API (requêtes, tokens, coûts estimés, latence par étape)
+use crate::llm::{LlmOutput, TokenUsage};
+use crate::preferences::ModelPrice;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Instant;
 use tauri::Manager;
 
 const USAGE_FILENAME: &str = "usage.json";
 
+/// Durations kept per stage and provider/model; p50/p95 cover the most recent dictations.
+const LATENCY_WINDOW: usize = 100;
+
+/// Wall time of one pipeline stage. Network stages (transcription, LLM) carry the
+/// provider and model that answered, so backends can be compared stage by stage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StageTiming {
+    pub stage: String,
+    pub ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+}
+
+/// Times consecutive pipeline stages with a monotonic clock: each mark closes the stage
+/// running since the previous mark.
+#[derive(Debug, Clone)]
+pub struct StageTimer {
+    started: Instant,
+    last: Instant,
+    timings: Vec<StageTiming>,
+}
+
+impl StageTimer {
+    pub fn start() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            last: now,
+            timings: Vec::new(),
+        }
+    }
+
+    pub fn mark(&mut self, stage: &str) {
+        self.mark_with(stage, None, None);
+    }
+
+    pub fn mark_with(&mut self, stage: &str, provider: Option<String>, model: Option<String>) {
+        let now = Instant::now();
+        self.timings.push(StageTiming {
+            stage: stage.to_string(),
+            ms: now.duration_since(self.last).as_millis() as u64,
+            provider,
+            model,
+        });
+        self.last = now;
+    }
+
+    /// Stages marked so far, then the "total" since `start`.
+    pub fn timings(&self) -> Vec<StageTiming> {
+        let mut timings = self.timings.clone();
+        timings.push(StageTiming {
+            stage: "total".to_string(),
+            ms: self.started.elapsed().as_millis() as u64,
+            provider: None,
+            model: None,
+        });
+        timings
+    }
+}
+
+/// Rolling latency of one stage on one provider/model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StageLatency {
+    pub stage: String,
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Runs recorded since the last reset, beyond the window too.
+    pub count: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    /// Last `LATENCY_WINDOW` durations, oldest first.
+    #[serde(default)]
+    pub recent_ms: Vec<u64>,
+}
+
+impl StageLatency {
+    fn push(&mut self, ms: u64) {
+        self.count += 1;
+        self.recent_ms.push(ms);
+        if self.recent_ms.len() > LATENCY_WINDOW {
+            self.recent_ms.remove(0);
+        }
+        let mut sorted = self.recent_ms.clone();
+        sorted.sort_unstable();
+        self.p50_ms = percentile(&sorted, 50);
+        self.p95_ms = percentile(&sorted, 95);
+    }
+}
+
+/// Nearest-rank percentile of sorted durations; 0 when there are none.
+fn percentile(sorted: &[u64], p: usize) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len() + 99) / 100;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// LLM calls and tokens of one provider/model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LlmModelUsage {
+    pub provider: String,
+    pub model: String,
+    pub requests: u64,
+    pub tokens_input: u64,
+    pub tokens_output: u64,
+    /// Requests whose tokens were estimated locally: the provider reported no usage.
+    #[serde(default)]
+    pub estimated_requests: u64,
+}
+
+/// Transcription uploads and audio of one provider/model.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TranscriptionModelUsage {
+    pub provider: String,
+    pub model: String,
+    /// Uploads answered, partial previews and retried segments included.
+    pub requests: u64,
+    pub audio_secs: f64,
+}
+
+/// Estimated cost of one provider/model; `usd` is None when no price is known for it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CostLine {
+    pub provider: String,
+    pub model: String,
+    /// "transcription" or "llm"
+    pub kind: &'static str,
+    pub usd: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CostBreakdown {
+    pub lines: Vec<CostLine>,
+    /// Sum of the priced lines.
+    pub total_usd: f64,
+}
+
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UsageStats {
     pub transcription_requests: u64,
@@ -15,14 +161,113 @@ pub struct UsageStats {
     pub tokens_output: u64,
     #[serde(default)]
     pub words_generated: u64,
+    /// Dictation pipeline latency by stage and provider/model.
+    #[serde(default)]
+    pub latency: Vec<StageLatency>,
+    /// LLM tokens by provider/model; the totals above cover all of them.
+    #[serde(default)]
+    pub llm_models: Vec<LlmModelUsage>,
+    /// Audio sent for transcription by provider/model.
+    #[serde(default)]
+    pub transcription_models: Vec<TranscriptionModelUsage>,
 }
 
 impl UsageStats {
-    #[allow(dead_code)]
-    pub fn estimated_cost_usd(&self) -> f64 {
-        let llm_input = (self.tokens_input as f64 / 1_000_000.0) * 0.15;
-        let llm_output = (self.tokens_output as f64 / 1_000_000.0) * 0.60;
-        (llm_input + llm_output).max(0.0)
+    /// Cost per provider/model at `pricing::price_for` prices, `overrides` from
+    /// `advanced.pricing`. Tokens counted before per-model tracking are left out.
+    pub fn cost_breakdown(&self, overrides: &[ModelPrice]) -> CostBreakdown {
+        let transcription = self.transcription_models.iter().map(|m| CostLine {
+            provider: m.provider.clone(),
+            model: m.model.clone(),
+            kind: "transcription",
+            usd: crate::pricing::price_for(&m.provider, &m.model, overrides)
+                .map(|price| m.audio_secs / 60.0 * price.per_minute),
+        });
+        let llm = self.llm_models.iter().map(|m| CostLine {
+            provider: m.provider.clone(),
+            model: m.model.clone(),
+            kind: "llm",
+            usd: crate::pricing::price_for(&m.provider, &m.model, overrides).map(|price| {
+                (m.tokens_input as f64 * price.input_per_mtok
+                    + m.tokens_output as f64 * price.output_per_mtok)
+                    / 1_000_000.0
+            }),
+        });
+        let lines: Vec<CostLine> = transcription.chain(llm).collect();
+        let total_usd = lines.iter().filter_map(|l| l.usd).sum::<f64>().max(0.0);
+        CostBreakdown { lines, total_usd }
+    }
+
+    pub fn estimated_cost_usd(&self, overrides: &[ModelPrice]) -> f64 {
+        self.cost_breakdown(overrides).total_usd
+    }
+
+    pub fn record_transcription(&mut self, provider: &str, model: &str, audio_secs: f64) {
+        let index = self
+            .transcription_models
+            .iter()
+            .position(|m| m.provider == provider && m.model == model);
+        let entry = match index {
+            Some(i) => &mut self.transcription_models[i],
+            None => {
+                self.transcription_models.push(TranscriptionModelUsage {
+                    provider: provider.to_string(),
+                    model: model.to_string(),
+                    ..Default::default()
+                });
+                self.transcription_models.last_mut().unwrap()
+            }
+        };
+        entry.requests += 1;
+        entry.audio_secs += audio_secs;
+    }
+
+    pub fn record_llm(&mut self, provider: &str, model: &str, usage: TokenUsage) {
+        self.llm_requests += 1;
+        self.tokens_input += usage.input;
+        self.tokens_output += usage.output;
+        let index = self
+            .llm_models
+            .iter()
+            .position(|m| m.provider == provider && m.model == model);
+        let entry = match index {
+            Some(i) => &mut self.llm_models[i],
+            None => {
+                self.llm_models.push(LlmModelUsage {
+                    provider: provider.to_string(),
+                    model: model.to_string(),
+                    ..Default::default()
+                });
+                self.llm_models.last_mut().unwrap()
+            }
+        };
+        entry.requests += 1;
+        entry.tokens_input += usage.input;
+        entry.tokens_output += usage.output;
+        if usage.estimated {
+            entry.estimated_requests += 1;
+        }
+    }
+
+    pub fn record_timings(&mut self, timings: &[StageTiming]) {
+        for timing in timings {
+            let index = self.latency.iter().position(|l| {
+                l.stage == timing.stage && l.provider == timing.provider && l.model == timing.model
+            });
+            let entry = match index {
+                Some(i) => &mut self.latency[i],
+                None => {
+                    self.latency.push(StageLatency {
+                        stage: timing.stage.clone(),
+                        provider: timing.provider.clone(),
+                        model: timing.model.clone(),
+                        ..Default::default()
+                    });
+                    self.latency.last_mut().unwrap()
+                }
+            };
+            entry.push(timing.ms);
+        }
     }
 }
 
@@ -50,30 +295,44 @@ fn save_to_file(path: &std::path::Path, stats: &UsageStats) {
     );
 }
 
-pub fn increment_transcription(app: &tauri::AppHandle) {
+/// Serializes usage.json read-modify-write cycles: live partials, the final pass and the
+/// LLM call record from overlapping tasks.
+static USAGE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Apply `change` to the stored statistics under `USAGE_LOCK`.
+fn update(app: &tauri::AppHandle, change: impl FnOnce(&mut UsageStats)) {
     if let Ok(path) = usage_path(app) {
+        let _guard = USAGE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
         let mut stats = load_from_file(&path);
-        stats.transcription_requests += 1;
+        change(&mut stats);
         save_to_file(&path, &stats);
     }
 }
 
-pub fn increment_llm(app: &tauri::AppHandle, tokens_input: u64, tokens_output: u64) {
-    if let Ok(path) = usage_path(app) {
-        let mut stats = load_from_file(&path);
-        stats.llm_requests += 1;
-        stats.tokens_input += tokens_input;
-        stats.tokens_output += tokens_output;
-        save_to_file(&path, &stats);
-    }
+pub fn increment_transcription(app: &tauri::AppHandle) {
+    update(app, |stats| stats.transcription_requests += 1);
+}
+
+/// Count one answered transcription upload and its audio duration. Dictations are
+/// counted by `increment_transcription`.
+pub fn record_transcription(app: &tauri::AppHandle, provider: &str, model: &str, audio_secs: f64) {
+    update(app, |stats| stats.record_transcription(provider, model, audio_secs));
+}
+
+/// Count one LLM call and its tokens, under the provider/model that answered.
+pub fn record_llm(app: &tauri::AppHandle, output: &LlmOutput) {
+    update(app, |stats| {
+        stats.record_llm(&output.provider, &output.model, output.usage.unwrap_or_default())
+    });
 }
 
 pub fn increment_words(app: &tauri::AppHandle, words: u64) {
-    if let Ok(path) = usage_path(app) {
-        let mut stats = load_from_file(&path);
-        stats.words_generated += words;
-        save_to_file(&path, &stats);
-    }
+    update(app, |stats| stats.words_generated += words);
+}
+
+/// Add one dictation's stage timings to the rolling latency statistics.
+pub fn record_timings(app: &tauri::AppHandle, timings: &[StageTiming]) {
+    update(app, |stats| stats.record_timings(timings));
 }
 
 pub fn get_usage_stats(app: &tauri::AppHandle) -> UsageStats {
@@ -87,6 +346,7 @@ pub fn reset_usage_stats(app: &tauri::AppHandle) -> UsageStats {
         Ok(p) => p,
         Err(_) => return UsageStats::default(),
     };
+    let _guard = USAGE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
     let prev = load_from_file(&path);
     save_to_file(&path, &UsageStats::default());
     prev
@@ -112,19 +372,14 @@ mod tests {
     #[test]
     fn test_estimated_cost_zero_for_defaults() {
         let stats = UsageStats::default();
-        assert!((stats.estimated_cost_usd() - 0.0).abs() < f64::EPSILON);
+        assert!((stats.estimated_cost_usd(&[]) - 0.0).abs() < f64::EPSILON);
     }
 
     #[test]
     fn test_estimated_cost_with_tokens() {
-        let stats = UsageStats {
-            transcription_requests: 10,
-            llm_requests: 5,
-            tokens_input: 1_000_000,
-            tokens_output: 1_000_000,
-            ..Default::default()
-        };
-        let cost = stats.estimated_cost_usd();
+        let mut stats = UsageStats::default();
+        stats.record_llm("openai", "gpt-4o-mini", TokenUsage { input: 1_000_000, output: 1_000_000, estimated: false });
+        let cost = stats.estimated_cost_usd(&[]);
         // Input: 1M * 0.15 / 1M = 0.15
         // Output: 1M * 0.60 / 1M = 0.60
         // Total: 0.75
@@ -140,7 +395,130 @@ mod tests {
             tokens_output: 0,
             ..Default::default()
         };
-        assert!(stats.estimated_cost_usd() >= 0.0);
+        assert!(stats.estimated_cost_usd(&[]) >= 0.0);
+    }
+
+    // ── cost_breakdown ──────────────────────────────────────────────
+
+    #[test]
+    fn test_cost_breakdown_prices_each_model() {
+        let mut stats = UsageStats::default();
+        stats.record_transcription("openai", "whisper-1", 90.0);
+        stats.record_transcription("openai", "whisper-1", 30.0);
+        stats.record_llm("groq", "llama-3.1-8b-instant", TokenUsage { input: 2_000_000, output: 1_000_000, estimated: false });
+        let costs = stats.cost_breakdown(&[]);
+        assert_eq!(costs.lines.len(), 2);
+        assert_eq!(costs.lines[0].kind, "transcription");
+        // 2 min * $0.006
+        assert!((costs.lines[0].usd.unwrap() - 0.012).abs() < 1e-9);
+        // 2M * 0.05 + 1M * 0.08
+        assert!((costs.lines[1].usd.unwrap() - 0.18).abs() < 1e-9);
+        assert!((costs.total_usd - 0.192).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cost_breakdown_unpriced_and_overridden() {
+        let mut stats = UsageStats::default();
+        stats.record_transcription("openai-compatible", "large-v3", 600.0);
+        stats.record_transcription("openai", "whisper-1", 60.0);
+        let costs = stats.cost_breakdown(&[]);
+        assert_eq!(costs.lines[0].usd, None);
+        assert!((costs.total_usd - 0.006).abs() < 1e-9);
+
+        let overrides = [ModelPrice {
+            provider: "openai-compatible".to_string(),
+            model: "*".to_string(),
+            per_minute: 0.001,
+            ..Default::default()
+        }];
+        assert!((stats.estimated_cost_usd(&overrides) - 0.016).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_record_transcription_accumulates_audio() {
+        let mut stats = UsageStats::default();
+        stats.record_transcription("groq", "whisper-large-v3-turbo", 12.5);
+        stats.record_transcription("groq", "whisper-large-v3-turbo", 7.5);
+        assert_eq!(stats.transcription_models.len(), 1);
+        assert_eq!(stats.transcription_models[0].requests, 2);
+        assert!((stats.transcription_models[0].audio_secs - 20.0).abs() < 1e-9);
+        // Dictations are counted separately
+        assert_eq!(stats.transcription_requests, 0);
+    }
+
+    // ── record_llm ──────────────────────────────────────────────────
+
+    #[test]
+    fn test_record_llm_totals_and_per_model() {
+        let mut stats = UsageStats::default();
+        let reported = TokenUsage { input: 100, output: 20, estimated: false };
+        stats.record_llm("openai", "gpt-4o-mini", reported);
+        stats.record_llm("openai", "gpt-4o-mini", reported);
+        stats.record_llm("groq", "llama-3.1-8b-instant", TokenUsage { input: 50, output: 10, estimated: true });
+        assert_eq!(stats.llm_requests, 3);
+        assert_eq!(stats.tokens_input, 250);
+        assert_eq!(stats.tokens_output, 50);
+        assert_eq!(stats.llm_models.len(), 2);
+        assert_eq!(stats.llm_models[0].requests, 2);
+        assert_eq!(stats.llm_models[0].tokens_input, 200);
+        assert_eq!(stats.llm_models[0].estimated_requests, 0);
+        assert_eq!(stats.llm_models[1].estimated_requests, 1);
+    }
+
+    // ── latency ─────────────────────────────────────────────────────
+
+    fn timing(stage: &str, provider: Option<&str>, ms: u64) -> StageTiming {
+        StageTiming {
+            stage: stage.to_string(),
+            ms,
+            provider: provider.map(str::to_string),
+            model: provider.map(|_| "whisper-large-v3-turbo".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_percentile_nearest_rank() {
+        let sorted: Vec<u64> = (1..=20).collect();
+        assert_eq!(percentile(&sorted, 50), 10);
+        assert_eq!(percentile(&sorted, 95), 19);
+        assert_eq!(percentile(&[7], 95), 7);
+        assert_eq!(percentile(&[], 50), 0);
+    }
+
+    #[test]
+    fn test_record_timings_keys_by_stage_and_provider() {
+        let mut stats = UsageStats::default();
+        stats.record_timings(&[timing("transcription", Some("groq"), 400), timing("paste", None, 30)]);
+        stats.record_timings(&[timing("transcription", Some("groq"), 600)]);
+        stats.record_timings(&[timing("transcription", Some("openai"), 900)]);
+        assert_eq!(stats.latency.len(), 3);
+        let groq = &stats.latency[0];
+        assert_eq!((groq.count, groq.p50_ms, groq.p95_ms), (2, 400, 600));
+        assert_eq!(stats.latency[2].provider.as_deref(), Some("openai"));
+    }
+
+    #[test]
+    fn test_record_timings_keeps_rolling_window() {
+        let mut stats = UsageStats::default();
+        for ms in 0..(LATENCY_WINDOW as u64 + 50) {
+            stats.record_timings(&[timing("llm", None, ms)]);
+        }
+        let llm = &stats.latency[0];
+        assert_eq!(llm.count, LATENCY_WINDOW as u64 + 50);
+        assert_eq!(llm.recent_ms.len(), LATENCY_WINDOW);
+        assert_eq!(llm.recent_ms[0], 50);
+        assert_eq!(llm.p50_ms, 99);
+    }
+
+    #[test]
+    fn test_stage_timer_marks_in_order_and_adds_total() {
+        let mut timer = StageTimer::start();
+        timer.mark("encoding");
+        timer.mark_with("transcription", Some("groq".to_string()), None);
+        let timings = timer.timings();
+        let stages: Vec<&str> = timings.iter().map(|t| t.stage.as_str()).collect();
+        assert_eq!(stages, vec!["encoding", "transcription", "total"]);
+        assert!(timings[2].ms >= timings[0].ms + timings[1].ms);
     }
 
     // ── Serialization roundtrip ─────────────────────────────────────
@@ -18,10 +18,9 @@ pub struct VoiceCommandResult {
     pub commands: Vec<VoiceCommand>,
 }
 
-/// Command phrase definitions: (phrase, command).
+/// Command phrase definitions: (phrase, command), one list per language.
 /// Order matters — longer phrases first to avoid partial matches.
-const COMMAND_PHRASES: &[(&str, VoiceCommand)] = &[
-    // English
+const COMMAND_PHRASES_EN: &[(&str, VoiceCommand)] = &[
     ("press enter", VoiceCommand::Enter),
     ("new line", VoiceCommand::NewLine),
     ("press tab", VoiceCommand::Tab),
@@ -29,7 +28,9 @@ const COMMAND_PHRASES: &[(&str, VoiceCommand)] = &[
     ("backspace", VoiceCommand::Backspace),
     ("undo", VoiceCommand::Undo),
     ("escape", VoiceCommand::Escape),
-    // French
+];
+
+const COMMAND_PHRASES_FR: &[(&str, VoiceCommand)] = &[
     ("appuie sur entrée", VoiceCommand::Enter),
     ("appuie sur entree", VoiceCommand::Enter),
     ("entrée", VoiceCommand::Enter),
@@ -50,11 +51,22 @@ const COMMAND_PHRASES: &[(&str, VoiceCommand)] = &[
     ("supprime", VoiceCommand::Backspace),
 ];
 
+/// Phrases of the detected language (ISO 639-1); every list when it is unknown, so an
+/// English "undo" isn't stripped from a French sentence once the language is known.
+fn phrases_for(language: Option<&str>) -> Vec<&'static (&'static str, VoiceCommand)> {
+    match language {
+        Some("en") => COMMAND_PHRASES_EN.iter().collect(),
+        Some("fr") => COMMAND_PHRASES_FR.iter().collect(),
+        _ => COMMAND_PHRASES_EN.iter().chain(COMMAND_PHRASES_FR.iter()).collect(),
+    }
+}
+
 /// Scans text for command phrases at word boundaries, removes them,
 /// and returns cleaned text + ordered list of commands.
 /// Commands are matched case-insensitively and must be at word boundaries
 /// (preceded by whitespace/start-of-string, followed by whitespace/punctuation/end-of-string).
-pub fn extract_commands(text: &str) -> VoiceCommandResult {
+pub fn extract_commands(text: &str, language: Option<&str>) -> VoiceCommandResult {
+    let phrases = phrases_for(language);
     let mut working = text.to_string();
     let mut commands: Vec<(usize, VoiceCommand)> = Vec::new();
 
@@ -64,7 +76,7 @@ pub fn extract_commands(text: &str) -> VoiceCommandResult {
         let lower = working.to_lowercase();
         let mut found = false;
 
-        for (phrase, cmd) in COMMAND_PHRASES {
+        for (phrase, cmd) in phrases.iter().copied() {
             if let Some(pos) = find_command_at_boundary(&lower, phrase) {
                 let end = pos + phrase.len();
                 // Also strip surrounding whitespace/punctuation
@@ -285,14 +297,14 @@ mod tests {
 
     #[test]
     fn test_extract_enter() {
-        let r = extract_commands("Hello world press enter");
+        let r = extract_commands("Hello world press enter", None);
         assert_eq!(r.cleaned_text, "Hello world");
         assert_eq!(r.commands, vec![VoiceCommand::Enter]);
     }
 
     #[test]
     fn test_extract_french_enter() {
-        let r = extract_commands("Bonjour entrée");
+        let r = extract_commands("Bonjour entrée", None);
         assert_eq!(r.cleaned_text, "Bonjour");
         assert_eq!(r.commands, vec![VoiceCommand::Enter]);
     }
@@ -300,36 +312,47 @@ mod tests {
     #[test]
     fn test_no_false_positive_in_word() {
         // "enter" inside "enterprise" should NOT match
-        let r = extract_commands("The enterprise is great");
+        let r = extract_commands("The enterprise is great", None);
         assert_eq!(r.cleaned_text, "The enterprise is great");
         assert!(r.commands.is_empty());
     }
 
     #[test]
     fn test_multiple_commands() {
-        let r = extract_commands("Hello new line world press enter");
+        let r = extract_commands("Hello new line world press enter", None);
         assert_eq!(r.cleaned_text, "Hello world");
         assert_eq!(r.commands, vec![VoiceCommand::NewLine, VoiceCommand::Enter]);
     }
 
     #[test]
     fn test_case_insensitive() {
-        let r = extract_commands("Hello Press Enter");
+        let r = extract_commands("Hello Press Enter", None);
         assert_eq!(r.cleaned_text, "Hello");
         assert_eq!(r.commands, vec![VoiceCommand::Enter]);
     }
 
     #[test]
     fn test_command_only() {
-        let r = extract_commands("press enter");
+        let r = extract_commands("press enter", None);
         assert_eq!(r.cleaned_text, "");
         assert_eq!(r.commands, vec![VoiceCommand::Enter]);
     }
 
     #[test]
     fn test_with_punctuation() {
-        let r = extract_commands("Hello, press enter.");
+        let r = extract_commands("Hello, press enter.", None);
         assert_eq!(r.cleaned_text, "Hello");
         assert_eq!(r.commands, vec![VoiceCommand::Enter]);
     }
+
+    #[test]
+    fn test_extract_respects_detected_language() {
+        let fr = extract_commands("Bonjour entrée", Some("en"));
+        assert_eq!(fr.cleaned_text, "Bonjour entrée");
+        assert!(fr.commands.is_empty());
+        let en = extract_commands("Hello world press enter", Some("en"));
+        assert_eq!(en.commands, vec![VoiceCommand::Enter]);
+        let other = extract_commands("Hola press enter", Some("es"));
+        assert_eq!(other.commands, vec![VoiceCommand::Enter]);
+    }
 }
@@ -0,0 +1,218 @@
+/// Whisper prompt: dictionary terms framed in the target language, then the text the
+/// audio continues (previous segment, text before the cursor, or the last output).
+///
+/// Whisper only keeps the last ~224 tokens of the prompt and conditions most on its end,
+/// so the context tail goes last and the terms fill what is left of the budget, most
+/// recently used first.
+use crate::audio::RecorderState;
+use tauri::Manager;
+
+/// Prompt tokens Whisper keeps (`n_text_ctx / 2`); anything before is dropped.
+const PROMPT_TOKEN_BUDGET: usize = 224;
+
+/// How much of the preceding text is fed back as prompt.
+pub const PROMPT_TAIL_CHARS: usize = 200;
+
+/// Framing sentence per language. A sentence conditions the decoder much more than a
+/// bare list, but in the wrong language it pulls the transcript toward that language.
+const FRAMINGS: &[(&str, &str)] = &[
+    ("fr", "Mots-clés utilisés :"),
+    ("en", "Keywords used:"),
+    ("de", "Verwendete Begriffe:"),
+    ("es", "Palabras clave utilizadas:"),
+    ("it", "Parole chiave utilizzate:"),
+    ("pt", "Palavras-chave utilizadas:"),
+    ("nl", "Gebruikte trefwoorden:"),
+];
+
+/// Framing for `language`; none when it is detected (or has no entry) — the bare list
+/// then leaves language detection alone.
+fn framing(language: Option<&str>) -> Option<&'static str> {
+    let language = language?;
+    FRAMINGS
+        .iter()
+        .find(|(code, _)| language.eq_ignore_ascii_case(code))
+        .map(|(_, sentence)| *sentence)
+}
+
+/// Rough token count: Whisper's BPE averages 3-4 characters per token on Latin text,
+/// accented and rare words (exactly what the dictionary holds) cost more.
+fn approx_tokens(text: &str) -> usize {
+    (text.chars().count() + 2) / 3
+}
+
+/// Build the prompt from dictionary `terms` (priority order) and the text the audio
+/// continues. Only correct spellings are given: misspellings in the prompt teach
+/// Whisper to *produce* them.
+pub fn build(terms: &[String], previous_text: Option<&str>, language: Option<&str>) -> String {
+    let tail = previous_text.map(prompt_tail).unwrap_or_default();
+    let framing = framing(language);
+    let mut budget = PROMPT_TOKEN_BUDGET
+        .saturating_sub(approx_tokens(&tail))
+        .saturating_sub(framing.map_or(0, approx_tokens) + 1);
+
+    let mut seen = std::collections::HashSet::new();
+    let mut words: Vec<&str> = Vec::new();
+    for term in terms {
+        let term = term.trim();
+        if term.is_empty() || !seen.insert(term.to_lowercase()) {
+            continue;
+        }
+        // ", " separator included
+        let cost = approx_tokens(term) + 1;
+        if cost > budget {
+            break;
+        }
+        budget -= cost;
+        words.push(term);
+    }
+
+    let keywords = match (words.is_empty(), framing) {
+        (true, _) => String::new(),
+        (false, Some(framing)) => format!("{} {}.", framing, words.join(", ")),
+        (false, None) => format!("{}.", words.join(", ")),
+    };
+    match (keywords.is_empty(), tail.is_empty()) {
+        (_, true) => keywords,
+        (true, false) => tail,
+        (false, false) => format!("{} {}", keywords, tail),
+    }
+}
+
+/// Last `PROMPT_TAIL_CHARS` characters of `text`, starting on a word boundary.
+pub fn prompt_tail(text: &str) -> String {
+    let text = text.trim();
+    let count = text.chars().count();
+    if count <= PROMPT_TAIL_CHARS {
+        return text.to_string();
+    }
+    let start = text
+        .char_indices()
+        .nth(count - PROMPT_TAIL_CHARS)
+        .map(|(i, _)| i)
+        .unwrap_or(0);
+    let tail = &text[start..];
+    match tail.find(char::is_whitespace) {
+        Some(space) => tail[space..].trim_start().to_string(),
+        None => tail.to_string(),
+    }
+}
+
+/// Text a new dictation continues: what precedes the cursor in the focused field when it
+/// was read at recording start (`transcription.cursor_context`), else the last output —
+/// only when it was inserted into the app this take is dictated in, so nothing from another
+/// app or conversation leaks into the prompt. An empty field means a fresh start.
+pub fn dictation_context(app: &tauri::AppHandle) -> Option<String> {
+    let recorder = app.try_state::<RecorderState>();
+    let context = match recorder.as_ref().and_then(|state| state.cursor_context()) {
+        Some(text) => text,
+        None => {
+            let output = app
+                .try_state::<crate::LastOutputState>()
+                .and_then(|state| state.0.lock().ok().and_then(|g| g.clone()));
+            let output_app = app
+                .try_state::<crate::LastOutputApp>()
+                .and_then(|state| state.0.lock().ok().and_then(|g| g.clone()));
+            let take_app = recorder.and_then(|state| state.previous_app());
+            same_app_output(output, output_app.as_deref(), take_app.as_deref())?
+        }
+    };
+    Some(prompt_tail(&context)).filter(|tail| !tail.is_empty())
+}
+
+/// `output` when it went into the app the take is dictated in (both known).
+fn same_app_output(output: Option<String>, output_app: Option<&str>, take_app: Option<&str>) -> Option<String> {
+    match (output_app, take_app) {
+        (Some(inserted_into), Some(current)) if inserted_into == current => output,
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(words: &[&str]) -> Vec<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    // ── build ───────────────────────────────────────────────────────
+
+    #[test]
+    fn test_build_dictionary_only() {
+        assert_eq!(build(&terms(&["Tauri"]), None, Some("fr")), "Mots-clés utilisés : Tauri.");
+        assert_eq!(build(&[], None, Some("fr")), "");
+    }
+
+    #[test]
+    fn test_build_appends_previous_tail() {
+        assert_eq!(
+            build(&terms(&["Tauri"]), Some("on a fini la revue."), Some("fr")),
+            "Mots-clés utilisés : Tauri. on a fini la revue."
+        );
+        assert_eq!(build(&[], Some("  suite  "), Some("fr")), "suite");
+    }
+
+    #[test]
+    fn test_build_framing_follows_language() {
+        assert_eq!(build(&terms(&["Tauri", "Whisper"]), None, Some("en")), "Keywords used: Tauri, Whisper.");
+        // Detected language, or one without a framing: bare list
+        assert_eq!(build(&terms(&["Tauri"]), None, None), "Tauri.");
+        assert_eq!(build(&terms(&["Tauri"]), None, Some("ja")), "Tauri.");
+    }
+
+    #[test]
+    fn test_build_dedupes_terms_case_insensitively() {
+        assert_eq!(build(&terms(&["Tauri", " tauri ", ""]), None, None), "Tauri.");
+    }
+
+    #[test]
+    fn test_build_keeps_first_terms_within_budget() {
+        let many: Vec<String> = (0..200).map(|i| format!("Terme{:03}", i)).collect();
+        let context = "mot ".repeat(100);
+        let prompt = build(&many, Some(&context), Some("fr"));
+        assert!(approx_tokens(&prompt) <= PROMPT_TOKEN_BUDGET);
+        assert!(prompt.contains("Terme000"));
+        assert!(!prompt.contains("Terme199"));
+        // The context tail is never the part cut
+        assert!(prompt.ends_with(&prompt_tail(&context)));
+    }
+
+    // ── prompt_tail ─────────────────────────────────────────────────
+
+    #[test]
+    fn test_prompt_tail_short_text_unchanged() {
+        assert_eq!(prompt_tail("bonjour à tous"), "bonjour à tous");
+    }
+
+    #[test]
+    fn test_prompt_tail_starts_on_word_boundary() {
+        let text = "mot ".repeat(100) + "fin.";
+        let tail = prompt_tail(&text);
+        assert!(tail.chars().count() <= PROMPT_TAIL_CHARS);
+        assert!(tail.starts_with("mot "));
+        assert!(tail.ends_with("fin."));
+    }
+
+    #[test]
+    fn test_prompt_tail_multibyte() {
+        let text = "é".repeat(PROMPT_TAIL_CHARS + 50);
+        // No whitespace to cut on: keep the raw tail, sliced on a char boundary
+        assert_eq!(prompt_tail(&text).chars().count(), PROMPT_TAIL_CHARS);
+    }
+
+    // ── same_app_output ─────────────────────────────────────────────
+
+    #[test]
+    fn test_last_output_only_continues_the_same_app() {
+        let output = || Some("Réunion reportée à jeudi.".to_string());
+        assert_eq!(
+            same_app_output(output(), Some("com.tinyspeck.slackmacgap"), Some("com.tinyspeck.slackmacgap")),
+            output()
+        );
+        assert_eq!(same_app_output(output(), Some("com.tinyspeck.slackmacgap"), Some("com.apple.mail")), None);
+        // Only copied, or the take's app is unknown
+        assert_eq!(same_app_output(output(), None, Some("com.apple.mail")), None);
+        assert_eq!(same_app_output(output(), Some("com.apple.mail"), None), None);
+    }
+}
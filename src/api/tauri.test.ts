@@ -211,6 +211,14 @@ describe("tauriApi", () => {
       expect(result).toEqual(stats);
     });
 
+    it("should call get_usage_costs", async () => {
+      const costs = { lines: [{ provider: "openai", model: "whisper-1", kind: "transcription", usd: 0.006 }], total_usd: 0.006 };
+      mockInvoke.mockResolvedValueOnce(costs);
+      const result = await api.usage.getCosts();
+      expect(mockInvoke).toHaveBeenCalledWith("get_usage_costs");
+      expect(result).toEqual(costs);
+    });
+
     it("should call reset_usage_stats", async () => {
       mockInvoke.mockResolvedValueOnce(undefined);
       await api.usage.reset();
@@ -225,6 +233,79 @@ describe("tauriApi", () => {
       expect(mockInvoke).toHaveBeenCalledWith("list_audio_input_devices");
       expect(result).toHaveLength(1);
     });
+
+    it("should call list_transcription_providers", async () => {
+      mockInvoke.mockResolvedValueOnce(["openai", "groq", "openai-compatible"]);
+      const result = await api.audio.listTranscriptionProviders();
+      expect(mockInvoke).toHaveBeenCalledWith("list_transcription_providers");
+      expect(result).toContain("openai-compatible");
+    });
+
+    it("should call transcribe_file with path", async () => {
+      mockInvoke.mockResolvedValueOnce({ output: "Bonjour", thoughts: null, mode: null, pasted: false });
+      const result = await api.audio.transcribeFile("/tmp/memo.ogg");
+      expect(mockInvoke).toHaveBeenCalledWith("transcribe_file", { path: "/tmp/memo.ogg" });
+      expect(result.output).toBe("Bonjour");
+    });
+
+    it("should call export_subtitles with path and format", async () => {
+      mockInvoke.mockResolvedValueOnce("WEBVTT\n\n");
+      const result = await api.audio.exportSubtitles("/tmp/demo.mp3", "vtt");
+      expect(mockInvoke).toHaveBeenCalledWith("export_subtitles", { path: "/tmp/demo.mp3", format: "vtt" });
+      expect(result).toBe("WEBVTT\n\n");
+    });
+  });
+
+  describe("localModels", () => {
+    it("should call list_local_models", async () => {
+      mockInvoke.mockResolvedValueOnce([{ id: "base", sizeMb: 142, downloaded: true }]);
+      const result = await api.localModels.list();
+      expect(mockInvoke).toHaveBeenCalledWith("list_local_models");
+      expect(result[0].downloaded).toBe(true);
+    });
+
+    it("should call download_local_model with id", async () => {
+      mockInvoke.mockResolvedValueOnce(undefined);
+      await api.localModels.download("small");
+      expect(mockInvoke).toHaveBeenCalledWith("download_local_model", { id: "small" });
+    });
+
+    it("should call delete_local_model with id", async () => {
+      mockInvoke.mockResolvedValueOnce(undefined);
+      await api.localModels.delete("small");
+      expect(mockInvoke).toHaveBeenCalledWith("delete_local_model", { id: "small" });
+    });
+  });
+
+  describe("archive", () => {
+    it("should call list_recordings", async () => {
+      mockInvoke.mockResolvedValueOnce([]);
+      await api.archive.list();
+      expect(mockInvoke).toHaveBeenCalledWith("list_recordings");
+    });
+
+    it("should call delete_recording with id", async () => {
+      mockInvoke.mockResolvedValueOnce(undefined);
+      await api.archive.delete("rec-1");
+      expect(mockInvoke).toHaveBeenCalledWith("delete_recording", { id: "rec-1" });
+    });
+
+    it("should call retranscribe_recording with overrides", async () => {
+      mockInvoke.mockResolvedValueOnce({ output: "ok", thoughts: null, mode: null, pasted: false });
+      await api.archive.retranscribe("rec-1", { provider: "openai" });
+      expect(mockInvoke).toHaveBeenCalledWith("retranscribe_recording", {
+        id: "rec-1",
+        provider: "openai",
+        language: null,
+        modeId: null,
+      });
+    });
+
+    it("should call export_recording_subtitles", async () => {
+      mockInvoke.mockResolvedValueOnce("1\n00:00:00,000 --> 00:00:01,000\nBonjour.\n\n");
+      await api.archive.exportSubtitles("rec-1", "srt");
+      expect(mockInvoke).toHaveBeenCalledWith("export_recording_subtitles", { id: "rec-1", format: "srt" });
+    });
   });
 
   describe("services", () => {
@@ -254,6 +335,18 @@ describe("tauriApi", () => {
       expect(mockInvoke).toHaveBeenCalledWith("stop_recording");
     });
 
+    it("should call pause_recording", async () => {
+      mockInvoke.mockResolvedValueOnce(undefined);
+      await api.recording.pause();
+      expect(mockInvoke).toHaveBeenCalledWith("pause_recording");
+    });
+
+    it("should call resume_recording", async () => {
+      mockInvoke.mockResolvedValueOnce(undefined);
+      await api.recording.resume();
+      expect(mockInvoke).toHaveBeenCalledWith("resume_recording");
+    });
+
     it("should call cancel_transcription", async () => {
       mockInvoke.mockResolvedValueOnce(undefined);
       await api.recording.cancel();
@@ -11,8 +11,13 @@ import type {
   ShortcutConfig,
   DictionaryEntry,
   UsageStats,
+  CostBreakdown,
   WordCandidate,
   Snippet,
+  TranscriptionResult,
+  RecordingMeta,
+  LocalModelStatus,
+  SubtitleFormat,
 } from "@/types";
 
 export const tauriApi = {
@@ -105,6 +110,7 @@ export const tauriApi = {
 
   usage: {
     getStats: (): Promise<UsageStats> => invoke("get_usage_stats"),
+    getCosts: (): Promise<CostBreakdown> => invoke("get_usage_costs"),
     reset: (): Promise<void> => invoke("reset_usage_stats"),
   },
 
@@ -115,6 +121,39 @@ export const tauriApi = {
   audio: {
     listInputDevices: (): Promise<Array<{ id: string; name: string }>> =>
       invoke("list_audio_input_devices"),
+    /** Ids accepted in `transcription.provider`. */
+    listTranscriptionProviders: (): Promise<string[]> => invoke("list_transcription_providers"),
+    /** WAV, FLAC, MP3 or OGG file → transcription + active mode; nothing is pasted. */
+    transcribeFile: (path: string): Promise<TranscriptionResult> =>
+      invoke("transcribe_file", { path }),
+    /** Transcribes the file again with word timings; returns the subtitle file content. */
+    exportSubtitles: (path: string, format: SubtitleFormat): Promise<string> =>
+      invoke("export_subtitles", { path, format }),
+  },
+
+  localModels: {
+    list: (): Promise<LocalModelStatus[]> => invoke("list_local_models"),
+    /** Progress comes as `local_model_download_progress` events. */
+    download: (id: string): Promise<void> => invoke("download_local_model", { id }),
+    delete: (id: string): Promise<void> => invoke("delete_local_model", { id }),
+  },
+
+  archive: {
+    list: (): Promise<RecordingMeta[]> => invoke("list_recordings"),
+    delete: (id: string): Promise<void> => invoke("delete_recording", { id }),
+    /** Omitted options fall back to the current preferences / active mode. */
+    retranscribe: (
+      id: string,
+      options: { provider?: string; language?: string; modeId?: string } = {}
+    ): Promise<TranscriptionResult> =>
+      invoke("retranscribe_recording", {
+        id,
+        provider: options.provider ?? null,
+        language: options.language ?? null,
+        modeId: options.modeId ?? null,
+      }),
+    exportSubtitles: (id: string, format: SubtitleFormat): Promise<string> =>
+      invoke("export_recording_subtitles", { id, format }),
   },
 
   services: {
@@ -126,6 +165,8 @@ export const tauriApi = {
   recording: {
     start: (): Promise<void> => invoke("start_recording"),
     stop: (): Promise<void> => invoke("stop_recording"),
+    pause: (): Promise<void> => invoke("pause_recording"),
+    resume: (): Promise<void> => invoke("resume_recording"),
     cancel: (): Promise<void> => invoke("cancel_transcription"),
   },
 
@@ -40,6 +40,10 @@ export default function FloatingBar() {
   const [errorFlash, setErrorFlash] = useState(false);
   const [errorMessage, setErrorMessage] = useState<string | null>(null);
   const [streamingText, setStreamingText] = useState<string>("");
+  // Live transcript while the key is held (opt-in); the LLM stream and the final result replace it
+  const [partialText, setPartialText] = useState<string>("");
+  // Set by the first `llm_delta` of a dictation (live preview on)
+  const deltaPreviewRef = useRef(false);
   const errorFlashTimerRef = useRef<ReturnType<typeof setTimeout> | null>(null);
   const errorClearTimerRef = useRef<ReturnType<typeof setTimeout> | null>(null);
   const monitoringIntervalRef = useRef<ReturnType<typeof setInterval> | null>(null);
@@ -162,6 +166,8 @@ export default function FloatingBar() {
     const unlistenStarted = listen("recording_started", () => {
       setVoiceState("recording");
       setStreamingText("");
+      setPartialText("");
+      deltaPreviewRef.current = false;
       // Clear any lingering error state from previous recording
       if (errorFlashTimerRef.current) clearTimeout(errorFlashTimerRef.current);
       if (errorClearTimerRef.current) clearTimeout(errorClearTimerRef.current);
@@ -170,11 +176,22 @@ export default function FloatingBar() {
     });
     const unlistenStopped = listen("recording_stopped", () => setVoiceState("processing"));
     const unlistenChunk = listen<string>("llm_chunk", (event) => {
+      // Live preview on: deltas drive the text
+      if (deltaPreviewRef.current) return;
       setStreamingText(event.payload);
     });
+    const unlistenDelta = listen<{ text: string; first: boolean }>("llm_delta", (event) => {
+      deltaPreviewRef.current = true;
+      const { text, first } = event.payload;
+      setStreamingText((prev) => (first ? text : prev + text).trimStart());
+    });
+    const unlistenPartial = listen<string>("partial_transcript", (event) => {
+      setPartialText(event.payload);
+    });
     const unlistenReady = listen<{ pasted?: boolean }>("transcription_ready", (event) => {
       setVoiceState("success");
       setStreamingText("");
+      setPartialText("");
       // Show toast only when no text field was focused (clipboard-only)
       if (!event.payload?.pasted) {
         setClipboardToast(true);
@@ -211,6 +228,7 @@ export default function FloatingBar() {
     });
     const unlistenError = listen<string>("transcription_error", (event) => {
       setStreamingText("");
+      setPartialText("");
       setVoiceState("idle");
       const msg = event.payload;
       console.error("[transcription_error]", msg);
@@ -263,13 +281,16 @@ export default function FloatingBar() {
       run(unlistenStarted);
       run(unlistenStopped);
       run(unlistenChunk);
+      run(unlistenDelta);
+      run(unlistenPartial);
       run(unlistenReady);
       run(unlistenError);
     };
   }, []);
 
   const layoutMode: FloatingLayoutMode = isMenuOpen || isMenuClosing ? "menu" : "pill";
-  const showStreaming = voiceState === "processing" && streamingText.length > 0;
+  const showPartial = (voiceState === "recording" || voiceState === "processing") && partialText.length > 0;
+  const showStreaming = (voiceState === "processing" && streamingText.length > 0) || showPartial;
   const isExpanded = isHovered || isMenuOpen || isMenuClosing || voiceState !== "idle" || !!errorMessage;
   useFloatingWindowBounds(layoutMode, positionReady, centerXRef, windowYRef, clipboardToast || !!errorMessage);
 
@@ -865,7 +886,9 @@ export default function FloatingBar() {
             animation: "fadeIn 200ms ease-out",
           }}
         >
-          {streamingText.length > 60 ? streamingText.slice(0, 60) + "\u2026" : streamingText}
+          {streamingText.length > 0
+            ? streamingText.length > 60 ? streamingText.slice(0, 60) + "\u2026" : streamingText
+            : partialText.length > 60 ? "\u2026" + partialText.slice(-60) : partialText}
         </div>
       )}
 
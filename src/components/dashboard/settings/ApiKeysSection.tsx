@@ -10,6 +10,7 @@ import { LANGUAGES } from "@/types";
 const PROVIDER_LABELS: Record<string, string> = {
   openai: "OpenAI",
   groq: "Groq",
+  anthropic: "Anthropic",
   custom: "Custom",
 };
 
@@ -151,7 +152,14 @@ export function ApiKeysSection({
   const handleLlmProviderChange = useCallback(
     async (e: React.ChangeEvent<HTMLSelectElement>) => {
       const v = e.target.value;
-      const model = v === "groq" ? "llama-3.1-8b-instant" : "gpt-4o-mini";
+      const model =
+        v === "groq"
+          ? "llama-3.1-8b-instant"
+          : v === "anthropic"
+            ? "claude-haiku-4-5"
+            : v === "ollama"
+              ? "llama3.2"
+              : "gpt-4o-mini";
       await updatePreferences({
         llm: { ...preferences?.llm, provider: v, model },
       });
@@ -264,6 +272,8 @@ export function ApiKeysSection({
               >
                 <option value="openai">{strings.settings.models.llmProviderOpenAI}</option>
                 <option value="groq">{strings.settings.models.llmProviderGroq}</option>
+                <option value="anthropic">{strings.settings.models.llmProviderAnthropic}</option>
+                <option value="ollama">{strings.settings.models.llmProviderOllama}</option>
               </select>
             </div>
             <div className="flex-1">
@@ -282,6 +292,17 @@ export function ApiKeysSection({
                     <option value="llama-3.3-70b-versatile">Llama 3.3 70B (best quality)</option>
                     <option value="mixtral-8x7b-32768">Mixtral 8x7B</option>
                   </>
+                ) : llmProvider === "ollama" ? (
+                  <>
+                    <option value="llama3.2">Llama 3.2 3B</option>
+                    <option value="qwen2.5:7b">Qwen 2.5 7B</option>
+                    <option value="mistral">Mistral 7B</option>
+                  </>
+                ) : llmProvider === "anthropic" ? (
+                  <>
+                    <option value="claude-haiku-4-5">Claude Haiku 4.5 (fastest)</option>
+                    <option value="claude-sonnet-4-5">Claude Sonnet 4.5 (best quality)</option>
+                  </>
                 ) : (
                   <>
                     <option value="gpt-4o-mini">GPT-4o Mini</option>
@@ -292,7 +313,9 @@ export function ApiKeysSection({
               </select>
             </div>
           </div>
-          <KeyStatus providerName={llmProvider} apiKeys={apiKeys} onAddKey={scrollToKeys} />
+          {llmProvider !== "ollama" && (
+            <KeyStatus providerName={llmProvider} apiKeys={apiKeys} onAddKey={scrollToKeys} />
+          )}
         </div>
 
         {/* ─── Separator ─── */}
@@ -70,6 +70,24 @@ export function SystemSection({
     [updatePreferences, preferences?.behavior]
   );
 
+  const handleTypeAsItStreams = useCallback(
+    async (checked: boolean) => {
+      await updatePreferences({
+        behavior: { ...preferences?.behavior, insertionMode: checked ? "stream" : "paste" },
+      });
+    },
+    [updatePreferences, preferences?.behavior]
+  );
+
+  const handleStreamPreview = useCallback(
+    async (checked: boolean) => {
+      await updatePreferences({
+        behavior: { ...preferences?.behavior, streamPreview: checked },
+      });
+    },
+    [updatePreferences, preferences?.behavior]
+  );
+
   const handleInstallServices = useCallback(async () => {
     try {
       const list = await api.services.install();
@@ -181,6 +199,28 @@ export function SystemSection({
           />
         </SettingsRow>
 
+        <SettingsRow
+          label={strings.settings.system.typeAsItStreams}
+          description={strings.settings.system.typeAsItStreamsDesc}
+        >
+          <ToggleSwitch
+            checked={preferences?.behavior.insertionMode === "stream"}
+            onChange={handleTypeAsItStreams}
+            aria-label={strings.settings.system.typeAsItStreams}
+          />
+        </SettingsRow>
+
+        <SettingsRow
+          label={strings.settings.system.streamPreview}
+          description={strings.settings.system.streamPreviewDesc}
+        >
+          <ToggleSwitch
+            checked={preferences?.behavior.streamPreview ?? false}
+            onChange={handleStreamPreview}
+            aria-label={strings.settings.system.streamPreview}
+          />
+        </SettingsRow>
+
         {ENABLE_RIGHT_CLICK_SERVICES && (
           <div className="pt-6">
             <div className="border-t border-black/[0.06] dark:border-white/[0.06] pt-4">
@@ -42,6 +42,9 @@ export function useAppState() {
         tokens_input: 0,
         tokens_output: 0,
         words_generated: 0,
+        latency: [],
+        llm_models: [],
+        transcription_models: [],
       });
     } catch {
       await loadUsageStats();
@@ -163,6 +163,11 @@ export const strings = {
       pasteInputAndOutput: "Include original when pasting",
       pasteInputAndOutputDesc:
         "Paste both your original words and the transformed result (format: Original / Result)",
+      typeAsItStreams: "Type as it streams",
+      typeAsItStreamsDesc:
+        "Type the transformed result sentence by sentence while it is generated, instead of pasting it at the end",
+      streamPreview: "Live preview",
+      streamPreviewDesc: "Show the transformed result word by word in the floating bar",
       services: {
         title: "Context menu shortcuts",
         label: "Text mode (context menu)",
@@ -232,6 +237,8 @@ export const strings = {
       textGenerationDesc: "Service that rewrites your dictation using your chosen mode",
       llmProviderOpenAI: "OpenAI (best quality)",
       llmProviderGroq: "Groq (faster, lower cost)",
+      llmProviderAnthropic: "Anthropic (Claude)",
+      llmProviderOllama: "Ollama (local)",
       llmProviderGroqDesc: "Open-source models optimized for speed and cost. Requires a Groq API key.",
       llmProviderOpenAIDesc: "OpenAI GPT models. Best quality for complex transformations.",
     },
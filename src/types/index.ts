@@ -18,6 +18,7 @@ export interface DictionaryEntry {
   pronunciation?: string;
   misspellings: string[];
   created_at: number;
+  last_used_at?: number;
 }
 
 /** Paire (misspelling, correction) détectée par diff transcription/clipboard. */
@@ -62,12 +63,125 @@ export const LANGUAGES: Record<Language, { label: string; flag: string }> = {
   de: { label: "Deutsch", flag: "🇩🇪" },
 };
 
+/** Result of `transcribe_file` (same shape as the `transcription_ready` event payload). */
+export interface TranscriptionResult {
+  output: string;
+  thoughts: string | null;
+  mode: string | null;
+  pasted: boolean;
+  /** ISO 639-1 code of the dictation: detected with auto-detect, else the configured one. */
+  language: string | null;
+  /** Wall time of each pipeline stage, in order, then "total". */
+  timings: StageTiming[];
+}
+
+/** One pipeline stage; network stages name the provider and model that answered. */
+export interface StageTiming {
+  stage: string;
+  ms: number;
+  provider?: string;
+  model?: string;
+}
+
+/** Rolling latency of one stage on one provider/model (last 100 dictations). */
+export interface StageLatency {
+  stage: string;
+  provider: string | null;
+  model: string | null;
+  count: number;
+  p50_ms: number;
+  p95_ms: number;
+  recent_ms: number[];
+}
+
+/** Subtitle export: SRT, WebVTT, or JSON with segment and word timings. */
+export type SubtitleFormat = "srt" | "vtt" | "json";
+
+/** Entry of the opt-in local recording archive. */
+export interface RecordingMeta {
+  id: string;
+  /** Unix timestamp (seconds). */
+  createdAt: number;
+  durationSecs: number;
+  device: string | null;
+  mode: string | null;
+  provider: string;
+  language: string | null;
+  sizeBytes: number;
+}
+
+/** Limits on Whisper `verbose_json` segment statistics (per provider id). */
+export interface HallucinationThresholds {
+  noSpeechProb: number;
+  avgLogprob: number;
+  compressionRatio: number;
+}
+
+/** whisper.cpp model for the "local" transcription provider. */
+export interface LocalModelStatus {
+  id: string;
+  sizeMb: number;
+  downloaded: boolean;
+}
+
+/** LLM calls and tokens of one provider/model. */
+export interface LlmModelUsage {
+  provider: string;
+  model: string;
+  requests: number;
+  tokens_input: number;
+  tokens_output: number;
+  /** Requests whose tokens were estimated locally (the provider reported no usage). */
+  estimated_requests: number;
+}
+
+/** Transcription uploads and audio of one provider/model. */
+export interface TranscriptionModelUsage {
+  provider: string;
+  model: string;
+  requests: number;
+  audio_secs: number;
+}
+
+/** Estimated cost of one provider/model; `usd` is null when no price is known. */
+export interface CostLine {
+  provider: string;
+  model: string;
+  kind: "transcription" | "llm";
+  usd: number | null;
+}
+
+export interface CostBreakdown {
+  lines: CostLine[];
+  total_usd: number;
+}
+
+/** Price override: model is a prefix, "*" for every model of the provider. */
+export interface ModelPrice {
+  provider: string;
+  model: string;
+  inputPerMtok?: number;
+  outputPerMtok?: number;
+  perMinute?: number;
+}
+
 export interface UsageStats {
   transcription_requests: number;
   llm_requests: number;
   tokens_input: number;
   tokens_output: number;
   words_generated: number;
+  latency: StageLatency[];
+  llm_models: LlmModelUsage[];
+  transcription_models: TranscriptionModelUsage[];
+}
+
+/** One hop of a provider fallback chain; omitted fields use the provider's defaults. */
+export interface FallbackHop {
+  provider: string;
+  model?: string | null;
+  timeoutSecs?: number | null;
+  baseUrl?: string | null;
 }
 
 export interface Preferences {
@@ -79,18 +193,22 @@ export interface Preferences {
     firstRunDone?: boolean;
   };
   shortcut: { modifiers: string[]; key: string };
-  recording: { maxDurationMinutes: number; inputDeviceId?: string | null };
-  transcription: { model: string; timeoutSecs: number; language?: string | null; provider?: string };
-  llm: { model: string; temperature: number; maxTokens: number; timeoutSecs: number; provider?: string };
+  recording: { maxDurationMinutes: number; inputDeviceId?: string | null; silenceTimeoutSecs?: number | null; alwaysWarm?: boolean; levelSpectrum?: boolean; archiveEnabled?: boolean; archiveMaxAgeDays?: number | null; archiveMaxCount?: number | null; archiveMaxSizeMb?: number | null };
+  transcription: { model: string; timeoutSecs: number; language?: string | null; provider?: string; uploadFormat?: "wav" | "flac" | "opus"; localModel?: string; hallucinationThresholds?: Record<string, Partial<HallucinationThresholds>>; languageModes?: Record<string, string>; livePartials?: boolean; cursorContext?: boolean; chainSegments?: boolean; fallback?: FallbackHop[] | null };
+  llm: { model: string; temperature: number; maxTokens: number; timeoutSecs: number; provider?: string; fallback?: FallbackHop[] | null };
   behavior: {
     autoCopy: boolean;
     soundOnComplete: boolean;
     systemNotification: boolean;
     autoPasteAfterTransform?: boolean;
     pasteInputAndOutput?: boolean;
+    /** "paste" (whole output at the end) or "stream" (typed sentence by sentence as generated). */
+    insertionMode?: "paste" | "stream";
+    /** Send each token to the floating window as `llm_delta`. */
+    streamPreview?: boolean;
   };
   appearance: { theme: string; barPosition: string; fontSize: string; showLockInWidget?: boolean };
-  advanced: { transcriptionBaseUrl?: string | null; llmBaseUrl?: string | null };
+  advanced: { transcriptionBaseUrl?: string | null; llmBaseUrl?: string | null; pricing?: ModelPrice[] };
 }
 
 export type DeepPartial<T> = {
@@ -129,6 +247,8 @@ export interface ShortcutConfig {
       | "stopRecording"
       | "pushToTalk"
       | "toggleRecording"
+      | "pauseRecording"
+      | "resumeRecording"
       | "openDashboard"
       | "toggleFloatingBar"
       | "pasteLastOutput";